@@ -0,0 +1,547 @@
+// Frontend bindings for the printer plugin. The interfaces mirror the Rust structs field for
+// field (snake_case, as serde sends them); command arguments are camelCase, as Tauri expects.
+
+import { invoke } from '@tauri-apps/api/core'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
+
+export type ErrorCode =
+  | 'InvalidContent'
+  | 'InvalidSettings'
+  | 'PortNotFound'
+  | 'Timeout'
+  | 'PaperOut'
+  | 'CoverOpen'
+  | 'AccessDenied'
+  | 'SpoolerError'
+  | 'ConnectionFailed'
+  | 'DuplicateJob'
+  | 'Database'
+  | 'Unknown'
+
+export interface PrintError {
+  code: ErrorCode
+  // Technical detail for logs and support
+  message: string
+  // What the cashier sees, in the configured language
+  user_message: string
+}
+
+export type DocumentType = 'kot' | 'bill' | 'refund' | 'report' | 'invoice' | 'expo' | 'pickup'
+export type KotTemplate = 'standard' | 'kitchen' | 'waiter'
+export type Transport = 'usb' | 'network' | 'ipp' | 'lpr' | 'virtual' | 'driver'
+export type VendorProfile = 'generic' | 'epson' | 'star' | 'star_line' | 'xprinter' | 'rongta'
+export type FlowControl = 'none' | 'hardware' | 'software'
+export type Parity = 'none' | 'odd' | 'even'
+export type PrinterType = 'auto' | 'thermal' | 'office'
+export type PaperStatus = 'ok' | 'near_end' | 'out'
+
+export interface SerialSettings {
+  flow_control: FlowControl
+  data_bits: number
+  parity: Parity
+  stop_bits: number
+  chunk_size: number
+  chunk_delay_ms: number
+}
+
+export type Destination =
+  | { transport: 'usb'; port: string; baud_rate: number; serial?: SerialSettings; printer_type?: PrinterType }
+  | { transport: 'network'; address: string }
+  | { transport: 'ipp'; url: string }
+  | { transport: 'lpr'; address: string }
+  | { transport: 'virtual'; directory: string; columns?: number }
+  | { transport: 'driver'; driver: string; address: string }
+
+export interface PrinterProfile {
+  // null until the profile has been saved
+  id: number | null
+  name: string
+  transport: Transport
+  address: string
+  // Driver transport only: the name the host app registered it under
+  driver?: string | null
+  baud_rate?: number
+  paper_width?: number
+  code_page?: number
+  vendor_profile?: VendorProfile
+  flow_control?: FlowControl
+  data_bits?: number
+  parity?: Parity
+  stop_bits?: number
+  chunk_size?: number
+  chunk_delay_ms?: number
+  printer_type?: PrinterType
+  timeout_secs?: number
+  // ESC 3 line spacing in dots; null keeps the printer's own
+  line_spacing?: number | null
+  // Replaces the document's blank lines before each cut; null leaves them
+  feed_lines_before_cut?: number | null
+  feed_lines_after_cut?: number
+  // Raw bytes sent after every ESC @, e.g. [0x1c, 0x2e] to turn Chinese mode off; at most 64
+  init_sequence?: number[]
+  snmp_community?: string | null
+  outlet_id?: string | null
+  // Read-only, kept up to date by failed jobs and the status monitor
+  paper_status?: PaperStatus
+  paper_status_at?: string | null
+}
+
+export interface PrinterStatus {
+  online: boolean
+  cover_open: boolean
+  paper_out: boolean
+  paper_near_end: boolean
+  cutter_error: boolean
+  unrecoverable_error: boolean
+  drawer_open: boolean
+}
+
+export interface ChannelResult {
+  success: boolean
+  printed_on_fallback: boolean
+  error: PrintError | null
+  // The bytes that would have been sent, when dry run is on
+  dry_run_payload?: number[]
+  // Not printed yet; the printer was unreachable and the job waits in the offline queue
+  queued: boolean
+}
+
+export interface PrinterPrintResult {
+  printer_id: number
+  name: string
+  result: ChannelResult
+}
+
+export interface StationKot {
+  station: string
+  destination: Destination
+  // Headed with the station and its own ticket number
+  content: string
+}
+
+export interface OrderPrintResult {
+  order_id: number
+  // Empty when the document couldn't be rendered or was refused before sending
+  printers: PrinterPrintResult[]
+  error: PrintError | null
+}
+
+export interface JobEvent {
+  order_id: number
+  destination: Destination
+  success: boolean
+  printed_on_fallback: boolean
+  dry_run: boolean
+  queued: boolean
+  error: PrintError | null
+}
+
+export type StatusTransition =
+  | 'online'
+  | 'offline'
+  | 'cover_opened'
+  | 'cover_closed'
+  | 'paper_near_end'
+  | 'paper_out'
+  | 'paper_restored'
+  | 'error'
+  | 'error_cleared'
+  | 'drawer_opened'
+  | 'drawer_closed'
+
+export interface StatusEvent {
+  printer_id: number
+  name: string
+  // null when the printer didn't answer at all
+  status: PrinterStatus | null
+  transitions: StatusTransition[]
+}
+
+export interface PaperEvent {
+  printer_id: number | null
+  name: string
+  paper_status: PaperStatus
+}
+
+export interface QueuedJob {
+  id: number
+  order_id: number | null
+  destination: Destination
+  reason: string
+  size: number
+  created_at: string
+}
+
+export interface FlushSummary {
+  printed: number
+  // Still queued because their printer is still unreachable
+  remaining: number
+  // Orders whose queued tickets went out, in the order they printed
+  order_ids: number[]
+}
+
+export interface QueueFlushedEvent extends FlushSummary {
+  printer_id: number | null
+  name: string
+}
+
+export interface UnprintedOrder {
+  order_id: number
+  document_type: DocumentType
+  // null when the app stopped mid-job rather than the printers failing it
+  error: string | null
+  updated_at: string
+}
+
+export type MetricsRange = 'last_hour' | 'today' | 'last_24_hours' | 'last_7_days' | 'all'
+
+export interface PrinterMetrics {
+  destination: Destination
+  // null for destinations without a saved profile; name is then the destination key
+  printer_id: number | null
+  name: string
+  jobs: number
+  successes: number
+  // Accepted into the offline queue rather than printed
+  queued: number
+  failures: Partial<Record<ErrorCode, number>>
+  p50_ms: number | null
+  p95_ms: number | null
+  // Jobs for an order document this printer had already been sent
+  retries: number
+}
+
+export interface PrinterHealth {
+  // null for destinations without a saved profile, such as a station route's own printer
+  printer_id: number | null
+  name: string
+  destination_key: string
+  // From the status monitor's last poll; null when it hasn't polled this printer
+  online: boolean | null
+  queued_jobs: number
+  breaker_open: boolean
+  consecutive_failures: number
+  last_success_at: string | null
+}
+
+export interface Health {
+  database_ok: boolean
+  database_error: string | null
+  queued_jobs: number
+  printers: PrinterHealth[]
+}
+
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace'
+
+export interface LogEntry {
+  timestamp: string
+  level: string
+  target: string
+  message: string
+}
+
+export interface Webhook {
+  url: string
+  // Signs each body as X-Printer-Signature: sha256=<HMAC>; empty sends it unsigned. Settings reads
+  // show a stored secret as '<redacted>', and saving that back keeps it
+  secret?: string
+}
+
+export interface ReceiptEmail {
+  id: number
+  order_id: number
+  address: string
+  sent: boolean
+  error: string | null
+  created_at: string
+}
+
+export type DrawerPin = 'pin2' | 'pin5'
+
+export interface DrawerAssignment {
+  printer_id: number
+  pin?: DrawerPin
+}
+
+export interface FiscalBill {
+  order_id: number
+  bill_number: string
+  // SHA-256 of the bytes the original was printed from
+  payload_hash: string
+  printed_at: string
+  copies: number
+}
+
+export interface ReceiptHeader {
+  name: string
+  // One printed line per line
+  address: string
+  phone: string
+  pan: string
+  // Thermal documents that print it
+  document_types: DocumentType[]
+}
+
+export interface ClockCheck {
+  // 'ntp://host' or the URL whose Date header was used
+  source: string
+  // Reference time minus ours; positive when this clock is behind
+  drift_secs: number
+  threshold_secs: number
+  drifted: boolean
+  checked_at: string
+}
+
+export interface PrintJob {
+  orderId: number
+  // Text with ESC/POS markup, or raw bytes sent as they are. Text that would open the cash drawer is
+  // refused; bytes need the allow-print-raw permission
+  content: string | Uint8Array | number[]
+  // Every configured printer when left out
+  printerIds?: number[]
+  documentType?: DocumentType
+  // Repeats of the same key inside the idempotency window are rejected as DuplicateJob
+  idempotencyKey?: string
+  timeoutSecs?: number
+}
+
+export const JOB_EVENT = 'printer://job'
+export const STATUS_EVENT = 'printer://status'
+export const PAPER_EVENT = 'printer://paper'
+export const QUEUE_EVENT = 'printer://queue'
+export const CLOCK_EVENT = 'printer://clock'
+
+export async function print(job: PrintJob): Promise<PrinterPrintResult[]> {
+  const args = {
+    orderId: job.orderId,
+    printerIds: job.printerIds ?? null,
+    documentType: job.documentType ?? null,
+    idempotencyKey: job.idempotencyKey ?? null,
+    timeoutSecs: job.timeoutSecs ?? null,
+  }
+  if (typeof job.content === 'string') {
+    return await invoke('plugin:printer|print_to_printers', { ...args, content: job.content })
+  }
+  return await invoke('plugin:printer|print_bytes_to_printers', { ...args, bytes: Array.from(job.content) })
+}
+
+// The order's KOT split by station routing, for sending each ticket yourself. With an expo printer
+// set, the last entry is the expo summary under station 'expo'.
+export async function generateStationKots(
+  orderId: number,
+  isReprint: boolean,
+  username: string,
+  onlyNew?: boolean
+): Promise<StationKot[]> {
+  return await invoke('plugin:printer|generate_station_kots', { orderId, isReprint, username, onlyNew: onlyNew ?? null })
+}
+
+// After printing a KOT yourself, so the next one with onlyNew carries only the added items.
+export async function markKotPrinted(orderId: number): Promise<void> {
+  await invoke('plugin:printer|mark_kot_printed', { orderId })
+}
+
+// One KOT for several orders, items grouped with each token's share under them.
+export async function generateConsolidatedKot(orderIds: number[], username: string): Promise<string> {
+  return await invoke('plugin:printer|generate_consolidated_kot', { orderIds, username })
+}
+
+export async function printConsolidatedKot(
+  orderIds: number[],
+  username: string,
+  printerIds?: number[]
+): Promise<PrinterPrintResult[]> {
+  return await invoke('plugin:printer|print_consolidated_kot', { orderIds, username, printerIds: printerIds ?? null })
+}
+
+// Resends what was last archived for each order's document; KOTs that weren't kept are generated
+// again, naming `username`.
+export async function printOrders(
+  orderIds: number[],
+  documentType: DocumentType,
+  printerIds?: number[],
+  username?: string
+): Promise<OrderPrintResult[]> {
+  return await invoke('plugin:printer|print_orders', {
+    orderIds,
+    documentType,
+    printerIds: printerIds ?? null,
+    username: username ?? null
+  })
+}
+
+// Big token, "ORDER READY" and the time, for the customer waiting at the counter.
+export async function printPickupSlip(orderId: number, printerIds?: number[]): Promise<PrinterPrintResult[]> {
+  return await invoke('plugin:printer|print_pickup_slip', { orderId, printerIds: printerIds ?? null })
+}
+
+export async function listPrinters(): Promise<PrinterProfile[]> {
+  return await invoke('plugin:printer|list_printers')
+}
+
+// Returns the profile as stored, with its id filled in.
+export async function savePrinter(profile: PrinterProfile): Promise<PrinterProfile> {
+  return await invoke('plugin:printer|save_printer', { profile })
+}
+
+export async function deletePrinter(id: number): Promise<void> {
+  await invoke('plugin:printer|delete_printer', { id })
+}
+
+// Pinged when a job fails for good or a printer goes offline; replaces the saved list.
+export async function setWebhooks(webhooks: Webhook[]): Promise<void> {
+  await invoke('plugin:printer|set_webhooks', { webhooks })
+}
+
+// Business details at the top of receipts; shared with the PDF letterhead.
+export async function getReceiptHeader(): Promise<ReceiptHeader> {
+  return await invoke('plugin:printer|get_receipt_header')
+}
+
+export async function setReceiptHeader(header: ReceiptHeader): Promise<void> {
+  await invoke('plugin:printer|set_receipt_header', { header })
+}
+
+// One layout per KOT copy in print order, e.g. ['kitchen', 'waiter'] for a kitchen copy without the total
+// and a waiter copy with it; extra copies print as 'standard'.
+export async function setKotCopyTemplates(templates: KotTemplate[]): Promise<void> {
+  await invoke('plugin:printer|set_kot_copy_templates', { templates })
+}
+
+// Every print to stations also sends the whole order, one line per item, to this printer; null stops it.
+export async function setExpoPrinter(printerId: number | null): Promise<void> {
+  await invoke('plugin:printer|set_expo_printer', { printerId })
+}
+
+// Plain text, printed by the KOT, receipt and invoice generators and wherever content has {{footer}}; null removes it.
+export async function setDocumentFooter(documentType: DocumentType, text: string | null): Promise<void> {
+  await invoke('plugin:printer|set_document_footer', { documentType, text })
+}
+
+// Transports the host app registered on the plugin Builder.
+export async function listPrinterDrivers(): Promise<string[]> {
+  return await invoke('plugin:printer|list_printer_drivers')
+}
+
+export async function getPrinterStatus(printerId: number): Promise<PrinterStatus> {
+  return await invoke('plugin:printer|get_printer_status', { printerId })
+}
+
+// Reprints on the job's original printer unless another one is given.
+export async function reprintJob(jobId: number, printerId?: number): Promise<ChannelResult> {
+  return await invoke('plugin:printer|reprint_job', { jobId, printerId: printerId ?? null })
+}
+
+// Takes the next number in `series` ("bill" by default) for a bill the app renders itself.
+export async function nextBillNumber(series?: string): Promise<string> {
+  return await invoke('plugin:printer|next_bill_number', { series: series ?? null })
+}
+
+// The order's bill number, the same one its receipt and invoice show; assigned on first use.
+export async function getBillNumber(orderId: number, series?: string): Promise<string> {
+  return await invoke('plugin:printer|get_bill_number', { orderId, series: series ?? null })
+}
+
+// The audit record of the bill's original print; null until it has printed in fiscal mode.
+export async function getFiscalBill(orderId: number): Promise<FiscalBill | null> {
+  return await invoke('plugin:printer|get_fiscal_bill', { orderId })
+}
+
+// The startup time check; null when it is off, failed, or hasn't finished.
+export async function getClockCheck(): Promise<ClockCheck | null> {
+  return await invoke('plugin:printer|get_clock_check')
+}
+
+// Emails the order's PDF receipt; rejects with the send error, which is recorded either way.
+export async function emailReceipt(orderId: number, address: string): Promise<ReceiptEmail> {
+  return await invoke('plugin:printer|email_receipt', { orderId, address })
+}
+
+export async function listReceiptEmails(orderId: number): Promise<ReceiptEmail[]> {
+  return await invoke('plugin:printer|list_receipt_emails', { orderId })
+}
+
+// Order documents that didn't reach every printer in the last `withinHours` (12 unless configured).
+export async function getUnprintedOrders(withinHours?: number): Promise<UnprintedOrder[]> {
+  return await invoke('plugin:printer|get_unprinted_orders', { withinHours: withinHours ?? null })
+}
+
+// Per-printer job counts, failures and latency; today's unless a range is given.
+export async function getPrinterMetrics(range?: MetricsRange): Promise<PrinterMetrics[]> {
+  return await invoke('plugin:printer|get_printer_metrics', { range: range ?? null })
+}
+
+// Database, queue, breaker and last-print state in one call, for monitoring agents.
+export async function health(): Promise<Health> {
+  return await invoke('plugin:printer|health')
+}
+
+// The newest `lines` entries (200 by default) from the plugin's log at `level` or worse, oldest first.
+export async function getRecentLogs(lines?: number, level?: LogLevel): Promise<LogEntry[]> {
+  return await invoke('plugin:printer|get_recent_logs', { lines: lines ?? null, level: level ?? null })
+}
+
+// Writes a support zip (logs, redacted printer profiles, queue, recent failed payloads) to `path`.
+export async function exportDiagnostics(path: string): Promise<string> {
+  return await invoke('plugin:printer|export_diagnostics', { path })
+}
+
+// Opens `username`'s assigned drawer, or the drawer on `printerId` at `pin` (pin 2 by default).
+export async function openCashDrawer(options: { printerId?: number; pin?: DrawerPin; username?: string }): Promise<void> {
+  await invoke('plugin:printer|open_cash_drawer', {
+    printerId: options.printerId ?? null,
+    pin: options.pin ?? null,
+    username: options.username ?? null
+  })
+}
+
+// `null` removes the cashier's drawer.
+export async function setDrawerAssignment(username: string, assignment: DrawerAssignment | null): Promise<void> {
+  await invoke('plugin:printer|set_drawer_assignment', { username, assignment })
+}
+
+// Customer display: the item just rung up and the running total.
+export async function displayItem(name: string, quantity: number, price: number, total: number): Promise<void> {
+  await invoke('plugin:printer|display_item', { name, quantity, price, total })
+}
+
+// `message` overrides the configured thank-you line.
+export async function displayThankYou(message?: string): Promise<void> {
+  await invoke('plugin:printer|display_thank_you', { message: message ?? null })
+}
+
+export async function clearDisplay(): Promise<void> {
+  await invoke('plugin:printer|clear_display')
+}
+
+// Jobs waiting for an unreachable printer, oldest first; `destinationKey` narrows it to one printer.
+export async function listPrintQueue(destinationKey?: string): Promise<QueuedJob[]> {
+  return await invoke('plugin:printer|list_print_queue', { destinationKey: destinationKey ?? null })
+}
+
+export async function flushPrintQueue(destinationKey?: string): Promise<FlushSummary> {
+  return await invoke('plugin:printer|flush_print_queue', { destinationKey: destinationKey ?? null })
+}
+
+// One event per destination a job was sent to, as soon as its outcome is known.
+export async function onJobStatus(callback: (event: JobEvent) => void): Promise<UnlistenFn> {
+  return await listen<JobEvent>(JOB_EVENT, (event) => callback(event.payload))
+}
+
+export async function onPrinterStatus(callback: (event: StatusEvent) => void): Promise<UnlistenFn> {
+  return await listen<StatusEvent>(STATUS_EVENT, (event) => callback(event.payload))
+}
+
+export async function onPaperStatus(callback: (event: PaperEvent) => void): Promise<UnlistenFn> {
+  return await listen<PaperEvent>(PAPER_EVENT, (event) => callback(event.payload))
+}
+
+// A printer came back online and its queued jobs were sent.
+export async function onQueueFlushed(callback: (event: QueueFlushedEvent) => void): Promise<UnlistenFn> {
+  return await listen<QueueFlushedEvent>(QUEUE_EVENT, (event) => callback(event.payload))
+}
+
+// The startup time check found the system clock off by more than the threshold.
+export async function onClockDrift(callback: (event: ClockCheck) => void): Promise<UnlistenFn> {
+  return await listen<ClockCheck>(CLOCK_EVENT, (event) => callback(event.payload))
+}
@@ -1,99 +1,1034 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::io::AsyncWriteExt;
-use serialport;
 use std::process::Command;
 use std::env;
-use winapi::um::winspool;
-use std::ffi::CString;
-use std::ptr;
 use crate::db::{DbState, PrinterSettings, Error};
-use chrono::{Local, Utc};
-use rusqlite::{params, Connection};
+use chrono::Utc;
+use rusqlite::types::ToSqlOutput;
+use rusqlite::{params, Connection, ToSql};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Write; 
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::OwnedMutexGuard;
+use tauri::{AppHandle, Runtime};
+
+// The plugin logs through these rather than the `log` macros so every record also lands in its
+// own rotating file; see logging.rs. Declared ahead of the modules so they can all use them.
+macro_rules! error {
+    ($($arg:tt)+) => { $crate::printer::logging::record(log::Level::Error, module_path!(), format_args!($($arg)+)) };
+}
+macro_rules! warn {
+    ($($arg:tt)+) => { $crate::printer::logging::record(log::Level::Warn, module_path!(), format_args!($($arg)+)) };
+}
+macro_rules! info {
+    ($($arg:tt)+) => { $crate::printer::logging::record(log::Level::Info, module_path!(), format_args!($($arg)+)) };
+}
+macro_rules! debug {
+    ($($arg:tt)+) => { $crate::printer::logging::record(log::Level::Debug, module_path!(), format_args!($($arg)+)) };
+}
+
+mod addons;
+mod alerts;
+pub mod archive;
+mod batch;
+mod breaker;
+mod clock;
+mod consolidated;
+pub mod config;
+pub mod database;
+pub mod devices;
+mod diagnostics;
+pub mod display;
+pub mod drawer;
+mod driver;
+pub mod email;
+mod emulator;
+mod error;
+pub mod escpos;
+mod expo;
+mod exporter;
+pub mod fiscal;
+mod gdi;
+pub mod health;
+mod idempotency;
+pub mod invoice;
+mod ipp;
+mod layout;
+pub mod letterhead;
+mod logging;
+mod lpr;
+#[cfg(test)]
+mod memory;
+mod messages;
+pub mod metrics;
+pub mod migrations;
+pub mod monitor;
+mod pdf;
+mod pickup;
+mod plugin;
+mod pool;
+pub mod preview;
+pub mod probe;
+pub mod profile;
+mod queue;
+mod raster;
+pub mod receipt;
+mod recovery;
+pub mod serial;
+mod secrets;
+pub mod sequences;
+mod shutdown;
+mod sms;
+pub mod snippets;
+mod snmp;
+pub mod spooler;
+pub mod status;
+pub mod test_page;
+#[cfg(test)]
+mod tests;
+pub mod tspl;
+pub mod validation;
+pub mod vendor;
+mod webhooks;
+#[cfg(windows)]
+mod win32;
+
+pub use config::{EmailSettings, EmailTransport, KotTemplate, SmsGateway, StationRoute, Webhook};
+pub use driver::PrinterDriver;
+pub use error::{ErrorCode, PrintError};
+pub use gdi::PrinterType;
+pub use messages::Language;
+pub use plugin::{init, Builder, PluginConfig};
+pub use profile::{PaperStatus, PrinterProfile, Transport};
+pub use queue::{FlushSummary, QueuedJob};
+pub use recovery::UnprintedOrder;
+pub use serial::{FlowControl, Parity, SerialSettings};
+pub use status::{DrawerStatus, PrinterStatus};
+pub use vendor::{DrawerPin, VendorProfile};
+
+use addons::PrintedItems;
 
 
 const PRINT_TIMEOUT: Duration = Duration::from_secs(10);
+// Set once by the plugin Builder; PRINT_TIMEOUT otherwise
+static DEFAULT_TIMEOUT: OnceLock<Duration> = OnceLock::new();
 const USB_WRITE_DELAY: Duration = Duration::from_millis(100);
 
+// Plain ESC/POS; layout::apply swaps bold and cut for each printer's vendor sequences
+const INIT: &str = "\x1B@";
+const BOLD_ON: &str = "\x1B\x45\x01";
+const BOLD_OFF: &str = "\x1B\x45\x00";
+const CUT_PAPER: &str = "\x1D\x56\x41\x00";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentType {
+    #[default]
+    Kot,
+    Bill,
+    Refund,
+    Report,
+    // A4 tax invoice spooled to an office printer
+    Invoice,
+    // One-line-per-item summary for the expeditor
+    Expo,
+    // Order-ready slip for the pack customer at the counter
+    Pickup,
+}
+
+impl DocumentType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DocumentType::Kot => "kot",
+            DocumentType::Bill => "bill",
+            DocumentType::Refund => "refund",
+            DocumentType::Report => "report",
+            DocumentType::Invoice => "invoice",
+            DocumentType::Expo => "expo",
+            DocumentType::Pickup => "pickup",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "bill" => DocumentType::Bill,
+            "refund" => DocumentType::Refund,
+            "report" => DocumentType::Report,
+            "invoice" => DocumentType::Invoice,
+            "expo" => DocumentType::Expo,
+            "pickup" => DocumentType::Pickup,
+            _ => DocumentType::Kot,
+        }
+    }
+}
+
+impl ToSql for DocumentType {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "transport", rename_all = "snake_case")]
+pub enum Destination {
+    Usb {
+        port: String,
+        baud_rate: u32,
+        #[serde(default)]
+        serial: SerialSettings,
+        #[serde(default)]
+        printer_type: PrinterType,
+    },
+    Network { address: String },
+    // ipp://host:631/ipp/print, including CUPS queues
+    Ipp { url: String },
+    // host[:port]/queue on an LPD print server
+    Lpr { address: String },
+    // Renders jobs to files in `directory` instead of printing; see emulator.rs
+    Virtual {
+        directory: String,
+        #[serde(default = "default_virtual_columns")]
+        columns: u32,
+    },
+    // A transport registered by the host app; see driver.rs
+    Driver { driver: String, address: String },
+}
+
+fn default_virtual_columns() -> u32 {
+    48
+}
+
+impl Destination {
+    pub fn key(&self) -> String {
+        match self {
+            Destination::Usb { port, .. } => format!("usb:{}", port),
+            Destination::Network { address } => format!("network:{}", address),
+            Destination::Ipp { url } => format!("ipp:{}", url),
+            Destination::Lpr { address } => format!("lpr:{}", address),
+            Destination::Virtual { directory, .. } => format!("virtual:{}", directory),
+            Destination::Driver { driver, address } => format!("driver:{}:{}", driver, address),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeliveredTo {
+    Primary,
+    Fallback,
+    // Nothing was sent; see PrinterConfig::dry_run
+    DryRun,
+    // The printer was unreachable; the job waits in the offline queue
+    Queued,
+}
+
+// Whether paper came out, as opposed to the job being accepted for later.
+fn was_printed(outcome: &Result<DeliveredTo, PrintError>) -> bool {
+    matches!(outcome, Ok(delivered_to) if *delivered_to != DeliveredTo::Queued)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelResult {
+    pub success: bool,
+    #[serde(default)]
+    pub printed_on_fallback: bool,
+    pub error: Option<PrintError>,
+    // The bytes that would have been sent, when dry run is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dry_run_payload: Option<Vec<u8>>,
+    // Not printed yet; it goes out when the offline queue is flushed
+    #[serde(default)]
+    pub queued: bool,
+}
+
+impl ChannelResult {
+    fn from_outcome(outcome: &Result<DeliveredTo, PrintError>, payload: &[u8]) -> Self {
+        match outcome {
+            Ok(delivered_to) => ChannelResult {
+                success: *delivered_to != DeliveredTo::Queued,
+                printed_on_fallback: *delivered_to == DeliveredTo::Fallback,
+                error: None,
+                dry_run_payload: (*delivered_to == DeliveredTo::DryRun).then(|| payload.to_vec()),
+                queued: *delivered_to == DeliveredTo::Queued,
+            },
+            Err(e) => ChannelResult {
+                success: false,
+                printed_on_fallback: false,
+                error: Some(e.clone()),
+                dry_run_payload: None,
+                queued: false,
+            },
+        }
+    }
+}
+
+// `None` means the channel was not configured for this job.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrintResult {
+    pub usb: Option<ChannelResult>,
+    pub network: Option<ChannelResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrinterPrintResult {
+    pub printer_id: i64,
+    pub name: String,
+    pub result: ChannelResult,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationPrintResult {
+    pub station: String,
+    pub result: ChannelResult,
+}
+
+struct StationFilter<'a> {
+    routes: &'a [StationRoute],
+    route: &'a StationRoute,
+    // The station's own ticket number for the order
+    ticket: Option<String>,
+}
+
+impl StationFilter<'_> {
+    fn accepts(&self, category: &str) -> bool {
+        if self.route.categories.is_empty() {
+            !self.routes.iter().any(|r| r.categories.iter().any(|c| c == category))
+        } else {
+            self.route.categories.iter().any(|c| c == category)
+        }
+    }
+}
+
+// One lock per physical destination so concurrent jobs never interleave bytes on the same printer.
+fn destination_locks() -> &'static Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>> {
+    static LOCKS: OnceLock<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> = OnceLock::new();
+    LOCKS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+async fn lock_destination(key: String) -> OwnedMutexGuard<()> {
+    let lock = {
+        let mut locks = destination_locks().lock().unwrap_or_else(|e| e.into_inner());
+        // A lock only the map holds has no job printing or waiting on it, so deleted and renamed
+        // printers don't pile up; the map never outgrows the printers currently in use
+        locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+        locks.entry(key).or_default().clone()
+    };
+    lock.lock_owned().await
+}
+
+// Call once when the app's database is opened.
+pub fn init_printer_tables(conn: &Connection) -> Result<(), PrintError> {
+    migrations::run(conn)?;
+    config::load(conn)?;
+    profile::load_init_sequences(conn)?;
+    recovery::scan(conn)
+}
+
+struct PreparedJob {
+    claim: idempotency::Claim,
+    payload: Vec<u8>,
+}
+
+// Shared front half of the print commands: content check, snippets, duplicate suppression and copies.
+fn prepare_job(
+    order_id: i64,
+    content: &[u8],
+    document_type: Option<DocumentType>,
+    idempotency_key: Option<String>,
+) -> Result<PreparedJob, PrintError> {
+    if content.is_empty() {
+        error!("Print content cannot be empty");
+        return Err(PrintError::new(ErrorCode::InvalidContent, "Print content cannot be empty"));
+    }
+    let expanded = snippets::expand(content, document_type)?;
+
+    let config = config::current();
+    let key = idempotency_key.unwrap_or_else(|| idempotency::derive_key(order_id, content));
+    let Some(claim) = idempotency::claim(&key, Duration::from_secs(config.idempotency_window_secs)) else {
+        warn!("Ignoring duplicate print job {} for order {}", key, order_id);
+        return Err(PrintError::new(ErrorCode::DuplicateJob, format!("Duplicate print job {}", key)));
+    };
+
+    let payload = repeat_copies(&expanded, config.copies_for(document_type.unwrap_or_default()));
+    Ok(PreparedJob { claim, payload })
+}
+
+fn default_timeout() -> Duration {
+    DEFAULT_TIMEOUT.get().copied().unwrap_or(PRINT_TIMEOUT)
+}
+
+// A per-job override beats the printer's own timeout, which beats the global default.
+fn job_timeout(override_secs: Option<u64>, profile: Option<&PrinterProfile>) -> Duration {
+    match override_secs {
+        Some(secs) if secs > 0 => Duration::from_secs(secs),
+        _ => profile.map_or_else(default_timeout, PrinterProfile::print_timeout),
+    }
+}
+
+// The outcome along with how long it took to get it, for the printer metrics.
+async fn timed_failover(
+    content: &[u8],
+    destination: &Destination,
+    timeout: Duration,
+    order_id: Option<i64>,
+) -> (Result<DeliveredTo, PrintError>, Duration) {
+    let started = Instant::now();
+    let outcome = print_with_failover(content, destination, timeout, order_id).await;
+    (outcome, started.elapsed())
+}
+
+// Prints each target's payload concurrently; results come back in input order.
+async fn dispatch_all<T: Send + 'static>(
+    order_id: i64,
+    targets: Vec<(T, Vec<u8>, Destination, Duration)>,
+) -> Result<Vec<(T, Vec<u8>, Destination, Result<DeliveredTo, PrintError>, Duration)>, PrintError> {
+    let handles: Vec<_> = targets
+        .into_iter()
+        .map(|(tag, payload, destination, timeout)| {
+            tokio::spawn(async move {
+                let (outcome, elapsed) = timed_failover(&payload, &destination, timeout, Some(order_id)).await;
+                (tag, payload, destination, outcome, elapsed)
+            })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(handle
+            .await
+            .map_err(|e| PrintError::new(ErrorCode::Unknown, format!("Print task failed: {}", e)))?);
+    }
+    Ok(results)
+}
+
+async fn mark_printed(state: &DbState, order_id: i64, destination: &Destination) {
+    let transport = transport_name(destination);
+    let updated = database::run(state, move |conn| {
+        if let Err(e) = set_print_status_internal(conn, order_id, transport, true) {
+            error!("Failed to update {} print status: {}", transport, e);
+        }
+        Ok(())
+    })
+    .await;
+    if let Err(e) = updated {
+        error!("Failed to update print status for order {}: {}", order_id, e);
+    }
+}
+
+async fn print_payload_to_printers<R: Runtime>(
+    app: &AppHandle<R>,
+    order_id: i64,
+    printer_ids: Option<Vec<i64>>,
+    content: &[u8],
+    document_type: Option<DocumentType>,
+    idempotency_key: Option<String>,
+    timeout_secs: Option<u64>,
+    state: &DbState,
+) -> Result<Vec<PrinterPrintResult>, PrintError> {
+    let printer_ids = printer_ids.unwrap_or_else(|| config::current().default_printer_ids);
+    if printer_ids.is_empty() {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "No printers selected and no default printers saved"));
+    }
+
+    let profiles = database::run(state, move |conn| {
+        printer_ids.iter().map(|id| profile::get(conn, *id)).collect::<Result<Vec<_>, _>>()
+    })
+    .await?;
+    send_to_profiles(app, order_id, profiles, content, document_type, idempotency_key, timeout_secs, state).await
+}
+
+// The send half of print_payload_to_printers, for callers that have the profiles loaded already.
+async fn send_to_profiles<R: Runtime>(
+    app: &AppHandle<R>,
+    order_id: i64,
+    profiles: Vec<PrinterProfile>,
+    content: &[u8],
+    document_type: Option<DocumentType>,
+    idempotency_key: Option<String>,
+    timeout_secs: Option<u64>,
+    state: &DbState,
+) -> Result<Vec<PrinterPrintResult>, PrintError> {
+    let mut job = prepare_job(order_id, content, document_type, idempotency_key)?;
+    let fiscal = fiscal::prepare(state, order_id, document_type, &mut job.payload).await?;
+    recovery::started(state, order_id, document_type.unwrap_or_default()).await;
+    // Printers with a backlog take the job behind it; the rest print it now, all at once
+    let mut outcomes = Vec::new();
+    let mut targets = Vec::new();
+    for (index, p) in profiles.into_iter().enumerate() {
+        let payload = layout::apply(&job.payload, &p);
+        let destination = p.destination();
+        match queue::join_backlog(state, Some(order_id), &destination, &payload).await {
+            Some(outcome) => outcomes.push((index, p, payload, destination, outcome, Duration::ZERO)),
+            None => {
+                let timeout = job_timeout(timeout_secs, Some(&p));
+                targets.push(((index, p), payload, destination, timeout));
+            }
+        }
+    }
+    for ((index, profile), payload, destination, outcome, elapsed) in dispatch_all(order_id, targets).await? {
+        let outcome = queue::queue_if_unreachable(state, Some(order_id), &destination, &payload, outcome).await;
+        outcomes.push((index, profile, payload, destination, outcome, elapsed));
+    }
+    outcomes.sort_by_key(|(index, ..)| *index);
+    let outcomes: Vec<_> = outcomes
+        .into_iter()
+        .map(|(_, profile, payload, destination, outcome, elapsed)| (profile, payload, destination, outcome, elapsed))
+        .collect();
+
+    // A queued job counts as accepted; letting the key go would queue it twice on a retry. Any other
+    // way out of here, errors included, gives it back
+    if outcomes.iter().any(|(_, _, _, outcome, _)| outcome.is_ok()) {
+        job.claim.keep();
+    }
+    if let Some(fiscal) = fiscal {
+        let printed = outcomes.iter().find(|(_, _, _, outcome, _)| outcome.is_ok()).map(|(_, payload, ..)| payload.as_slice());
+        fiscal::record(state, fiscal, printed).await;
+    }
+    recovery::finished(state, order_id, document_type.unwrap_or_default(), outcomes.iter().map(|(_, _, _, outcome, _)| outcome)).await;
+
+    let mut results = Vec::with_capacity(outcomes.len());
+    for (profile, payload, destination, outcome, elapsed) in outcomes {
+        alerts::report_job_outcome(app, state, order_id, &destination, &outcome).await;
+        archive::record(state, order_id, document_type.unwrap_or_default(), &destination, content, &payload, &outcome, elapsed).await;
+        match &outcome {
+            Ok(DeliveredTo::Queued) => warn!("Printer {} unreachable; order {} queued", profile.name, order_id),
+            Ok(_) => {
+                info!("Printer {} print successful for order {}", profile.name, order_id);
+                mark_printed(state, order_id, &destination).await;
+            }
+            Err(e) => error!("Printer {} Error for order {}: {}", profile.name, order_id, e),
+        }
+        results.push(PrinterPrintResult {
+            printer_id: profile.id.unwrap_or_default(),
+            name: profile.name,
+            result: ChannelResult::from_outcome(&outcome, &payload),
+        });
+    }
+
+    Ok(results)
+}
+
 #[tauri::command]
-pub async fn print_to_all_printers(
+pub async fn print_to_printers<R: Runtime>(
+    app: AppHandle<R>,
     order_id: i64,
+    printer_ids: Option<Vec<i64>>,
     content: String,
-    printer_settings: PrinterSettings,
+    document_type: Option<DocumentType>,
+    idempotency_key: Option<String>,
+    timeout_secs: Option<u64>,
     state: tauri::State<'_, DbState>,
-) -> Result<String, Error> {
-    if content.is_empty() {
-        log::error!("Print content cannot be empty");
-        return Err(Error::Printer("Print content cannot be empty".into()));
+) -> Result<Vec<PrinterPrintResult>, PrintError> {
+    drawer::refuse_kick(content.as_bytes(), document_type)?;
+    print_payload_to_printers(&app, order_id, printer_ids, content.as_bytes(), document_type, idempotency_key, timeout_secs, &state)
+        .await
+}
+
+// Same as print_to_printers for payloads that aren't text, e.g. raster logos. Taken as they are, so
+// it needs allow-print-raw.
+#[tauri::command]
+pub async fn print_bytes_to_printers<R: Runtime>(
+    app: AppHandle<R>,
+    order_id: i64,
+    printer_ids: Option<Vec<i64>>,
+    bytes: Vec<u8>,
+    document_type: Option<DocumentType>,
+    idempotency_key: Option<String>,
+    timeout_secs: Option<u64>,
+    state: tauri::State<'_, DbState>,
+) -> Result<Vec<PrinterPrintResult>, PrintError> {
+    print_payload_to_printers(&app, order_id, printer_ids, &bytes, document_type, idempotency_key, timeout_secs, &state).await
+}
+
+// Sends pre-built bytes (e.g. vendor configuration sequences) as-is: no copies or duplicate check.
+// They still wait behind the printer's backlog and go through the breaker, fallback and offline queue.
+#[tauri::command]
+pub async fn print_raw(
+    printer_id: i64,
+    bytes: Vec<u8>,
+    timeout_secs: Option<u64>,
+    state: tauri::State<'_, DbState>,
+) -> Result<(), PrintError> {
+    if bytes.is_empty() {
+        return Err(PrintError::new(ErrorCode::InvalidContent, "Print content cannot be empty"));
     }
+    let profile = database::run(&state, move |conn| profile::get(conn, printer_id)).await?;
+
+    let destination = profile.destination();
+    let outcome = match queue::join_backlog(&state, None, &destination, &bytes).await {
+        Some(outcome) => outcome,
+        None => {
+            let (outcome, _) = timed_failover(&bytes, &destination, job_timeout(timeout_secs, Some(&profile)), None).await;
+            queue::queue_if_unreachable(&state, None, &destination, &bytes, outcome).await
+        }
+    };
+    match &outcome {
+        Ok(DeliveredTo::Queued) => warn!("{} unreachable; raw job of {} bytes queued", profile.name, bytes.len()),
+        Ok(_) => info!("Raw job of {} bytes sent to {}", bytes.len(), profile.name),
+        Err(e) => error!("Raw job to {} failed: {}", profile.name, e),
+    }
+    outcome.map(|_| ())
+}
 
-    let errors = validate_printer_settings(&printer_settings);
+async fn print_payload_to_all_printers<R: Runtime>(
+    app: &AppHandle<R>,
+    order_id: i64,
+    content: &[u8],
+    printer_settings: PrinterSettings,
+    document_type: Option<DocumentType>,
+    idempotency_key: Option<String>,
+    timeout_secs: Option<u64>,
+    state: &DbState,
+) -> Result<PrintResult, PrintError> {
+    let errors = settings_errors(&printer_settings);
     if !errors.is_empty() {
         let error_msg = errors.join(" | ");
-        log::error!("Invalid printer settings: {}", error_msg);
-        return Err(Error::Printer(error_msg));
+        error!("Invalid printer settings: {}", error_msg);
+        return Err(PrintError::new(ErrorCode::InvalidSettings, error_msg));
     }
 
-    let mut print_errors = Vec::new();
+    let mut job = prepare_job(order_id, content, document_type, idempotency_key)?;
+    let fiscal = fiscal::prepare(state, order_id, document_type, &mut job.payload).await?;
+    let source = content;
+    let content = job.payload;
+    let timeout = job_timeout(timeout_secs, None);
+    recovery::started(state, order_id, document_type.unwrap_or_default()).await;
 
-    // USB printing
-    if !printer_settings.usb_port.is_empty() {
-        match attempt_usb_print(&content, &printer_settings).await {
-            Ok(_) => {
-                log::info!("USB print successful for order {}", order_id);
-                let conn = state.0.lock().map_err(|e| e.to_string())?;
-                if let Err(e) = set_print_status_internal(&conn, order_id, "usb", true){
-                    log::error!("Failed to update USB print status: {}", e);
+    let usb_destination = (!printer_settings.usb_port.is_empty()).then(|| Destination::Usb {
+        port: printer_settings.usb_port.clone(),
+        baud_rate: printer_settings.baud_rate,
+        // PrinterSettings predates serial line options; saved printer profiles carry them
+        serial: SerialSettings::default(),
+        printer_type: PrinterType::Auto,
+    });
+    let network_destination = (!printer_settings.network_ip.is_empty())
+        .then(|| Destination::Network { address: printer_settings.network_ip.clone() });
+
+    // A saved profile for either address brings its line spacing and feeds
+    let destinations = (usb_destination.clone(), network_destination.clone());
+    let (usb_content, network_content) = database::run(state, move |conn| {
+        let lay_out = |destination: Option<Destination>| -> Result<Vec<u8>, PrintError> {
+            let profile = match destination {
+                Some(destination) => profile::find_by_destination(conn, &destination)?,
+                None => None,
+            };
+            Ok(profile.map_or_else(|| content.clone(), |profile| layout::apply(&content, &profile)))
+        };
+        Ok((lay_out(destinations.0)?, lay_out(destinations.1)?))
+    })
+    .await?;
+
+    // Both destinations run concurrently so a dead network printer can't hold up the USB ticket
+    let usb_job = async {
+        match &usb_destination {
+            Some(destination) => {
+                if let Some(outcome) = queue::join_backlog(state, Some(order_id), destination, &usb_content).await {
+                    return Some((outcome, Duration::ZERO));
                 }
-            },
-            Err(e) => {
-                log::error!("USB Printer Error for order {}: {}", e, order_id);
-                print_errors.push(format!("USB: {}", e));
+                let (outcome, elapsed) = timed_failover(&usb_content, destination, timeout, Some(order_id)).await;
+                Some((queue::queue_if_unreachable(state, Some(order_id), destination, &usb_content, outcome).await, elapsed))
             }
+            None => None,
         }
+    };
+    let network_job = async {
+        match &network_destination {
+            Some(destination) => {
+                if let Some(outcome) = queue::join_backlog(state, Some(order_id), destination, &network_content).await {
+                    return Some((outcome, Duration::ZERO));
+                }
+                let (outcome, elapsed) = timed_failover(&network_content, destination, timeout, Some(order_id)).await;
+                Some((queue::queue_if_unreachable(state, Some(order_id), destination, &network_content, outcome).await, elapsed))
+            }
+            None => None,
+        }
+    };
+    let (usb_sent, network_sent) = tokio::join!(usb_job, network_job);
+    let (usb_result, usb_elapsed) = usb_sent.unzip();
+    let (network_result, network_elapsed) = network_sent.unzip();
+
+    let document_type = document_type.unwrap_or_default();
+    if let (Some(destination), Some(outcome), Some(elapsed)) = (&usb_destination, &usb_result, usb_elapsed) {
+        alerts::report_job_outcome(app, state, order_id, destination, outcome).await;
+        archive::record(state, order_id, document_type, destination, source, &usb_content, outcome, elapsed).await;
+    }
+    if let (Some(destination), Some(outcome), Some(elapsed)) = (&network_destination, &network_result, network_elapsed) {
+        alerts::report_job_outcome(app, state, order_id, destination, outcome).await;
+        archive::record(state, order_id, document_type, destination, source, &network_content, outcome, elapsed).await;
+    }
+
+    let result = PrintResult {
+        usb: usb_result.as_ref().map(|outcome| ChannelResult::from_outcome(outcome, &usb_content)),
+        network: network_result.as_ref().map(|outcome| ChannelResult::from_outcome(outcome, &network_content)),
+    };
+
+    if matches!(usb_result, Some(Ok(_))) || matches!(network_result, Some(Ok(_))) {
+        job.claim.keep();
+    }
+    if let Some(fiscal) = fiscal {
+        let printed = match (&usb_result, &network_result) {
+            (Some(Ok(_)), _) => Some(usb_content.as_slice()),
+            (_, Some(Ok(_))) => Some(network_content.as_slice()),
+            _ => None,
+        };
+        fiscal::record(state, fiscal, printed).await;
+    }
+    recovery::finished(state, order_id, document_type, usb_result.iter().chain(network_result.iter())).await;
+
+    // USB printing
+    match usb_result {
+        Some(Ok(DeliveredTo::Queued)) => warn!("USB printer unreachable; order {} queued", order_id),
+        Some(Ok(_)) => {
+            info!("USB print successful for order {}", order_id);
+            database::run(state, move |conn| {
+                if let Err(e) = set_print_status_internal(conn, order_id, "usb", true){
+                    error!("Failed to update USB print status: {}", e);
+                }
+                Ok(())
+            })
+            .await?;
+        },
+        Some(Err(e)) => {
+            error!("USB Printer Error for order {}: {}", order_id, e);
+        }
+        None => {}
     }
 
     // Network printing
-    if !printer_settings.network_ip.is_empty() {
-        match attempt_network_print(&content, &printer_settings).await {
-            Ok(_) => {
-                log::info!("Network print successful for order {}", order_id);
-                let conn = state.0.lock().map_err(|e| e.to_string())?;
-                if let Err(e) = set_print_status_internal(&conn, order_id, "network", true){
-                    log::error!("Failed to update Network print status: {}", e);
+    match network_result {
+        Some(Ok(DeliveredTo::Queued)) => warn!("Network printer unreachable; order {} queued", order_id),
+        Some(Ok(_)) => {
+            info!("Network print successful for order {}", order_id);
+            database::run(state, move |conn| {
+                if let Err(e) = set_print_status_internal(conn, order_id, "network", true){
+                    error!("Failed to update Network print status: {}", e);
                 }
+                Ok(())
+            })
+            .await?;
+        }
+        Some(Err(e)) => {
+            error!("Network Printer Error for order {}: {}", order_id, e);
+        }
+        None => {}
+    }
+
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn print_to_all_printers<R: Runtime>(
+    app: AppHandle<R>,
+    order_id: i64,
+    content: String,
+    printer_settings: PrinterSettings,
+    document_type: Option<DocumentType>,
+    idempotency_key: Option<String>,
+    timeout_secs: Option<u64>,
+    state: tauri::State<'_, DbState>,
+) -> Result<PrintResult, PrintError> {
+    drawer::refuse_kick(content.as_bytes(), document_type)?;
+    print_payload_to_all_printers(&app, order_id, content.as_bytes(), printer_settings, document_type, idempotency_key, timeout_secs, &state)
+        .await
+}
+
+#[tauri::command]
+pub async fn print_bytes_to_all_printers<R: Runtime>(
+    app: AppHandle<R>,
+    order_id: i64,
+    bytes: Vec<u8>,
+    printer_settings: PrinterSettings,
+    document_type: Option<DocumentType>,
+    idempotency_key: Option<String>,
+    timeout_secs: Option<u64>,
+    state: tauri::State<'_, DbState>,
+) -> Result<PrintResult, PrintError> {
+    print_payload_to_all_printers(&app, order_id, &bytes, printer_settings, document_type, idempotency_key, timeout_secs, &state).await
+}
+
+// Each station's KOT for the order under its own ticket number, one copy per template; stations with
+// nothing to make are left out.
+fn station_tickets(
+    conn: &Connection,
+    order_id: i64,
+    is_reprint: bool,
+    username: &str,
+    printed: Option<&PrintedItems>,
+    routes: &[StationRoute],
+    templates: &[KotTemplate],
+) -> Result<Vec<(StationRoute, String)>, PrintError> {
+    let item_types: Vec<String> = fetch_order_details(conn, order_id)?.item_data.into_iter().map(|item| item.0).collect();
+    let mut tickets = Vec::new();
+    for route in routes {
+        let mut filter = StationFilter { routes, route, ticket: None };
+        if !item_types.iter().any(|item_type| filter.accepts(item_type)) {
+            continue;
+        }
+        filter.ticket = Some(sequences::number_for_order(conn, &sequences::station_series(&route.station), order_id)?);
+        let mut copies = String::new();
+        for &template in templates {
+            if let Some(content) = build_kot_content(conn, order_id, is_reprint, username, Some(&filter), printed, template)? {
+                copies.push_str(&content);
             }
+        }
+        if !copies.is_empty() {
+            tickets.push((route.clone(), copies));
+        }
+    }
+    Ok(tickets)
+}
+
+#[tauri::command]
+pub async fn print_kot_to_stations<R: Runtime>(
+    app: AppHandle<R>,
+    order_id: i64,
+    is_reprint: bool,
+    username: String,
+    // Pack orders text the customer their token once the ticket is in the kitchen
+    customer_phone: Option<String>,
+    // Only what was added since the last KOT, as an ADD-ON ticket
+    only_new: Option<bool>,
+    // Repeats inside the idempotency window are rejected as DuplicateJob, as for other jobs
+    idempotency_key: Option<String>,
+    // Beats each station printer's own timeout
+    timeout_secs: Option<u64>,
+    state: tauri::State<'_, DbState>,
+) -> Result<Vec<StationPrintResult>, PrintError> {
+    let config = config::current();
+    if config.station_routes.is_empty() {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "No station routes configured"));
+    }
+    let only_new = only_new.unwrap_or(false);
+
+    let (tickets, expo) = database::run(&state, move |conn| {
+        let printed = if only_new { Some(addons::printed_items(conn, order_id)?) } else { None };
+        let expo = match config.expo_printer_id.map(|id| expo::printer(conn, id)).transpose()?.flatten() {
+            Some(printer) => expo::build(conn, order_id, &config.station_routes, printed.as_ref())?.map(|content| (printer, content)),
+            None => None,
+        };
+        let mut tickets = Vec::new();
+        let templates = config.kot_templates();
+        for (route, source) in station_tickets(conn, order_id, is_reprint, &username, printed.as_ref(), &config.station_routes, &templates)? {
+            let source = source.into_bytes();
+            let profile = profile::find_by_destination(conn, &route.destination)?;
+            let content = match &profile {
+                Some(profile) => layout::apply(&source, profile),
+                None => source.clone(),
+            };
+            tickets.push((route, source, content, job_timeout(timeout_secs, profile.as_ref())));
+        }
+        Ok((tickets, expo))
+    })
+    .await?;
+    if only_new && tickets.is_empty() {
+        info!("Nothing added to order {} since its last KOT", order_id);
+        return Ok(Vec::new());
+    }
+
+    let key = idempotency_key.unwrap_or_else(|| {
+        let all: Vec<u8> = tickets.iter().flat_map(|(_, source, ..)| source.iter().copied()).collect();
+        idempotency::derive_key(order_id, &all)
+    });
+    let Some(claim) = idempotency::claim(&key, Duration::from_secs(config::current().idempotency_window_secs)) else {
+        warn!("Ignoring duplicate station KOT {} for order {}", key, order_id);
+        return Err(PrintError::new(ErrorCode::DuplicateJob, format!("Duplicate print job {}", key)));
+    };
+
+    recovery::started(&state, order_id, DocumentType::Kot).await;
+
+    // Stations print concurrently; the per-destination locks still keep shared printers in order, and
+    // a station with a backlog gets the ticket queued behind it
+    let mut handles = Vec::with_capacity(tickets.len());
+    for (route, source, content, timeout) in tickets {
+        let backlogged = queue::join_backlog(&state, Some(order_id), &route.destination, &content).await;
+        handles.push(tokio::spawn(async move {
+            let (outcome, elapsed) = match backlogged {
+                Some(outcome) => (outcome, Duration::ZERO),
+                None => timed_failover(&content, &route.destination, timeout, Some(order_id)).await,
+            };
+            (route, source, content, outcome, elapsed)
+        }));
+    }
+
+    let mut results = Vec::new();
+    let mut outcomes = Vec::new();
+    for handle in handles {
+        let (route, source, content, outcome, elapsed) = handle
+            .await
+            .map_err(|e| PrintError::new(ErrorCode::Unknown, format!("Station print task failed: {}", e)))?;
+        let outcome = queue::queue_if_unreachable(&state, Some(order_id), &route.destination, &content, outcome).await;
+        alerts::report_job_outcome(&app, &state, order_id, &route.destination, &outcome).await;
+        archive::record(&state, order_id, DocumentType::Kot, &route.destination, &source, &content, &outcome, elapsed).await;
+        match &outcome {
+            Ok(DeliveredTo::Queued) => warn!("Station {} unreachable; KOT for order {} queued", route.station, order_id),
+            Ok(_) => {
+                info!("KOT for order {} printed at station {}", order_id, route.station);
+                mark_printed(&state, order_id, &route.destination).await;
+            }
+            Err(e) => error!("Station {} Printer Error for order {}: {}", route.station, order_id, e),
+        }
+        results.push(StationPrintResult { station: route.station, result: ChannelResult::from_outcome(&outcome, &content) });
+        outcomes.push(outcome);
+    }
+    recovery::finished(&state, order_id, DocumentType::Kot, &outcomes).await;
+    // A queued ticket counts as accepted, as in send_to_profiles
+    if outcomes.iter().any(|o| o.is_ok()) {
+        claim.keep();
+    }
+
+    // The expo copy goes through the usual job path; its failure doesn't hold back the ADD-ON snapshot
+    if let Some((profile, content)) = expo {
+        let station = expo::EXPO_STATION.to_string();
+        match send_to_profiles(&app, order_id, vec![profile], content.as_bytes(), Some(DocumentType::Expo), None, timeout_secs, &state).await {
+            Ok(printed) => results.extend(printed.into_iter().map(|p| StationPrintResult { station: station.clone(), result: p.result })),
             Err(e) => {
-                log::error!("Network Printer Error for order {}: {}", e, order_id);
-                print_errors.push(format!("Network: {}", e));
+                error!("Expo ticket for order {} failed: {}", order_id, e);
+                results.push(StationPrintResult { station, result: ChannelResult::from_outcome(&Err(e), content.as_bytes()) });
             }
         }
     }
+    // A station that failed still owes its items, so the next ADD-ON must include them
+    if !is_reprint && outcomes.iter().all(|o| o.is_ok()) {
+        addons::record_printed(&state, order_id).await;
+    }
 
-    if print_errors.is_empty() {
-       Ok("Successfully sent print job(s).".to_string())
-    } else {
-       Err(Error::Printer(print_errors.join(" | ")))
+    if let Some(phone) = customer_phone.filter(|_| !is_reprint && outcomes.iter().any(|o| o.is_ok())) {
+        sms::notify_pack_order(&state, order_id, &phone).await;
     }
+
+    Ok(results)
 }
 
-// 
+// `only_new` gives the ADD-ON ticket (empty when nothing was added); call mark_kot_printed once it is out.
+// `copy` is the copy index whose kot_copy_templates layout to use.
 #[tauri::command]
-pub async fn generate_kot_content_from_db(order_id: i64, is_reprint: bool, username: String, state: tauri::State<'_, DbState>,) -> Result<String, Error> {
-    let conn = state.0.lock().map_err(|e| Error::Lock(e.to_string()))?;
-
-    // ESC/POS Commands
-    const INIT: &str = "\x1B@";
-    const BOLD_ON: &str = "\x1B\x45\x01";
-    const BOLD_OFF: &str = "\x1B\x45\x00";
-    const CUT_PAPER: &str = "\x1D\x56\x41\x00";
-    const LINE_WIDTH: usize = 48;
+pub async fn generate_kot_content_from_db(order_id: i64, is_reprint: bool, username: String, only_new: Option<bool>, copy: Option<usize>, state: tauri::State<'_, DbState>,) -> Result<String, PrintError> {
+    let template = copy.map_or_else(KotTemplate::default, |copy| config::current().kot_template(copy));
+    database::run(&state, move |conn| {
+        let printed = if only_new.unwrap_or(false) { Some(addons::printed_items(conn, order_id)?) } else { None };
+        Ok(build_kot_content(conn, order_id, is_reprint, &username, None, printed.as_ref(), template)?.unwrap_or_default())
+    })
+    .await
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StationKot {
+    pub station: String,
+    pub destination: Destination,
+    pub content: String,
+}
+
+// The per-station split print_kot_to_stations would print, for apps that send the tickets themselves.
+#[tauri::command]
+pub async fn generate_station_kots(
+    order_id: i64,
+    is_reprint: bool,
+    username: String,
+    only_new: Option<bool>,
+    state: tauri::State<'_, DbState>,
+) -> Result<Vec<StationKot>, PrintError> {
+    let config = config::current();
+    if config.station_routes.is_empty() {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "No station routes configured"));
+    }
+    database::run(&state, move |conn| {
+        let routes = &config.station_routes;
+        let printed = if only_new.unwrap_or(false) { Some(addons::printed_items(conn, order_id)?) } else { None };
+        let tickets = station_tickets(conn, order_id, is_reprint, &username, printed.as_ref(), routes, &config.kot_templates())?;
+        let mut kots: Vec<_> = tickets
+            .into_iter()
+            .map(|(route, content)| StationKot { station: route.station, destination: route.destination, content })
+            .collect();
+        if let Some(printer) = config.expo_printer_id.map(|id| expo::printer(conn, id)).transpose()?.flatten() {
+            if let Some(content) = expo::build(conn, order_id, routes, printed.as_ref())? {
+                kots.push(StationKot { station: expo::EXPO_STATION.to_string(), destination: printer.destination(), content });
+            }
+        }
+        Ok(kots)
+    })
+    .await
+}
 
+// The order as the KOT and label generators see it.
+struct OrderDetails {
+    order_number: String,
+    has_table: bool,
+    notes: String,
+    total_amount: f64,
+    discount_amount: f64,
+    // (item type, name, quantity, dine-in JSON, pack JSON)
+    item_data: Vec<(String, String, i64, Option<String>, Option<String>)>,
+}
+
+fn fetch_order_details(conn: &Connection, order_id: i64) -> Result<OrderDetails, Error> {
     // 1. Fetch order details
     ......
 
+    Ok(OrderDetails { order_number, has_table, notes, total_amount, discount_amount, item_data })
+}
+
+// Word-wraps to `width` columns, keeping the note's own line breaks; words longer than a line are split.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines().filter(|p| !p.trim().is_empty()) {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let chars: Vec<char> = word.chars().collect();
+            for chunk in chars.chunks(width.max(1)) {
+                let line_len = line.chars().count();
+                if line_len > 0 && line_len + 1 + chunk.len() > width {
+                    lines.push(std::mem::take(&mut line));
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.extend(chunk);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// Bold and wrapped under a "Notes:" label so allergy notes aren't chopped at the paper edge.
+fn render_notes(notes: &str, width: usize) -> String {
+    const LABEL: &str = "Notes: ";
+    let mut block = String::from(BOLD_ON);
+    for (i, line) in wrap_text(notes, width - LABEL.len()).iter().enumerate() {
+        let lead = if i == 0 { LABEL.to_string() } else { " ".repeat(LABEL.len()) };
+        block.push_str(&format!("{}{}\n", lead, line));
+    }
+    block.push_str(BOLD_OFF);
+    block
+}
+
+// Returns `None` when a station filter leaves nothing to print, or `printed` leaves nothing new.
+// With `printed` the ticket is an ADD-ON carrying only what was added since those quantities went out.
+fn build_kot_content(
+    conn: &Connection,
+    order_id: i64,
+    is_reprint: bool,
+    username: &str,
+    station: Option<&StationFilter>,
+    printed: Option<&PrintedItems>,
+    template: KotTemplate,
+) -> Result<Option<String>, Error> {
+    const LINE_WIDTH: usize = 48;
+    let config = config::current();
+
+    let OrderDetails { order_number, has_table, notes, total_amount, discount_amount, item_data } =
+        fetch_order_details(conn, order_id)?;
+
+    let items: Vec<_> = item_data
+        .iter()
+        .filter(|(item_type, ..)| station.map_or(true, |filter| filter.accepts(item_type)))
+        .map(|item| (item, printed.map_or(0, |printed| addons::already_printed(printed, &item.0, &item.1))))
+        .filter(|((_, _, quantity, ..), already)| printed.is_none() || quantity > already)
+        .collect();
+    if (station.is_some() || printed.is_some()) && items.is_empty() {
+        return Ok(None);
+    }
+
     // Build the content string
     let mut content = String::new();
     content.push_str(INIT);
@@ -101,9 +1036,22 @@ pub async fn generate_kot_content_from_db(order_id: i64, is_reprint: bool, usern
     if is_reprint {
         content.push_str(&format!("{}*** REPRINT ***{}\n", BOLD_ON, BOLD_OFF));
     }
+    if printed.is_some() {
+        content.push_str(&format!("{}*** ADD-ON ***{}\n", BOLD_ON, BOLD_OFF));
+    }
+    match template {
+        KotTemplate::Standard => {}
+        KotTemplate::Kitchen => content.push_str(&format!("{}KITCHEN COPY{}\n", BOLD_ON, BOLD_OFF)),
+        KotTemplate::Waiter => content.push_str(&format!("{}WAITER COPY{}\n", BOLD_ON, BOLD_OFF)),
+    }
+    if let Some(filter) = station {
+        let ticket = filter.ticket.as_ref().map(|ticket| format!(" #{}", ticket)).unwrap_or_default();
+        content.push_str(&format!("{}[{}]{}{}\n", BOLD_ON, filter.route.station.to_uppercase(), ticket, BOLD_OFF));
+    }
 
+    content.push_str(&letterhead::escpos_header(&config, DocumentType::Kot));
     let order_type_text = if has_table { "Table " } else { "[Pack]" };
-    let date_time = Local::now().format("%Y-%m-%d %I:%M:%S %p").to_string();
+    let date_time = clock::now().format("%Y-%m-%d %I:%M:%S %p").to_string();
     let kot_number = order_number.split('-').last().unwrap_or("");
     let header_line = format!(
         "Kot: {}{}{}{}{}{}{}",
@@ -113,11 +1061,24 @@ pub async fn generate_kot_content_from_db(order_id: i64, is_reprint: bool, usern
     );
     content.push_str(&format!("{}{}{}\n", header_line, " ".repeat(6), date_time));
     content.push_str(&("-".repeat(LINE_WIDTH) + "\n"));
-    content.push_str(&format!("Notes: {}\n", notes));
-    content.push_str(&("-".repeat(LINE_WIDTH) + "\n"));
+    if !notes.trim().is_empty() {
+        let rule = if config.kot_notes_boxed { "*" } else { "-" }.repeat(LINE_WIDTH) + "\n";
+        if config.kot_notes_boxed {
+            content.push_str(&rule);
+        }
+        content.push_str(&render_notes(&notes, LINE_WIDTH));
+        content.push_str(&rule);
+    }
 
     // --- Render Items ---
-    for (item_type, name, quantity, dinein_json, pack_json) in &item_data {
+    for (item, already) in items {
+        let (item_type, name, quantity, dinein_json, pack_json) = item;
+        // More of something already made: the kitchen needs the extra, not the whole breakdown again
+        if already > 0 {
+            content.push_str(&format!("{}+{}) {}{}\n", BOLD_ON, quantity - already, name, BOLD_OFF));
+            content.push_str(&format!("  - now {} in total\n", quantity));
+            continue;
+        }
         content.push_str(&format!("{}{}) {}{}\n", BOLD_ON, quantity, name, BOLD_OFF));
 
         match item_type.as_str() {
@@ -168,21 +1129,169 @@ pub async fn generate_kot_content_from_db(order_id: i64, is_reprint: bool, usern
 
     // --- Footer ---
     content.push_str(&("-".repeat(LINE_WIDTH) + "\n"));
-    let estimate_text = if discount_amount > 0.0 {
+    // The kitchen copy leaves the money off
+    let estimate_text = if template == KotTemplate::Kitchen {
+        String::new()
+    } else if discount_amount > 0.0 {
         format!("{} (-{})", total_amount, discount_amount)
     } else {
         format!("{}", total_amount)
     };
     let footer_padding = LINE_WIDTH.saturating_sub(estimate_text.len()).saturating_sub(username.len());
     content.push_str(&format!("{}{}{}\n", estimate_text, " ".repeat(footer_padding), username));
-    content.push_str("Note: This is not a bill. Please contact cash counter for the bill.");
+    if let Some(footer) = letterhead::footer(&config, DocumentType::Kot) {
+        content.push_str(footer);
+    }
     content.push_str("\n\n");
     content.push_str(CUT_PAPER);
 
-    Ok(content)
+    Ok(Some(content))
+}
+
+// One sticker's worth of text for a packed cup or box.
+struct PackLabel {
+    token: String,
+    name: String,
+    flavor: Option<String>,
+    modifiers: Option<String>,
+}
+
+// Only the pack side of each item gets labels; dine-in items go out on plates.
+fn pack_labels(order: &OrderDetails) -> Vec<PackLabel> {
+    let token = order.order_number.split('-').last().unwrap_or("").to_string();
+    let mut labels = Vec::new();
+
+    for (item_type, name, _, _, pack_json) in &order.item_data {
+        let Some(json) = pack_json else {
+            continue;
+        };
+        match item_type.as_str() {
+            "corndog" | "beverage" => {
+                let Ok(data) = serde_json::from_str::<SectionData>(json) else {
+                    continue;
+                };
+                for (flavor_name, flavor_data) in &data.flavors {
+                    let modifier_texts: Vec<String> = flavor_data
+                        .modifier
+                        .iter()
+                        .filter(|(_, count)| **count > 0)
+                        .map(|(key, count)| format!("{}:{}", key.replace("_", " "), count))
+                        .collect();
+                    for _ in 0..flavor_data.total {
+                        labels.push(PackLabel {
+                            token: token.clone(),
+                            name: name.clone(),
+                            flavor: Some(flavor_name.replace("_", " ")),
+                            modifiers: (!modifier_texts.is_empty()).then(|| modifier_texts.join(", ")),
+                        });
+                    }
+                }
+            }
+            _ => {
+                let Ok(data) = serde_json::from_str::<SimpleSectionData>(json) else {
+                    continue;
+                };
+                for _ in 0..data.total {
+                    labels.push(PackLabel { token: token.clone(), name: name.clone(), flavor: None, modifiers: None });
+                }
+            }
+        }
+    }
+    labels
 }
 
-fn validate_printer_settings(settings: &PrinterSettings) -> Vec<String> {
+// TSPL printers get real labels; anything else gets a short ticket per item with a cut between.
+fn render_labels(labels: &[PackLabel], printer: &PrinterProfile) -> Vec<u8> {
+    let config = config::current();
+    let commands = vendor::commands(printer.vendor_profile);
+    let count = labels.len();
+
+    if commands.protocol == vendor::Protocol::Tspl {
+        let mut output = Vec::new();
+        for (i, label) in labels.iter().enumerate() {
+            let mut sticker = tspl::TsplBuilder::new(config.label_width_mm, config.label_height_mm, config.label_gap_mm);
+            sticker
+                .text(16, 16, "4", 1, &format!("#{}", label.token))
+                .text(200, 24, "2", 1, &format!("{}/{}", i + 1, count))
+                .text(16, 72, "3", 1, &label.name);
+            if let Some(flavor) = &label.flavor {
+                sticker.text(16, 112, "2", 1, flavor);
+            }
+            if let Some(modifiers) = &label.modifiers {
+                sticker.text(16, 144, "1", 1, modifiers);
+            }
+            sticker.print(1);
+            output.extend(sticker.build());
+        }
+        return output;
+    }
+
+    let mut page = escpos::EscPosBuilder::for_vendor(commands);
+    for (i, label) in labels.iter().enumerate() {
+        page.align(escpos::Align::Center)
+            .size(2, 2)
+            .line(&format!("#{}", label.token))
+            .size(1, 1)
+            .bold(true)
+            .line(&label.name)
+            .bold(false);
+        if let Some(flavor) = &label.flavor {
+            page.line(flavor);
+        }
+        if let Some(modifiers) = &label.modifiers {
+            page.line(modifiers);
+        }
+        page.line(&format!("{}/{}", i + 1, count)).feed(2).cut();
+    }
+    page.build()
+}
+
+// Prints one sticker per packed cup/box on the configured label printer; returns how many.
+#[tauri::command]
+pub async fn print_pack_labels(order_id: i64, state: tauri::State<'_, DbState>) -> Result<usize, PrintError> {
+    let Some(printer_id) = config::current().label_printer_id else {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "No label printer configured"));
+    };
+    let (printer, labels) = database::run(&state, move |conn| {
+        let printer = profile::get(conn, printer_id)?;
+        Ok((printer, pack_labels(&fetch_order_details(conn, order_id)?)))
+    })
+    .await?;
+    if labels.is_empty() {
+        return Ok(0);
+    }
+
+    print_to_destination(&render_labels(&labels, &printer), &printer.destination(), printer.print_timeout(), None).await?;
+    info!("Printed {} pack labels for order {} on {}", labels.len(), order_id, printer.name);
+    Ok(labels.len())
+}
+
+fn transport_name(destination: &Destination) -> &'static str {
+    match destination {
+        Destination::Usb { .. } => "usb",
+        Destination::Network { .. } | Destination::Ipp { .. } | Destination::Lpr { .. } | Destination::Driver { .. } => {
+            "network"
+        }
+        // Stands in for the LAN receipt printer so QA sees the same status flags
+        Destination::Virtual { .. } => "network",
+    }
+}
+
+// Every copy gets its own cut so the kitchen can tear them apart.
+fn repeat_copies(content: &[u8], copies: u8) -> Vec<u8> {
+    if copies <= 1 {
+        return content.to_vec();
+    }
+
+    let mut copy = content.to_vec();
+    if !copy.ends_with(CUT_PAPER.as_bytes()) {
+        copy.extend_from_slice(b"\n\n");
+        copy.extend_from_slice(CUT_PAPER.as_bytes());
+    }
+    copy.repeat(copies as usize)
+}
+
+fn settings_errors(settings: &PrinterSettings) -> Vec<String> {
     let mut errors = Vec::new();
     
     if settings.usb_port.is_empty() && settings.network_ip.is_empty() {
@@ -196,74 +1305,206 @@ fn validate_printer_settings(settings: &PrinterSettings) -> Vec<String> {
     errors
 }
 
-async fn attempt_usb_print(content: &str, settings: &PrinterSettings) -> Result<(), String> {
+// `order_id` goes with the job if shutdown parks it, so it can still be marked printed.
+async fn print_with_failover(
+    content: &[u8],
+    destination: &Destination,
+    timeout: Duration,
+    order_id: Option<i64>,
+) -> Result<DeliveredTo, PrintError> {
+    let config = config::current();
+    if config.dry_run {
+        info!("Dry run: {} bytes for {} not sent", content.len(), destination.key());
+        return Ok(DeliveredTo::DryRun);
+    }
+    let key = destination.key();
+    let fallback = config.fallbacks.get(&key);
+
+    let primary_error = if breaker::is_open(&key) {
+        PrintError::new(ErrorCode::ConnectionFailed, format!("Circuit breaker open for {}", key))
+    } else {
+        let preflight_error = if config.preflight_status_check {
+            status::preflight_check(destination).await
+        } else {
+            None
+        };
+
+        // A preflight rejection is a human problem (cover, paper), not a flaky link, so it skips the breaker
+        match preflight_error {
+            Some(e) => e,
+            None => match print_to_destination(content, destination, timeout, order_id).await {
+                Ok(_) => {
+                    breaker::record_success(&key);
+                    return Ok(DeliveredTo::Primary);
+                }
+                Err(e) => {
+                    breaker::record_failure(
+                        &key,
+                        config.breaker_failure_threshold,
+                        Duration::from_secs(config.breaker_cooldown_secs),
+                    );
+                    status::diagnose_failure(destination, e).await
+                }
+            },
+        }
+    };
+
+    // A job held for shutdown is parked as it is; the fallback would print it a second time
+    let Some(route) = fallback.filter(|_| !shutdown::is_shutting_down()) else {
+        return Err(primary_error);
+    };
+
+    warn!("{} unavailable ({}), printing on fallback {}", key, primary_error, route.destination.key());
+    let content = with_down_banner(content, route);
+    match print_to_destination(&content, &route.destination, timeout, order_id).await {
+        Ok(_) => Ok(DeliveredTo::Fallback),
+        Err(e) => {
+            error!("Fallback printer {} also failed: {}", route.destination.key(), e);
+            Err(primary_error)
+        }
+    }
+}
+
+// The banner goes after ESC @ since the reset would otherwise discard it.
+fn with_down_banner(content: &[u8], route: &config::FallbackRoute) -> Vec<u8> {
+    let commands = vendor::commands(route.vendor_profile);
+    let text = format!("*** {} PRINTER DOWN ***", route.label.to_uppercase());
+    let banner = [commands.bold(true), text.as_bytes(), commands.bold(false), b"\n"].concat();
+    match content.strip_prefix(INIT.as_bytes()) {
+        Some(rest) => [INIT.as_bytes(), &banner, rest].concat(),
+        None => [banner.as_slice(), content].concat(),
+    }
+}
+
+async fn print_to_destination(
+    content: &[u8],
+    destination: &Destination,
+    timeout: Duration,
+    order_id: Option<i64>,
+) -> Result<(), PrintError> {
+    // Covers the paths that bypass failover: raw jobs, labels, test pages
+    let config = config::current();
+    if config.dry_run {
+        info!("Dry run: {} bytes for {} not sent", content.len(), destination.key());
+        return Ok(());
+    }
+    if shutdown::is_shutting_down() {
+        shutdown::hold(order_id, destination, content);
+        return Err(PrintError::new(ErrorCode::ConnectionFailed, "The app is closing; the job will print when it restarts"));
+    }
+    let _in_flight = shutdown::InFlight::start(order_id, destination, content);
+    // The printer's own setup goes with every job, raw, label and drawer jobs included
+    let prepared;
+    let content = match profile::init_sequence(destination) {
+        Some((vendor, init_sequence)) => {
+            prepared = layout::with_init(content, vendor, &init_sequence);
+            prepared.as_slice()
+        }
+        None => content,
+    };
+    if config.simulation_mode {
+        return emulator::simulate(content, destination, &config, timeout).await;
+    }
+    match destination {
+        Destination::Usb { port, baud_rate, serial, printer_type } => {
+            attempt_usb_print(content, port, *baud_rate, serial, *printer_type, timeout).await
+        }
+        Destination::Network { address } => attempt_network_print(content, address, timeout).await,
+        Destination::Ipp { url } => attempt_ipp_print(content, url, timeout).await,
+        Destination::Lpr { address } => attempt_lpr_print(content, address, timeout).await,
+        Destination::Virtual { directory, columns } => attempt_virtual_print(content, directory, *columns).await,
+        Destination::Driver { driver, address } => {
+            let _guard = lock_destination(destination.key()).await;
+            driver::print(driver, address, content, timeout).await
+        }
+    }
+}
+
+async fn attempt_usb_print(
+    content: &[u8],
+    port: &str,
+    baud_rate: u32,
+    serial: &SerialSettings,
+    printer_type: PrinterType,
+    timeout: Duration,
+) -> Result<(), PrintError> {
+    let _guard = lock_destination(format!("usb:{}", port)).await;
+    let mut failures = Vec::new();
+
+    // Escape codes come out as garbage on office printers, so draw the text instead
+    if gdi::use_text_mode(port, printer_type) {
+        return gdi::print_text(content, port);
+    }
+
     // Anyhow print to usb connected printer 
     // Try Windows RAW printing first
-    match try_raw_usb_print(content, settings).await {
+    match try_raw_usb_print(content, port).await {
         Ok(_) => return Ok(()),
-         Err(e) => log::error!("Raw USB print failed: {}", e),
+        Err(e) => {
+            error!("Raw USB print failed: {}", e);
+            failures.push(e);
+        }
     }
 
     // Fall back to Windows print command
-    match try_windows_print_command(content, &settings.usb_port).await {
+    match try_windows_print_command(content, port).await {
         Ok(_) => return Ok(()),
-        Err(e) => log::error!("Windows print command failed: {}", e),
+        Err(e) => {
+            error!("Windows print command failed: {}", e);
+            failures.push(e);
+        }
     }
     // Fall back to serial port
-    if settings.baud_rate > 0 {
-        match try_serial_port(content, settings).await {
+    if baud_rate > 0 {
+        match try_serial_port(content, port, baud_rate, serial, timeout).await {
             Ok(_) => return Ok(()),
-            Err(e) => log::warn!("Serial port print failed. Error: {}", e),
+            Err(e) => {
+                warn!("Serial port print failed. Error: {}", e);
+                failures.push(e);
+            }
         }
     }
 
-    Err("All USB printing methods failed".to_string())
+    // Report the most specific cause any of the methods gave us
+    let code = failures.iter()
+        .map(|e| e.code)
+        .find(|code| *code != ErrorCode::Unknown)
+        .unwrap_or(ErrorCode::Unknown);
+    let details = failures.iter().map(|e| e.message.as_str()).collect::<Vec<_>>().join(" | ");
+    Err(PrintError::new(code, format!("All USB printing methods failed: {}", details)))
 }
 
-async fn try_raw_usb_print(content: &str, settings: &PrinterSettings) -> Result<(), String> {
-    let printer_name = CString::new(settings.usb_port.clone()).map_err(|e| format!("Invalid printer name: {}", e))?;
-    let mut hprinter = ptr::null_mut();
-
-    unsafe {
-        if winspool::OpenPrinterA(printer_name.as_ptr() as *mut _, &mut hprinter, ptr::null_mut()) == 0 {
-            return Err(format!("OpenPrinter failed with error code: {}", winapi::um::errhandlingapi::GetLastError()));
-        }
-
-        let doc_name = CString::new("KOT Print").unwrap();
-        let data_type = CString::new("RAW").unwrap();
-        
-        let doc_info = winspool::DOC_INFO_1A {
-            pDocName: doc_name.as_ptr() as *mut _,
-            pOutputFile: ptr::null_mut(),
-            pDatatype: data_type.as_ptr() as *mut _,
-        };
-
-        if winspool::StartDocPrinterA(hprinter, 1, &doc_info as *const _ as *mut _) == 0 {
-            winspool::ClosePrinter(hprinter);
-            return Err(format!("StartDocPrinter failed: {}", winapi::um::errhandlingapi::GetLastError()));
-        }
-
-        let mut bytes_written: u32 = 0;
-        if winspool::WritePrinter(hprinter, content.as_ptr() as *mut _, content.len() as u32, &mut bytes_written) == 0 {
-            winspool::EndDocPrinter(hprinter);
-            winspool::ClosePrinter(hprinter);
-            return Err(format!("WritePrinter failed: {}", winapi::um::errhandlingapi::GetLastError()));
-        }
+#[cfg(windows)]
+async fn try_raw_usb_print(content: &[u8], printer_name: &str) -> Result<(), PrintError> {
+    // The handles must not live across the await below
+    let job_id = {
+        let printer = win32::Printer::open(Some(printer_name))?;
+        let document = printer.start_raw_document("KOT Print")?;
+        document.write(content)?;
+        document.job_id()
+    };
 
-        winspool::EndDocPrinter(hprinter);
-        winspool::ClosePrinter(hprinter);
+    // WritePrinter only means the spooler has the bytes; wait for the printer to actually take them
+    let timeout_secs = config::current().spool_job_timeout_secs;
+    if timeout_secs > 0 {
+        spooler::wait_for_job(printer_name, job_id, Duration::from_secs(timeout_secs)).await?;
     }
 
     Ok(())
 }
 
-async fn try_windows_print_command(content: &str, printer_name: &str) -> Result<(), String> {
+#[cfg(not(windows))]
+async fn try_raw_usb_print(_content: &[u8], printer_name: &str) -> Result<(), PrintError> {
+    Err(PrintError::windows_only(&format!("Printing RAW to {}", printer_name)))
+}
+
+async fn try_windows_print_command(content: &[u8], printer_name: &str) -> Result<(), PrintError> {
     let temp_path = env::temp_dir().join("zkp_print.txt");
-    let formatted_content = format!("\x1B@{}", content);
+    let formatted_content = [INIT.as_bytes(), content].concat();
     
     if let Err(e) = std::fs::write(&temp_path, formatted_content) {
-        log::error!("Failed to create print file: {}", e);
-        return Err(format!("Failed to create print file: {}", e));
+        error!("Failed to create print file: {}", e);
+        return Err(PrintError::from_io("Failed to create print file", &e));
     }
 
     let output = match Command::new("cmd")
@@ -273,9 +1514,9 @@ async fn try_windows_print_command(content: &str, printer_name: &str) -> Result<
     {
         Ok(output) => output,
         Err(e) => {
-            log::error!("Failed to execute print command: {}", e);
+            error!("Failed to execute print command: {}", e);
             let _ = std::fs::remove_file(&temp_path);
-            return Err(format!("Failed to execute print command: {}", e));
+            return Err(PrintError::from_io("Failed to execute print command", &e));
         }
     };
 
@@ -284,45 +1525,108 @@ async fn try_windows_print_command(content: &str, printer_name: &str) -> Result<
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         let stdout = String::from_utf8_lossy(&output.stdout);
-        log::error!("Print command failed. Status: {}. Stderr: {}. Stdout: {}", output.status, stderr, stdout);
-        return Err(format!("Print command failed. Status: {}. Stderr: {}. Stdout: {}", output.status, stderr, stdout));
+        error!("Print command failed. Status: {}. Stderr: {}. Stdout: {}", output.status, stderr, stdout);
+        return Err(PrintError::new(
+            ErrorCode::SpoolerError,
+            format!("Print command failed. Status: {}. Stderr: {}. Stdout: {}", output.status, stderr, stdout),
+        ));
     }
 
     Ok(())
 }
 
-async fn try_serial_port(content: &str, settings: &PrinterSettings) -> Result<(), String> {
-    let port_name = &settings.usb_port;
-    let baud_rate = settings.baud_rate;
-    
-    let mut port = serialport::new(port_name, baud_rate)
-        .timeout(PRINT_TIMEOUT)
-        .open()
-        .map_err(|e| format!("Failed to open serial port {}: {}", port_name, e))?;
+async fn try_serial_port(
+    content: &[u8],
+    port_name: &str,
+    baud_rate: u32,
+    settings: &SerialSettings,
+    write_timeout: Duration,
+) -> Result<(), PrintError> {
+    let mut port = serial::open(port_name, baud_rate, settings, write_timeout)?;
 
-    port.write_all(content.as_bytes()).map_err(|e| format!("Failed to write to port {}: {}", port_name, e))?;
-    port.flush().map_err(|e| format!("Failed to flush port {}: {}", port_name, e))?;
+    let chunk_size = match settings.chunk_size {
+        0 => content.len().max(1),
+        size => size,
+    };
+    for (i, chunk) in content.chunks(chunk_size).enumerate() {
+        if i > 0 && settings.chunk_delay_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(settings.chunk_delay_ms)).await;
+        }
+        port.write_all(chunk)
+            .map_err(|e| PrintError::from_io(&format!("Failed to write to port {}", port_name), &e))?;
+        port.flush()
+            .map_err(|e| PrintError::from_io(&format!("Failed to flush port {}", port_name), &e))?;
+    }
     
     tokio::time::sleep(USB_WRITE_DELAY).await;
     
     Ok(())
 }
 
-async fn attempt_network_print(content: &str, settings: &PrinterSettings) -> Result<(), String> {
-    use tokio::{net::TcpStream, time::timeout};
-    
-    let stream_result = timeout(PRINT_TIMEOUT, TcpStream::connect(&settings.network_ip)).await;
-    
-    let mut stream = match stream_result {
-        Ok(Ok(stream)) => stream,
-        Ok(Err(e)) => return Err(format!("Connection failed: {}", e)),
-        Err(_) => return Err("Connection timeout".to_string()),
+// Cheap print servers truncate one big write, so large payloads go out in paced chunks.
+async fn write_to_stream(stream: &mut tokio::net::TcpStream, bytes: &[u8], job_timeout: Duration) -> Result<(), PrintError> {
+    let config = config::current();
+    let chunk_size = match config.network_chunk_size {
+        0 => bytes.len().max(1),
+        size => size,
+    };
+    let chunk_delay = Duration::from_millis(config.network_chunk_delay_ms);
+
+    let mut sent = 0;
+    let write = async {
+        for (i, chunk) in bytes.chunks(chunk_size).enumerate() {
+            if i > 0 && !chunk_delay.is_zero() {
+                tokio::time::sleep(chunk_delay).await;
+            }
+            stream.writable().await?;
+            stream.write_all(chunk).await?;
+            sent += chunk.len();
+        }
+        stream.flush().await
     };
+    let outcome = tokio::time::timeout(job_timeout, write).await;
+    match outcome {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(PrintError::from_io(&format!("Write failed after {} of {} bytes", sent, bytes.len()), &e)),
+        Err(_) => Err(PrintError::new(
+            ErrorCode::Timeout,
+            format!("Printer stopped accepting data after {} of {} bytes", sent, bytes.len()),
+        )),
+    }
+}
+
+async fn attempt_ipp_print(content: &[u8], url: &str, job_timeout: Duration) -> Result<(), PrintError> {
+    let _guard = lock_destination(format!("ipp:{}", url)).await;
+    ipp::print(content, url, job_timeout).await
+}
 
-    stream.write_all(content.as_bytes()).await.map_err(|e| format!("Write failed: {}", e))?;
-    stream.flush().await.map_err(|e| format!("Flush failed: {}", e))?;
+async fn attempt_lpr_print(content: &[u8], address: &str, job_timeout: Duration) -> Result<(), PrintError> {
+    let _guard = lock_destination(format!("lpr:{}", address)).await;
+    lpr::print(content, address, job_timeout).await
+}
 
-    Ok(())
+async fn attempt_virtual_print(content: &[u8], directory: &str, columns: u32) -> Result<(), PrintError> {
+    let _guard = lock_destination(format!("virtual:{}", directory)).await;
+    emulator::print(content, directory, columns).await
 }
 
+async fn attempt_network_print(content: &[u8], address: &str, job_timeout: Duration) -> Result<(), PrintError> {
+    let _guard = lock_destination(format!("network:{}", address)).await;
+
+    let (mut stream, reused) = pool::checkout(address, job_timeout).await?;
+    let result = match write_to_stream(&mut stream, content, job_timeout).await {
+        // The printer may have dropped an idle connection without us noticing; retry once on a fresh one
+        Err(e) if reused && e.code != ErrorCode::Timeout => {
+            debug!("Pooled connection to {} failed ({}), reconnecting", address, e);
+            let (fresh, _) = pool::checkout(address, job_timeout).await?;
+            stream = fresh;
+            write_to_stream(&mut stream, content, job_timeout).await
+        }
+        result => result,
+    };
+
+    if result.is_ok() {
+        pool::checkin(address, stream);
+    }
+    result
 }
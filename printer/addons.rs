@@ -0,0 +1,54 @@
+// What each order's KOTs have already sent to the kitchen, so that when a table orders more the
+// next ticket is an ADD-ON with only the new items instead of the whole order again. The snapshot
+// is the order's item quantities as of its last KOT that went out in full.
+
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+
+use crate::db::DbState;
+
+use super::database;
+use super::error::PrintError;
+use super::fetch_order_details;
+
+// Quantity already printed, keyed by (item type, name)
+pub(crate) type PrintedItems = HashMap<(String, String), i64>;
+
+pub(crate) fn already_printed(printed: &PrintedItems, item_type: &str, name: &str) -> i64 {
+    printed.get(&(item_type.to_string(), name.to_string())).copied().unwrap_or(0)
+}
+
+pub(crate) fn printed_items(conn: &Connection, order_id: i64) -> rusqlite::Result<PrintedItems> {
+    let mut stmt = conn.prepare("SELECT item_type, name, quantity FROM kot_printed_items WHERE order_id = ?1")?;
+    let rows = stmt.query_map(params![order_id], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))?;
+    rows.collect()
+}
+
+// Takes the order's current items as printed.
+fn record(conn: &Connection, order_id: i64) -> Result<(), PrintError> {
+    let order = fetch_order_details(conn, order_id)?;
+    database::write_transaction(conn, |conn| {
+        conn.execute("DELETE FROM kot_printed_items WHERE order_id = ?1", params![order_id])?;
+        for (item_type, name, quantity, ..) in &order.item_data {
+            conn.execute(
+                "INSERT INTO kot_printed_items (order_id, item_type, name, quantity) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (order_id, item_type, name) DO UPDATE SET quantity = quantity + excluded.quantity",
+                params![order_id, item_type, name, quantity],
+            )?;
+        }
+        Ok(())
+    })
+}
+
+pub(crate) async fn record_printed(state: &DbState, order_id: i64) {
+    if let Err(e) = database::run(state, move |conn| record(conn, order_id)).await {
+        error!("Failed to record which items of order {} were printed: {}", order_id, e);
+    }
+}
+
+// For apps that print the KOT from generate_kot_content_from_db themselves: call once it is out.
+#[tauri::command]
+pub fn mark_kot_printed(order_id: i64, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    let conn = database::connection(&state)?;
+    record(&conn, order_id)
+}
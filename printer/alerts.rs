@@ -0,0 +1,110 @@
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::db::DbState;
+
+use super::database;
+use super::error::{ErrorCode, PrintError};
+use super::profile::{self, PaperStatus, PrinterProfile};
+use super::webhooks;
+use super::{was_printed, DeliveredTo, Destination};
+
+pub const PAPER_EVENT: &str = "printer://paper";
+pub const JOB_EVENT: &str = "printer://job";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PaperEvent {
+    // `None` for destinations that only exist in the legacy settings blob
+    pub printer_id: Option<i64>,
+    pub name: String,
+    pub paper_status: PaperStatus,
+}
+
+// One per destination a job was sent to, as soon as its outcome is known.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobEvent {
+    pub order_id: i64,
+    pub destination: Destination,
+    pub success: bool,
+    pub printed_on_fallback: bool,
+    pub dry_run: bool,
+    // Not printed yet; the printer was unreachable and the job waits in the offline queue
+    pub queued: bool,
+    pub error: Option<PrintError>,
+}
+
+async fn find_profile(state: &DbState, destination: &Destination) -> Option<PrinterProfile> {
+    let lookup = destination.clone();
+    database::run(state, move |conn| Ok(profile::find_by_destination(conn, &lookup)?))
+        .await
+        .unwrap_or_else(|e| {
+            error!("Failed to look up printer for {}: {}", destination.key(), e);
+            None
+        })
+}
+
+// Stores the paper state on the printer record and tells the UI about it.
+pub(crate) async fn record_paper_status<R: Runtime>(
+    app: &AppHandle<R>,
+    state: &DbState,
+    destination: &Destination,
+    paper_status: PaperStatus,
+) {
+    let printer = find_profile(state, destination).await;
+    if let Some(id) = printer.as_ref().and_then(|p| p.id) {
+        let stored = database::run(state, move |conn| Ok(profile::set_paper_status(conn, id, paper_status)?)).await;
+        if let Err(e) = stored {
+            error!("Failed to record paper status for printer {}: {}", id, e);
+        }
+    }
+
+    let event = PaperEvent {
+        printer_id: printer.as_ref().and_then(|p| p.id),
+        name: printer.map(|p| p.name).unwrap_or_else(|| destination.key()),
+        paper_status,
+    };
+    if let Err(e) = app.emit(PAPER_EVENT, event) {
+        error!("Failed to emit paper event: {}", e);
+    }
+}
+
+// Emits the job event and pings the webhooks when it failed. A paper-out failure also raises
+// the alert; the next successful job on that printer clears it.
+pub(crate) async fn report_job_outcome<R: Runtime>(
+    app: &AppHandle<R>,
+    state: &DbState,
+    order_id: i64,
+    destination: &Destination,
+    outcome: &Result<DeliveredTo, PrintError>,
+) {
+    let event = JobEvent {
+        order_id,
+        destination: destination.clone(),
+        success: was_printed(outcome),
+        printed_on_fallback: matches!(outcome, Ok(DeliveredTo::Fallback)),
+        dry_run: matches!(outcome, Ok(DeliveredTo::DryRun)),
+        queued: matches!(outcome, Ok(DeliveredTo::Queued)),
+        error: outcome.as_ref().err().cloned(),
+    };
+    if let Err(e) = app.emit(JOB_EVENT, event) {
+        error!("Failed to emit job event: {}", e);
+    }
+
+    if let Err(e) = outcome {
+        let name = find_profile(state, destination).await.map(|p| p.name).unwrap_or_else(|| destination.key());
+        webhooks::job_failed(order_id, &name, e);
+    }
+
+    match outcome {
+        Err(e) if e.code == ErrorCode::PaperOut => {
+            warn!("{} is out of paper", destination.key());
+            record_paper_status(app, state, destination, PaperStatus::Out).await;
+        }
+        Ok(DeliveredTo::Primary) => {
+            if find_profile(state, destination).await.map_or(false, |p| p.paper_status == PaperStatus::Out) {
+                record_paper_status(app, state, destination, PaperStatus::Ok).await;
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,328 @@
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::time::Duration;
+use tauri::{AppHandle, Runtime};
+
+use crate::db::DbState;
+
+use super::clock;
+use super::config;
+use super::database;
+use super::error::{ErrorCode, PrintError};
+use super::{alerts, drawer, fiscal, job_timeout, layout, profile, queue, timed_failover, was_printed};
+use super::{ChannelResult, DeliveredTo, Destination, DocumentType};
+
+// One sent job as the history screen lists it; the payload itself is fetched separately.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchivedJob {
+    pub id: i64,
+    pub order_id: i64,
+    pub outlet_id: Option<String>,
+    pub document_type: DocumentType,
+    pub destination: Destination,
+    pub printed: bool,
+    pub error: Option<String>,
+    // Uncompressed payload size in bytes
+    pub size: usize,
+    pub created_at: String,
+    // Set when the system clock was off by this much, so created_at can't be trusted
+    pub clock_drift_secs: Option<i64>,
+}
+
+impl ArchivedJob {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let document_type: String = row.get("document_type")?;
+        let destination: String = row.get("destination")?;
+        let size: i64 = row.get("size")?;
+        Ok(ArchivedJob {
+            id: row.get("id")?,
+            order_id: row.get("order_id")?,
+            outlet_id: row.get("outlet_id")?,
+            document_type: DocumentType::parse(&document_type),
+            destination: serde_json::from_str(&destination).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+            })?,
+            printed: row.get("printed")?,
+            error: row.get("error")?,
+            size: size as usize,
+            created_at: row.get("created_at")?,
+            clock_drift_secs: row.get("clock_drift_secs")?,
+        })
+    }
+}
+
+pub(crate) fn create_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS print_jobs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            order_id INTEGER NOT NULL,
+            document_type TEXT NOT NULL,
+            destination TEXT NOT NULL,
+            printed INTEGER NOT NULL,
+            error TEXT,
+            size INTEGER NOT NULL,
+            payload BLOB NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS print_jobs_order_id ON print_jobs (order_id);
+        CREATE INDEX IF NOT EXISTS print_jobs_created_at ON print_jobs (created_at);",
+    )
+}
+
+fn compress(payload: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(payload)?;
+    encoder.finish()
+}
+
+fn decompress(stored: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut payload = Vec::new();
+    DeflateDecoder::new(stored).read_to_end(&mut payload)?;
+    Ok(payload)
+}
+
+fn insert(
+    conn: &Connection,
+    order_id: i64,
+    document_type: DocumentType,
+    destination: &Destination,
+    source: &[u8],
+    payload: &[u8],
+    outcome: &Result<DeliveredTo, PrintError>,
+    elapsed: Duration,
+) -> Result<(), PrintError> {
+    let destination = serde_json::to_string(destination).map_err(|e| PrintError::new(ErrorCode::Unknown, e.to_string()))?;
+    // An empty blob marks a job whose bytes weren't kept; a deflate stream is never empty
+    let (stored, stored_source) = if config::current().archive_payloads {
+        let compressed = |bytes: &[u8]| compress(bytes).map_err(|e| PrintError::from_io("Could not compress payload", &e));
+        (compressed(payload)?, compressed(source)?)
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    database::write_transaction(conn, |conn| {
+        conn.execute(
+            "INSERT INTO print_jobs
+                (order_id, document_type, destination, printed, error, size, payload, outlet_id, error_code, duration_ms,
+                 clock_drift_secs, source)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                order_id,
+                document_type,
+                destination,
+                was_printed(outcome),
+                outcome.as_ref().err().map(|e| e.to_string()),
+                payload.len() as i64,
+                stored,
+                config::current().outlet_id,
+                outcome.as_ref().err().map(|e| e.code),
+                elapsed.as_millis() as i64,
+                clock::drift_flag(),
+                stored_source
+            ],
+        )?;
+
+        let retention_days = config::current().archive_retention_days;
+        if retention_days > 0 {
+            conn.execute(
+                "DELETE FROM print_jobs WHERE created_at < datetime('now', ?1)",
+                params![format!("-{} days", retention_days)],
+            )?;
+        }
+        Ok(())
+    })
+}
+
+// Logs every job sent to one destination, with its outcome and how long it took, for history and
+// the printer metrics; the exact bytes are kept too when archiving is on. Failures are logged
+// rather than returned; the ticket has already gone out either way.
+pub(crate) async fn record(
+    state: &DbState,
+    order_id: i64,
+    document_type: DocumentType,
+    destination: &Destination,
+    source: &[u8],
+    payload: &[u8],
+    outcome: &Result<DeliveredTo, PrintError>,
+    elapsed: Duration,
+) {
+    if matches!(outcome, Ok(DeliveredTo::DryRun)) {
+        return;
+    }
+    let (destination, source, payload, outcome) = (destination.clone(), source.to_vec(), payload.to_vec(), outcome.clone());
+    let archived = database::run(state, move |conn| {
+        insert(conn, order_id, document_type, &destination, &source, &payload, &outcome, elapsed)
+    })
+    .await;
+    if let Err(e) = archived {
+        error!("Failed to archive print job for order {}: {}", order_id, e);
+    }
+}
+
+// When each destination last took a job, keyed by destination key.
+pub(crate) fn last_printed(conn: &Connection) -> rusqlite::Result<HashMap<String, String>> {
+    let mut stmt = conn.prepare("SELECT destination, MAX(created_at) FROM print_jobs WHERE printed = 1 GROUP BY destination")?;
+    let mut rows = stmt.query([])?;
+    let mut last = HashMap::new();
+    while let Some(row) = rows.next()? {
+        let destination: String = row.get(0)?;
+        let printed_at: String = row.get(1)?;
+        // Destinations differing only in settings, such as baud rate, share a key
+        if let Ok(destination) = serde_json::from_str::<Destination>(&destination) {
+            let latest = last.entry(destination.key()).or_insert_with(String::new);
+            if printed_at > *latest {
+                *latest = printed_at;
+            }
+        }
+    }
+    Ok(last)
+}
+
+// The newest failed jobs whose bytes were kept, newest first.
+pub(crate) fn recent_failures(conn: &Connection, limit: u32) -> Result<Vec<(ArchivedJob, Vec<u8>)>, PrintError> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM print_jobs WHERE printed = 0 AND error IS NOT NULL AND length(payload) > 0 ORDER BY id DESC LIMIT ?1",
+    )?;
+    let stored = stmt
+        .query_map(params![limit], |row| Ok((ArchivedJob::from_row(row)?, row.get::<_, Vec<u8>>("payload")?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    stored
+        .into_iter()
+        .map(|(job, stored)| {
+            let payload = decompress(&stored)
+                .map_err(|e| PrintError::from_io(&format!("Archived job {} is corrupt", job.id), &e))?;
+            Ok((job, payload))
+        })
+        .collect()
+}
+
+// What the caller last sent for the order's document, before copies, the fiscal watermark and the
+// printer's layout, so printing it again goes through the whole job path like the first time did.
+// `None` when no job kept it.
+pub(crate) fn latest_source(conn: &Connection, order_id: i64, document_type: DocumentType) -> Result<Option<Vec<u8>>, PrintError> {
+    let stored: Option<(i64, Vec<u8>)> = conn
+        .query_row(
+            "SELECT id, source FROM print_jobs WHERE order_id = ?1 AND document_type = ?2 AND length(source) > 0
+             ORDER BY id DESC LIMIT 1",
+            params![order_id, document_type],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    stored
+        .map(|(job_id, stored)| {
+            decompress(&stored).map_err(|e| PrintError::from_io(&format!("Archived job {} is corrupt", job_id), &e))
+        })
+        .transpose()
+}
+
+// The job's source as latest_source keeps it; empty when it wasn't kept.
+fn source(conn: &Connection, job_id: i64) -> Result<Vec<u8>, PrintError> {
+    let stored: Vec<u8> = conn.query_row("SELECT source FROM print_jobs WHERE id = ?1", params![job_id], |row| row.get(0))?;
+    if stored.is_empty() {
+        return Ok(stored);
+    }
+    decompress(&stored).map_err(|e| PrintError::from_io(&format!("Archived job {} is corrupt", job_id), &e))
+}
+
+pub(crate) fn get(conn: &Connection, job_id: i64) -> Result<(ArchivedJob, Vec<u8>), PrintError> {
+    let found = conn
+        .query_row("SELECT * FROM print_jobs WHERE id = ?1", params![job_id], |row| {
+            Ok((ArchivedJob::from_row(row)?, row.get::<_, Vec<u8>>("payload")?))
+        })
+        .optional()?;
+    let (job, stored) =
+        found.ok_or_else(|| PrintError::new(ErrorCode::InvalidSettings, format!("Archived job {} does not exist", job_id)))?;
+    if stored.is_empty() {
+        return Err(PrintError::new(
+            ErrorCode::InvalidSettings,
+            format!("Job {} was logged without its payload; turn on archive_payloads to keep them", job_id),
+        ));
+    }
+    let payload = decompress(&stored).map_err(|e| PrintError::from_io(&format!("Archived job {} is corrupt", job_id), &e))?;
+    Ok((job, payload))
+}
+
+// Newest first; `order_id` narrows it to one order's tickets and `outlet_id` to one branch.
+#[tauri::command]
+pub fn list_archived_jobs(
+    order_id: Option<i64>,
+    outlet_id: Option<String>,
+    limit: Option<u32>,
+    state: tauri::State<'_, DbState>,
+) -> Result<Vec<ArchivedJob>, PrintError> {
+    let conn = database::connection(&state)?;
+    let mut stmt = conn.prepare(
+        "SELECT * FROM print_jobs WHERE (?1 IS NULL OR order_id = ?1) AND (?2 IS NULL OR outlet_id = ?2)
+         ORDER BY id DESC LIMIT ?3",
+    )?;
+    let jobs = stmt
+        .query_map(params![order_id, outlet_id, limit.unwrap_or(100)], ArchivedJob::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(jobs)
+}
+
+// The exact bytes that went to the printer, for settling what a ticket did or didn't say.
+#[tauri::command]
+pub fn get_archived_payload(job_id: i64, state: tauri::State<'_, DbState>) -> Result<Vec<u8>, PrintError> {
+    let conn = database::connection(&state)?;
+    Ok(get(&conn, job_id)?.1)
+}
+
+// Sends an archived job's bytes again, to its original destination or to `printer_id`, e.g.
+// after a jam cut a ticket short. Goes through the normal failover path and is archived too. In
+// fiscal mode a bill is a copy like any other reprint: it is watermarked and counted.
+#[tauri::command]
+pub async fn reprint_job<R: Runtime>(
+    app: AppHandle<R>,
+    job_id: i64,
+    printer_id: Option<i64>,
+    state: tauri::State<'_, DbState>,
+) -> Result<ChannelResult, PrintError> {
+    let (job, source, mut payload, printer, destination, timeout) = database::run(&state, move |conn| {
+        let (job, payload) = get(conn, job_id)?;
+        let source = source(conn, job_id)?;
+        let printer = match printer_id {
+            Some(id) => Some(profile::get(conn, id)?),
+            None => profile::find_by_destination(conn, &job.destination)?,
+        };
+        let destination = match (&printer, printer_id) {
+            (Some(printer), Some(_)) => printer.destination(),
+            _ => job.destination.clone(),
+        };
+        let timeout = job_timeout(None, printer.as_ref());
+        Ok((job, source, payload, printer, destination, timeout))
+    })
+    .await?;
+
+    // The watermark goes on what the caller sent, laid out again, so the old print's layout and any
+    // earlier watermark aren't doubled; jobs archived without their source are marked as they are
+    let mut marked = if source.is_empty() { payload.clone() } else { source.clone() };
+    let fiscal = fiscal::prepare(&state, job.order_id, Some(job.document_type), &mut marked).await?;
+    if fiscal.is_some() {
+        payload = match (&printer, source.is_empty()) {
+            (Some(printer), false) => layout::apply(&marked, printer),
+            _ => marked,
+        };
+    }
+
+    // The archive holds byte jobs too, which allow-print alone mustn't be able to replay
+    drawer::refuse_kick(&payload, Some(job.document_type))?;
+    let (outcome, elapsed) = match queue::join_backlog(&state, Some(job.order_id), &destination, &payload).await {
+        Some(outcome) => (outcome, Duration::ZERO),
+        None => timed_failover(&payload, &destination, timeout, Some(job.order_id)).await,
+    };
+    if let Some(fiscal) = fiscal {
+        fiscal::record(&state, fiscal, outcome.is_ok().then_some(payload.as_slice())).await;
+    }
+    alerts::report_job_outcome(&app, &state, job.order_id, &destination, &outcome).await;
+    record(&state, job.order_id, job.document_type, &destination, &source, &payload, &outcome, elapsed).await;
+    match &outcome {
+        Ok(_) => info!("Reprinted archived job {} for order {} to {}", job_id, job.order_id, destination.key()),
+        Err(e) => error!("Reprint of archived job {} failed: {}", job_id, e),
+    }
+    Ok(ChannelResult::from_outcome(&outcome, &payload))
+}
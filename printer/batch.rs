@@ -0,0 +1,99 @@
+// Many orders' documents in one call: reprinting the morning's failed tickets, or every open
+// table's bill at close. All the orders are read in one transaction on one connection, then sent
+// one after another so they come out in the order asked for.
+
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::{AppHandle, Runtime};
+
+use crate::db::DbState;
+
+use super::archive;
+use super::config;
+use super::database;
+use super::drawer;
+use super::error::{ErrorCode, PrintError};
+use super::profile;
+use super::{build_kot_content, send_to_profiles, DocumentType, PrinterPrintResult};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderPrintResult {
+    pub order_id: i64,
+    // Empty when the document couldn't be rendered or was refused before sending
+    pub printers: Vec<PrinterPrintResult>,
+    pub error: Option<PrintError>,
+}
+
+// What was last sent for the document, as the app sent it, so copies, the fiscal watermark and the
+// printers' layouts are applied once like on any new job. A KOT that wasn't kept is generated
+// again for `username` with the first copy's template; other documents are rendered by the app.
+fn render(conn: &Connection, order_id: i64, document_type: DocumentType, username: &str) -> Result<Vec<u8>, PrintError> {
+    if let Some(source) = archive::latest_source(conn, order_id, document_type)? {
+        return Ok(source);
+    }
+    match document_type {
+        DocumentType::Kot => {
+            let template = config::current().kot_template(0);
+            Ok(build_kot_content(conn, order_id, false, username, None, None, template)?.unwrap_or_default().into_bytes())
+        }
+        _ => Err(PrintError::new(
+            ErrorCode::InvalidSettings,
+            format!("No archived {} for order {}; turn on archive_payloads to keep them", document_type.as_str(), order_id),
+        )),
+    }
+}
+
+// One failed order doesn't stop the rest; its error is in its result.
+#[tauri::command]
+pub async fn print_orders<R: Runtime>(
+    app: AppHandle<R>,
+    order_ids: Vec<i64>,
+    document_type: DocumentType,
+    printer_ids: Option<Vec<i64>>,
+    // Who the KOTs name when they have to be generated again
+    username: Option<String>,
+    state: tauri::State<'_, DbState>,
+) -> Result<Vec<OrderPrintResult>, PrintError> {
+    let printer_ids = printer_ids.unwrap_or_else(|| config::current().default_printer_ids);
+    if printer_ids.is_empty() {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "No printers selected and no default printers saved"));
+    }
+
+    let (profiles, documents) = database::run(&state, move |conn| {
+        database::read_transaction(conn, |conn| {
+            let profiles = printer_ids.iter().map(|id| profile::get(conn, *id)).collect::<Result<Vec<_>, _>>()?;
+            let username = username.unwrap_or_default();
+            // Archived sources include byte jobs, which allow-print alone mustn't be able to replay
+            let documents: Vec<_> = order_ids
+                .iter()
+                .map(|&id| {
+                    let document = render(conn, id, document_type, &username)
+                        .and_then(|content| drawer::refuse_kick(&content, Some(document_type)).map(|_| content));
+                    (id, document)
+                })
+                .collect();
+            Ok((profiles, documents))
+        })
+    })
+    .await?;
+
+    let mut results = Vec::with_capacity(documents.len());
+    for (order_id, document) in documents {
+        let sent = match document {
+            Ok(content) => {
+                send_to_profiles(&app, order_id, profiles.clone(), &content, Some(document_type), None, None, &state).await
+            }
+            Err(e) => Err(e),
+        };
+        let result = match sent {
+            Ok(printers) => OrderPrintResult { order_id, printers, error: None },
+            Err(e) => {
+                warn!("Batch print of {} for order {} failed: {}", document_type.as_str(), order_id, e);
+                OrderPrintResult { order_id, printers: Vec::new(), error: Some(e) }
+            }
+        };
+        results.push(result);
+    }
+    info!("Batch printed {} {} documents", results.len(), document_type.as_str());
+    Ok(results)
+}
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+fn breakers() -> &'static Mutex<HashMap<String, Breaker>> {
+    static BREAKERS: OnceLock<Mutex<HashMap<String, Breaker>>> = OnceLock::new();
+    BREAKERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Once the cooldown passes the breaker is half-open: the next job is let through as a probe.
+pub(crate) fn is_open(key: &str) -> bool {
+    let breakers = breakers().lock().unwrap_or_else(|e| e.into_inner());
+    match breakers.get(key).and_then(|b| b.open_until) {
+        Some(until) => Instant::now() < until,
+        None => false,
+    }
+}
+
+pub(crate) fn record_success(key: &str) {
+    let mut breakers = breakers().lock().unwrap_or_else(|e| e.into_inner());
+    breakers.remove(key);
+}
+
+pub(crate) fn record_failure(key: &str, threshold: u32, cooldown: Duration) {
+    let mut breakers = breakers().lock().unwrap_or_else(|e| e.into_inner());
+    let breaker = breakers.entry(key.to_string()).or_default();
+    breaker.consecutive_failures += 1;
+    if breaker.consecutive_failures >= threshold.max(1) {
+        if breaker.open_until.map_or(true, |until| Instant::now() >= until) {
+            warn!("Circuit breaker opened for {} after {} failures", key, breaker.consecutive_failures);
+        }
+        breaker.open_until = Some(Instant::now() + cooldown);
+    }
+}
+
+pub(crate) fn reset(key: &str) {
+    let mut breakers = breakers().lock().unwrap_or_else(|e| e.into_inner());
+    breakers.remove(key);
+}
+
+// Every printer that has failed since its last success: (key, consecutive failures, open).
+pub(crate) fn snapshot() -> Vec<(String, u32, bool)> {
+    let breakers = breakers().lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    breakers
+        .iter()
+        .map(|(key, b)| (key.clone(), b.consecutive_failures, b.open_until.map_or(false, |until| now < until)))
+        .collect()
+}
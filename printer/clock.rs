@@ -0,0 +1,179 @@
+// The wall clock for everything printed and every "today". Kiosks are often locked to UTC, so the
+// outlet's own timezone can be set instead of trusting the OS. A clock that is simply wrong is
+// caught by the startup check against `time_check_source`: past the threshold it raises a clock
+// event, and every job printed until the next check records the drift.
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Runtime};
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+use crate::db::DbState;
+
+use super::config;
+use super::error::{ErrorCode, PrintError};
+
+pub const CLOCK_EVENT: &str = "printer://clock";
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+// NTP counts seconds from 1900, Unix from 1970
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClockCheck {
+    pub source: String,
+    // The reference time minus ours; positive when this clock is behind
+    pub drift_secs: i64,
+    pub threshold_secs: u64,
+    pub drifted: bool,
+    pub checked_at: String,
+}
+
+fn last_check() -> &'static Mutex<Option<ClockCheck>> {
+    static LAST_CHECK: OnceLock<Mutex<Option<ClockCheck>>> = OnceLock::new();
+    LAST_CHECK.get_or_init(|| Mutex::new(None))
+}
+
+fn parse(name: &str) -> Result<Tz, PrintError> {
+    name.parse()
+        .map_err(|_| PrintError::new(ErrorCode::InvalidSettings, format!("Unknown timezone {}", name)))
+}
+
+// The configured zone, or None for the OS's. A bad name falls back to the OS rather than failing a print.
+fn zone() -> Option<Tz> {
+    let name = config::current().timezone?;
+    parse(&name).map_err(|e| warn!("{}; using the system timezone", e)).ok()
+}
+
+pub(crate) fn now() -> DateTime<FixedOffset> {
+    match zone() {
+        Some(tz) => Utc::now().with_timezone(&tz).fixed_offset(),
+        None => Local::now().fixed_offset(),
+    }
+}
+
+pub(crate) fn today() -> NaiveDate {
+    now().date_naive()
+}
+
+// When `date` started, in UTC, for comparing against the UTC timestamps SQLite writes. A midnight
+// skipped by a DST change falls back to 24 hours before now.
+pub(crate) fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    let start = match zone() {
+        Some(tz) => tz.from_local_datetime(&midnight).earliest().map(|t| t.with_timezone(&Utc)),
+        None => Local.from_local_datetime(&midnight).earliest().map(|t| t.with_timezone(&Utc)),
+    };
+    start.unwrap_or_else(|| Utc::now() - chrono::Duration::hours(24))
+}
+
+// How far off this clock is while it is past the threshold, for flagging printed jobs.
+pub(crate) fn drift_flag() -> Option<i64> {
+    last_check().lock().unwrap_or_else(|e| e.into_inner()).as_ref().filter(|c| c.drifted).map(|c| c.drift_secs)
+}
+
+fn check_failed(source: &str, reason: impl std::fmt::Display) -> PrintError {
+    PrintError::new(ErrorCode::ConnectionFailed, format!("Time check against {} failed: {}", source, reason))
+}
+
+// SNTP: one client request, and the server's transmit timestamp from the reply.
+async fn ntp_offset(server: &str) -> Result<chrono::Duration, PrintError> {
+    let address = if server.contains(':') { server.to_string() } else { format!("{}:123", server) };
+    let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(|e| check_failed(server, e))?;
+    let mut packet = [0u8; 48];
+    // Leap indicator 0, version 3, client mode
+    packet[0] = 0x1B;
+    let sent = Utc::now();
+    socket.send_to(&packet, &address).await.map_err(|e| check_failed(server, e))?;
+    match timeout(CHECK_TIMEOUT, socket.recv_from(&mut packet)).await {
+        Ok(Ok((len, _))) if len >= 48 => {}
+        Ok(Ok(_)) => return Err(check_failed(server, "short reply")),
+        Ok(Err(e)) => return Err(check_failed(server, e)),
+        Err(_) => return Err(check_failed(server, "no reply")),
+    }
+    let received = Utc::now();
+
+    let seconds = u32::from_be_bytes([packet[40], packet[41], packet[42], packet[43]]) as i64 - NTP_UNIX_OFFSET;
+    let fraction = u32::from_be_bytes([packet[44], packet[45], packet[46], packet[47]]) as u64;
+    let nanos = ((fraction * 1_000_000_000) >> 32) as u32;
+    let server_time = DateTime::from_timestamp(seconds, nanos).ok_or_else(|| check_failed(server, "bad timestamp"))?;
+    // The reply is half a round trip old by the time it lands
+    Ok(server_time + (received - sent) / 2 - received)
+}
+
+// The Date header of any web server; good to the second, which is plenty here.
+async fn http_offset(url: &str) -> Result<chrono::Duration, PrintError> {
+    let client = reqwest::Client::builder().timeout(CHECK_TIMEOUT).build().map_err(|e| check_failed(url, e))?;
+    let response = client.head(url).send().await.map_err(|e| check_failed(url, e))?;
+    let received = Utc::now();
+    let date = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|date| date.to_str().ok())
+        .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+        .ok_or_else(|| check_failed(url, "no Date header"))?;
+    Ok(date.with_timezone(&Utc) - received)
+}
+
+// `ntp://host[:port]` or an http(s) URL.
+async fn check(source: &str, threshold_secs: u64) -> Result<ClockCheck, PrintError> {
+    let offset = match source.strip_prefix("ntp://") {
+        Some(server) => ntp_offset(server).await?,
+        None => http_offset(source).await?,
+    };
+    let drift_secs = offset.num_seconds();
+    Ok(ClockCheck {
+        source: source.to_string(),
+        drift_secs,
+        threshold_secs,
+        drifted: drift_secs.unsigned_abs() > threshold_secs,
+        checked_at: Utc::now().to_rfc3339(),
+    })
+}
+
+// For plugin setup. Off unless a source is configured; an unreachable source is only logged.
+pub(crate) fn check_on_startup<R: Runtime>(app: &AppHandle<R>) {
+    let config = config::current();
+    let Some(source) = config.time_check_source else {
+        return;
+    };
+    let threshold_secs = config.time_check_threshold_secs;
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = match check(&source, threshold_secs).await {
+            Ok(result) => result,
+            Err(e) => {
+                warn!("{}", e);
+                return;
+            }
+        };
+        if result.drifted {
+            warn!("System clock is off by {} seconds (checked against {})", result.drift_secs, source);
+            if let Err(e) = app.emit(CLOCK_EVENT, result.clone()) {
+                error!("Failed to emit clock event: {}", e);
+            }
+        } else {
+            debug!("System clock is within {} seconds of {}", result.drift_secs.abs(), source);
+        }
+        *last_check().lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+    });
+}
+
+// The startup check's result; None when it is off, failed, or hasn't finished.
+#[tauri::command]
+pub fn get_clock_check() -> Option<ClockCheck> {
+    last_check().lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+// An IANA name such as "Asia/Kathmandu"; `None` goes back to the system timezone.
+#[tauri::command]
+pub fn set_timezone(timezone: Option<String>, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    if let Some(name) = &timezone {
+        parse(name)?;
+    }
+    config::update_persisted(&state, |config| config.timezone = timezone)
+}
@@ -0,0 +1,509 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Mutex, OnceLock, RwLock};
+
+use crate::db::DbState;
+
+use super::database;
+use super::display::CustomerDisplay;
+use super::drawer::DrawerAssignment;
+use super::error::{ErrorCode, PrintError};
+use super::letterhead;
+use super::messages::Language;
+use super::profile;
+use super::secrets;
+use super::sequences::BillSeries;
+use super::vendor::{VendorOperation, VendorProfile};
+use super::{DocumentType, Destination};
+
+// Plugin-wide printing preferences shared by every command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrinterConfig {
+    pub language: Language,
+    // Branch this terminal belongs to; it only sees that outlet's printers and shared ones
+    pub outlet_id: Option<String>,
+    // Identical jobs inside this window are rejected as duplicates; 0 disables the check
+    pub idempotency_window_secs: u64,
+    // Copies printed per document type; anything missing prints once
+    pub copies: HashMap<DocumentType, u8>,
+    // Consecutive failures before a printer's breaker opens, and how long it stays open
+    pub breaker_failure_threshold: u32,
+    pub breaker_cooldown_secs: u64,
+    // Keyed by the primary destination's key
+    pub fallbacks: HashMap<String, FallbackRoute>,
+    pub station_routes: Vec<StationRoute>,
+    // Printer profile that gets the expo summary with every print_kot_to_stations
+    pub expo_printer_id: Option<i64>,
+    // Printer profile ids used when a print call doesn't name any
+    pub default_printer_ids: Vec<i64>,
+    // Background status polling interval; 0 leaves the monitor off
+    pub status_poll_interval_secs: u64,
+    // Query status before each job so a cover-open printer fails fast instead of buffering a stale ticket
+    pub preflight_status_check: bool,
+    // Drawers wire their switch differently; most report open with pin 3 high
+    pub drawer_open_when_pin_high: bool,
+    // Which printer and kick-out pin each cashier's drawer hangs off, keyed by username
+    pub drawer_assignments: HashMap<String, DrawerAssignment>,
+    // How long to wait for a spooled job to reach PRINTED before cancelling it; 0 trusts WritePrinter
+    pub spool_job_timeout_secs: u64,
+    // Idle network connections are reused for this long; 0 opens a new connection per job
+    pub network_keep_alive_secs: u64,
+    // Network payloads are written this many bytes at a time with a pause between; 0 sends in one write
+    pub network_chunk_size: usize,
+    pub network_chunk_delay_ms: u64,
+    // Hex byte strings for `{{cmd "name"}}` in print content, overriding the built-ins
+    pub command_snippets: HashMap<String, String>,
+    // Hex byte strings replacing a vendor's built-in sequence for an operation
+    pub vendor_overrides: HashMap<VendorProfile, HashMap<VendorOperation, String>>,
+    // Printer profile that gets pack labels, and the sticker size it is loaded with
+    pub label_printer_id: Option<i64>,
+    pub label_width_mm: f32,
+    pub label_height_mm: f32,
+    pub label_gap_mm: f32,
+    // Frame KOT notes in a row of asterisks above and below so allergy notes stand out
+    pub kot_notes_boxed: bool,
+    // Layout of each KOT copy by copy index, e.g. [Kitchen, Waiter]; copies past the end are Standard
+    pub kot_copy_templates: Vec<KotTemplate>,
+    // Letterhead for PDF receipts and the header of the thermal documents listed in
+    // `header_document_types`; the logo must be a JPEG and only goes on PDFs
+    pub business_name: String,
+    pub business_address: String,
+    pub business_phone: String,
+    pub receipt_logo_path: Option<String>,
+    pub header_document_types: Vec<DocumentType>,
+    // Closing text per document type; see letterhead.rs
+    pub document_footers: HashMap<DocumentType, String>,
+    // Printed on A4 invoices; prices are VAT-inclusive and 0 leaves the VAT breakdown out
+    pub business_pan: String,
+    pub vat_rate_percent: f64,
+    // IANA name for printed dates and day boundaries, e.g. "Asia/Kathmandu"; None uses the OS's
+    pub timezone: Option<String>,
+    // Checked once at startup: "ntp://pool.ntp.org" or any http(s) URL, whose Date header is used.
+    // A clock off by more than the threshold raises a warning and flags printed jobs
+    pub time_check_source: Option<String>,
+    pub time_check_threshold_secs: u64,
+    // Bill number series by name ("bill" numbers receipts and invoices), and the day fiscal-year
+    // series restart on
+    pub bill_series: HashMap<String, BillSeries>,
+    pub fiscal_year_start_month: u32,
+    pub fiscal_year_start_day: u32,
+    // Audit mode: bills are recorded with a hash of their first print and reprints say "COPY n"
+    pub fiscal_mode: bool,
+    // Windows printer for A4 invoices; falls back to the system default
+    pub invoice_printer_name: Option<String>,
+    // Every job is logged for history and metrics and kept this many days (0 keeps them forever);
+    // `archive_payloads` keeps its exact bytes too, compressed
+    pub archive_payloads: bool,
+    pub archive_retention_days: u32,
+    // Staging: jobs are validated, rendered and marked printed but never sent to a printer
+    pub dry_run: bool,
+    // Demo and training mode: every printer is swapped for the virtual printer writing under
+    // `simulation_directory` (the temp dir when empty), with a fake delay, and every Nth job
+    // failing with `simulation_failure` when `simulation_fail_every` is set
+    pub simulation_mode: bool,
+    pub simulation_directory: String,
+    pub simulation_latency_ms: u64,
+    pub simulation_fail_every: u32,
+    pub simulation_failure: ErrorCode,
+    // On exit, how long jobs already being sent get to finish before they are parked for the next start
+    pub shutdown_grace_secs: u64,
+    // Jobs kept per unreachable printer until it comes back; 0 fails them at once instead
+    pub offline_queue_limit: u32,
+    // How far back the startup scan and get_unprinted_orders look for orders that never printed
+    pub recovery_window_hours: u32,
+    // e.g. "0.0.0.0:9464" to serve Prometheus metrics at /metrics; read at startup, None leaves it off
+    pub metrics_listen_address: Option<String>,
+    // Called when a job fails for good or a printer goes offline; see webhooks.rs
+    pub webhooks: Vec<Webhook>,
+    // How receipts are emailed to customers; None turns email_receipt off
+    pub email: Option<EmailSettings>,
+    // Texts pack-order customers their token when the KOT prints; None leaves it off
+    pub sms: Option<SmsGateway>,
+    // Serial pole display facing the customer
+    pub customer_display: Option<CustomerDisplay>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KotTemplate {
+    // The ticket as it has always printed
+    #[default]
+    Standard,
+    // Headed KITCHEN COPY, without the order total
+    Kitchen,
+    // Headed WAITER COPY, with the order total and table/pack
+    Waiter,
+}
+
+// A route with no categories catches every item no other station claims.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StationRoute {
+    pub station: String,
+    pub categories: Vec<String>,
+    pub destination: Destination,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackRoute {
+    pub destination: Destination,
+    // Printed in the banner, e.g. "KITCHEN" -> "*** KITCHEN PRINTER DOWN ***"
+    pub label: String,
+    // The fallback's make, so the banner is bolded in its own command set
+    #[serde(default)]
+    pub vendor_profile: VendorProfile,
+}
+
+// An empty secret sends the notification unsigned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default)]
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailSettings {
+    // e.g. "Suju Snacks <receipts@example.com>"
+    pub from: String,
+    // The order number is appended
+    pub subject: String,
+    pub transport: EmailTransport,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EmailTransport {
+    Smtp { host: String, port: u16, username: String, password: String },
+    // A JSON POST with the key as a bearer token
+    Api { url: String, api_key: String },
+}
+
+// `{{to}}` and `{{message}}` are filled in, URL-encoded in `url` and JSON-escaped in `body`.
+// With a body the gateway gets a JSON POST, otherwise a GET.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmsGateway {
+    pub url: String,
+    #[serde(default)]
+    pub body: Option<String>,
+    // e.g. an Authorization header with the provider's key
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    // `{{token}}` and `{{total}}` are filled in
+    pub message: String,
+}
+
+impl Default for PrinterConfig {
+    fn default() -> Self {
+        PrinterConfig {
+            language: Language::default(),
+            outlet_id: None,
+            idempotency_window_secs: 10,
+            copies: HashMap::new(),
+            breaker_failure_threshold: 3,
+            breaker_cooldown_secs: 30,
+            fallbacks: HashMap::new(),
+            station_routes: Vec::new(),
+            expo_printer_id: None,
+            default_printer_ids: Vec::new(),
+            status_poll_interval_secs: 0,
+            preflight_status_check: false,
+            drawer_open_when_pin_high: true,
+            drawer_assignments: HashMap::new(),
+            spool_job_timeout_secs: 15,
+            network_keep_alive_secs: 60,
+            network_chunk_size: 1024,
+            network_chunk_delay_ms: 5,
+            command_snippets: HashMap::new(),
+            vendor_overrides: HashMap::new(),
+            label_printer_id: None,
+            label_width_mm: 40.0,
+            label_height_mm: 30.0,
+            label_gap_mm: 2.0,
+            kot_notes_boxed: false,
+            kot_copy_templates: Vec::new(),
+            business_name: String::new(),
+            business_address: String::new(),
+            business_phone: String::new(),
+            receipt_logo_path: None,
+            header_document_types: vec![DocumentType::Bill, DocumentType::Refund],
+            document_footers: letterhead::default_footers(),
+            business_pan: String::new(),
+            vat_rate_percent: 0.0,
+            timezone: None,
+            time_check_source: None,
+            time_check_threshold_secs: 120,
+            bill_series: HashMap::new(),
+            fiscal_year_start_month: 1,
+            fiscal_year_start_day: 1,
+            fiscal_mode: false,
+            invoice_printer_name: None,
+            archive_payloads: false,
+            archive_retention_days: 30,
+            dry_run: false,
+            simulation_mode: false,
+            simulation_directory: String::new(),
+            simulation_latency_ms: 300,
+            simulation_fail_every: 0,
+            simulation_failure: ErrorCode::PaperOut,
+            shutdown_grace_secs: 5,
+            offline_queue_limit: 50,
+            recovery_window_hours: 12,
+            metrics_listen_address: None,
+            webhooks: Vec::new(),
+            email: None,
+            sms: None,
+            customer_display: None,
+        }
+    }
+}
+
+impl PrinterConfig {
+    pub fn copies_for(&self, document_type: DocumentType) -> u8 {
+        self.copies.get(&document_type).copied().unwrap_or(1).max(1)
+    }
+
+    // One template per KOT copy to print.
+    pub fn kot_templates(&self) -> Vec<KotTemplate> {
+        (0..self.copies_for(DocumentType::Kot) as usize).map(|copy| self.kot_template(copy)).collect()
+    }
+
+    pub fn kot_template(&self, copy: usize) -> KotTemplate {
+        self.kot_copy_templates.get(copy).copied().unwrap_or_default()
+    }
+}
+
+fn config_lock() -> &'static RwLock<PrinterConfig> {
+    static CONFIG: OnceLock<RwLock<PrinterConfig>> = OnceLock::new();
+    CONFIG.get_or_init(|| RwLock::new(PrinterConfig::default()))
+}
+
+pub fn current() -> PrinterConfig {
+    config_lock().read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+// Every PrintError looks this up, so it reads the one field instead of cloning the config.
+pub fn language() -> Language {
+    config_lock().read().unwrap_or_else(|e| e.into_inner()).language
+}
+
+pub fn update(f: impl FnOnce(&mut PrinterConfig)) {
+    let mut config = config_lock().write().unwrap_or_else(|e| e.into_inner());
+    f(&mut config);
+}
+
+// Several registers can share one database file, so every settings row belongs to a terminal.
+// The empty id holds the shared defaults used by terminals without their own row.
+const SHARED_TERMINAL: &str = "";
+
+fn terminal_lock() -> &'static RwLock<Option<String>> {
+    static TERMINAL: OnceLock<RwLock<Option<String>>> = OnceLock::new();
+    TERMINAL.get_or_init(|| RwLock::new(None))
+}
+
+// Host apps call this at startup; otherwise the machine name identifies the terminal.
+pub fn set_terminal_id(id: impl Into<String>) {
+    *terminal_lock().write().unwrap_or_else(|e| e.into_inner()) = Some(id.into());
+}
+
+pub fn terminal_id() -> String {
+    if let Some(id) = terminal_lock().read().unwrap_or_else(|e| e.into_inner()).clone() {
+        return id;
+    }
+    env::var("COMPUTERNAME")
+        .or_else(|_| env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "default".to_string())
+}
+
+pub(crate) fn create_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS printer_config (
+            terminal_id TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );",
+    )
+}
+
+// With the credentials from printer_secrets filled back in.
+fn read(conn: &Connection, terminal_id: &str) -> Result<Option<PrinterConfig>, PrintError> {
+    let stored: Option<(String, String)> = conn
+        .query_row(
+            "SELECT terminal_id, value FROM printer_config WHERE terminal_id IN (?1, ?2)
+             ORDER BY terminal_id = ?1 DESC LIMIT 1",
+            params![terminal_id, SHARED_TERMINAL],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((found, json)) = stored else {
+        return Ok(None);
+    };
+    let mut config: PrinterConfig = serde_json::from_str(&json)
+        .map_err(|e| PrintError::new(ErrorCode::InvalidSettings, format!("Stored printer settings are invalid: {}", e)))?;
+    secrets::put_back(&mut config, secrets::read(conn, &found)?);
+    Ok(Some(config))
+}
+
+// Replaces the in-memory config with whatever was last saved for this terminal, if anything.
+pub(crate) fn load(conn: &Connection) -> Result<(), PrintError> {
+    if let Some(config) = read(conn, &terminal_id())? {
+        update(|current| *current = config);
+    }
+    Ok(())
+}
+
+fn persist(conn: &Connection, terminal_id: &str, config: &PrinterConfig) -> Result<(), PrintError> {
+    let mut stored = config.clone();
+    let secrets = secrets::take(&mut stored);
+    let json = serde_json::to_string(&stored)
+        .map_err(|e| PrintError::new(ErrorCode::InvalidSettings, e.to_string()))?;
+    database::write_transaction(conn, |conn| {
+        conn.execute(
+            "INSERT INTO printer_config (terminal_id, value) VALUES (?1, ?2)
+             ON CONFLICT(terminal_id) DO UPDATE SET value = excluded.value",
+            params![terminal_id, json],
+        )?;
+        secrets::write(conn, terminal_id, &secrets)?;
+        Ok(())
+    })
+}
+
+// The change is saved first and only then made live, so a failed write leaves both as they were.
+pub(crate) fn update_persisted(state: &DbState, f: impl FnOnce(&mut PrinterConfig)) -> Result<(), PrintError> {
+    // Two saves at once would each start from the same config and one would drop the other's change
+    static SAVING: Mutex<()> = Mutex::new(());
+    let _saving = SAVING.lock().unwrap_or_else(|e| e.into_inner());
+    let conn = database::connection(state)?;
+    let mut next = current();
+    f(&mut next);
+    persist(&conn, &terminal_id(), &next)?;
+    *config_lock().write().unwrap_or_else(|e| e.into_inner()) = next;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_terminal_id() -> String {
+    terminal_id()
+}
+
+#[tauri::command]
+pub fn list_terminals(state: tauri::State<'_, DbState>) -> Result<Vec<String>, PrintError> {
+    let conn = database::connection(&state)?;
+    let mut stmt = conn.prepare("SELECT terminal_id FROM printer_config WHERE terminal_id != ?1 ORDER BY terminal_id")?;
+    let terminals = stmt.query_map(params![SHARED_TERMINAL], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+    Ok(terminals)
+}
+
+// `terminal_id` defaults to this terminal; pass "" for the shared defaults. Credentials come back
+// as a placeholder.
+#[tauri::command]
+pub fn get_printer_settings(
+    terminal_id: Option<String>,
+    state: tauri::State<'_, DbState>,
+) -> Result<PrinterConfig, PrintError> {
+    let config = match terminal_id {
+        Some(id) if id != self::terminal_id() => {
+            let conn = database::connection(&state)?;
+            read(&conn, &id)?.unwrap_or_default()
+        }
+        _ => current(),
+    };
+    Ok(secrets::redacted(config))
+}
+
+#[tauri::command]
+pub fn save_printer_settings(
+    mut settings: PrinterConfig,
+    terminal_id: Option<String>,
+    state: tauri::State<'_, DbState>,
+) -> Result<PrinterConfig, PrintError> {
+    match terminal_id {
+        Some(id) if id != self::terminal_id() => {
+            let conn = database::connection(&state)?;
+            secrets::restore(&mut settings, &read(&conn, &id)?.unwrap_or_default());
+            persist(&conn, &id, &settings)?;
+            Ok(secrets::redacted(settings))
+        }
+        _ => {
+            update_persisted(&state, |config| {
+                secrets::restore(&mut settings, config);
+                *config = settings;
+            })?;
+            // A new outlet brings other printers' init sequences
+            profile::load_init_sequences(&database::connection(&state)?)?;
+            Ok(secrets::redacted(current()))
+        }
+    }
+}
+
+#[tauri::command]
+pub fn set_printer_language(language: Language, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    update_persisted(&state, |config| config.language = language)
+}
+
+#[tauri::command]
+pub fn set_document_copies(document_type: DocumentType, copies: u8, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    update_persisted(&state, |config| {
+        config.copies.insert(document_type, copies.max(1));
+    })
+}
+
+#[tauri::command]
+pub fn set_kot_copy_templates(templates: Vec<KotTemplate>, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    update_persisted(&state, |config| config.kot_copy_templates = templates)
+}
+
+#[tauri::command]
+pub fn set_fallback_printer(
+    primary: Destination,
+    fallback: Option<FallbackRoute>,
+    state: tauri::State<'_, DbState>,
+) -> Result<(), PrintError> {
+    update_persisted(&state, |config| match fallback {
+        Some(route) => {
+            config.fallbacks.insert(primary.key(), route);
+        }
+        None => {
+            config.fallbacks.remove(&primary.key());
+        }
+    })
+}
+
+#[tauri::command]
+pub fn set_station_routes(routes: Vec<StationRoute>, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    update_persisted(&state, |config| config.station_routes = routes)
+}
+
+#[tauri::command]
+pub fn set_expo_printer(printer_id: Option<i64>, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    if let Some(id) = printer_id {
+        profile::get(&database::connection(&state)?, id)?;
+    }
+    update_persisted(&state, |config| config.expo_printer_id = printer_id)
+}
+
+#[tauri::command]
+pub fn set_webhooks(webhooks: Vec<Webhook>, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    if let Some(hook) = webhooks.iter().find(|h| !h.url.starts_with("https://") && !h.url.starts_with("http://")) {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, format!("Webhook URL {} must be http or https", hook.url)));
+    }
+    update_persisted(&state, |config| {
+        let previous = config.clone();
+        config.webhooks = webhooks;
+        secrets::restore(config, &previous);
+    })
+}
+
+#[tauri::command]
+pub fn set_idempotency_window(seconds: u64, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    update_persisted(&state, |config| config.idempotency_window_secs = seconds)
+}
+
+// Training and demo mode; see PrinterConfig::simulation_mode.
+#[tauri::command]
+pub fn set_simulation_mode(enabled: bool, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    update_persisted(&state, |config| config.simulation_mode = enabled)?;
+    info!("Simulation mode {}", if enabled { "on" } else { "off" });
+    Ok(())
+}
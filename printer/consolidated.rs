@@ -0,0 +1,149 @@
+// One KOT for a burst of orders, e.g. a catering drop of twenty boxes: items are grouped across
+// the orders with the total to make, and under each item which tokens want how many.
+
+use rusqlite::Connection;
+use tauri::{AppHandle, Runtime};
+
+use crate::db::DbState;
+
+use super::clock;
+use super::config;
+use super::database;
+use super::error::{ErrorCode, PrintError};
+use super::letterhead;
+use super::profile;
+use super::receipt::item_details;
+use super::{fetch_order_details, mark_printed, send_to_profiles, wrap_text};
+use super::{DocumentType, PrinterPrintResult, BOLD_OFF, BOLD_ON, CUT_PAPER, INIT};
+
+const LINE_WIDTH: usize = 48;
+
+// One order's share of a grouped item.
+struct Share {
+    token: String,
+    has_table: bool,
+    quantity: i64,
+    details: Vec<String>,
+}
+
+fn token(order_number: &str) -> String {
+    order_number.split('-').last().unwrap_or("").to_string()
+}
+
+fn build(conn: &Connection, order_ids: &[i64], username: &str) -> Result<String, PrintError> {
+    let config = config::current();
+    let mut tokens = Vec::new();
+    let mut notes = Vec::new();
+    // (item type, name) in the order first seen, with each order's share
+    let mut groups: Vec<((String, String), Vec<Share>)> = Vec::new();
+    for &order_id in order_ids {
+        let order = fetch_order_details(conn, order_id)?;
+        let token = token(&order.order_number);
+        if !order.notes.trim().is_empty() {
+            notes.push(format!("#{}: {}", token, order.notes.trim()));
+        }
+        for (item_type, name, quantity, dinein_json, pack_json) in &order.item_data {
+            let share = Share {
+                token: token.clone(),
+                has_table: order.has_table,
+                quantity: *quantity,
+                details: item_details(item_type, dinein_json, pack_json),
+            };
+            let key = (item_type.clone(), name.clone());
+            match groups.iter_mut().find(|(existing, _)| *existing == key) {
+                Some((_, shares)) => shares.push(share),
+                None => groups.push((key, vec![share])),
+            }
+        }
+        tokens.push(token);
+    }
+
+    let mut content = String::from(INIT);
+    content.push_str(&format!("{}CONSOLIDATED KOT ({} orders){}\n", BOLD_ON, order_ids.len(), BOLD_OFF));
+    for line in wrap_text(&format!("Tokens: {}", tokens.join(", ")), LINE_WIDTH) {
+        content.push_str(&format!("{}\n", line));
+    }
+    content.push_str(&format!("{}\n", clock::now().format("%Y-%m-%d %I:%M:%S %p")));
+    content.push_str(&("-".repeat(LINE_WIDTH) + "\n"));
+    if !notes.is_empty() {
+        content.push_str(BOLD_ON);
+        for line in notes.iter().flat_map(|note| wrap_text(note, LINE_WIDTH)) {
+            content.push_str(&format!("{}\n", line));
+        }
+        content.push_str(BOLD_OFF);
+        content.push_str(&("-".repeat(LINE_WIDTH) + "\n"));
+    }
+
+    for ((_, name), shares) in &groups {
+        let total: i64 = shares.iter().map(|share| share.quantity).sum();
+        content.push_str(&format!("{}{}) {}{}\n", BOLD_ON, total, name, BOLD_OFF));
+        for share in shares {
+            let order_type = if share.has_table { "Table" } else { "Pack" };
+            content.push_str(&format!("  #{} [{}] x{}\n", share.token, order_type, share.quantity));
+            for detail in &share.details {
+                content.push_str(&format!("      - {}\n", detail));
+            }
+        }
+    }
+
+    content.push_str(&("-".repeat(LINE_WIDTH) + "\n"));
+    content.push_str(&format!("{}\n", username));
+    if let Some(footer) = letterhead::footer(&config, DocumentType::Kot) {
+        content.push_str(footer);
+    }
+    content.push_str("\n\n");
+    content.push_str(CUT_PAPER);
+    Ok(content)
+}
+
+// The merged ticket text, for previewing before it is printed.
+#[tauri::command]
+pub async fn generate_consolidated_kot(
+    order_ids: Vec<i64>,
+    username: String,
+    state: tauri::State<'_, DbState>,
+) -> Result<String, PrintError> {
+    database::run(&state, move |conn| database::read_transaction(conn, |conn| build(conn, &order_ids, &username))).await
+}
+
+// Prints the merged ticket and marks every order in it printed. The job is archived and
+// deduplicated under the first order.
+#[tauri::command]
+pub async fn print_consolidated_kot<R: Runtime>(
+    app: AppHandle<R>,
+    order_ids: Vec<i64>,
+    username: String,
+    printer_ids: Option<Vec<i64>>,
+    state: tauri::State<'_, DbState>,
+) -> Result<Vec<PrinterPrintResult>, PrintError> {
+    let Some(&first) = order_ids.first() else {
+        return Err(PrintError::new(ErrorCode::InvalidContent, "No orders to consolidate"));
+    };
+    let printer_ids = printer_ids.unwrap_or_else(|| config::current().default_printer_ids);
+    if printer_ids.is_empty() {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "No printers selected and no default printers saved"));
+    }
+
+    let ids = order_ids.clone();
+    let (profiles, content) = database::run(&state, move |conn| {
+        database::read_transaction(conn, |conn| {
+            let profiles = printer_ids.iter().map(|id| profile::get(conn, *id)).collect::<Result<Vec<_>, _>>()?;
+            Ok((profiles, build(conn, &ids, &username)?))
+        })
+    })
+    .await?;
+
+    let destinations: Vec<_> = profiles.iter().map(|p| p.destination()).collect();
+    let results =
+        send_to_profiles(&app, first, profiles, content.as_bytes(), Some(DocumentType::Kot), None, None, &state).await?;
+    for (destination, printed) in destinations.iter().zip(&results) {
+        if !printed.result.success || printed.result.queued {
+            continue;
+        }
+        for &order_id in &order_ids[1..] {
+            mark_printed(&state, order_id, destination).await;
+        }
+    }
+    info!("Consolidated KOT for orders {:?} sent", order_ids);
+    Ok(results)
+}
@@ -0,0 +1,127 @@
+// The app's SQLite database is shared through a connection pool: commands no longer queue behind
+// one Mutex<Connection>, and a panic mid-query can't poison the database for the rest of the app.
+// The app builds `DbState(open_pool(path)?)` at startup.
+
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Connection, DatabaseName, OpenFlags, Transaction, TransactionBehavior};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::db::DbState;
+
+use super::error::{ErrorCode, PrintError};
+use super::{config, migrations};
+
+pub type DbPool = Pool<SqliteConnectionManager>;
+pub type DbConnection = PooledConnection<SqliteConnectionManager>;
+
+const POOL_SIZE: u32 = 8;
+// How long a writer waits for another connection's lock before failing with "database is locked"
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// WAL lets report reads run alongside print-status writes instead of blocking on them.
+fn configure(conn: &mut Connection) -> rusqlite::Result<()> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    let mode: String = conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))?;
+    if !mode.eq_ignore_ascii_case("wal") {
+        warn!("Database stayed in {} journal mode", mode);
+    }
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    conn.pragma_update(None, "foreign_keys", true)
+}
+
+pub fn open_pool(path: impl AsRef<Path>) -> Result<DbPool, PrintError> {
+    Pool::builder()
+        .max_size(POOL_SIZE)
+        .build(SqliteConnectionManager::file(path).with_init(configure))
+        .map_err(|e| PrintError::new(ErrorCode::Database, format!("Could not open database: {}", e)))
+}
+
+fn checkout(pool: &DbPool) -> Result<DbConnection, PrintError> {
+    pool.get().map_err(|e| PrintError::new(ErrorCode::Database, format!("No database connection available: {}", e)))
+}
+
+pub(crate) fn connection(state: &DbState) -> Result<DbConnection, PrintError> {
+    checkout(&state.0)
+}
+
+// Runs `f` on tokio's blocking pool, so a slow query such as an end-of-day report can't stall the
+// runtime that print jobs and status polling share. Async code should use this, not connection().
+pub(crate) async fn run<T, F>(state: &DbState, f: F) -> Result<T, PrintError>
+where
+    T: Send + 'static,
+    F: FnOnce(&Connection) -> Result<T, PrintError> + Send + 'static,
+{
+    let pool = state.0.clone();
+    tokio::task::spawn_blocking(move || f(&checkout(&pool)?))
+        .await
+        .map_err(|e| PrintError::new(ErrorCode::Unknown, format!("Database task failed: {}", e)))?
+}
+
+// Multi-statement writes take the write lock up front (BEGIN IMMEDIATE): a deferred transaction
+// that reads first and upgrades later fails with SQLITE_BUSY at once instead of waiting it out.
+pub(crate) fn write_transaction<T>(
+    conn: &Connection,
+    f: impl FnOnce(&Connection) -> Result<T, PrintError>,
+) -> Result<T, PrintError> {
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    let value = f(&tx)?;
+    tx.commit()?;
+    Ok(value)
+}
+
+// Several reads that must agree with each other, e.g. a batch of orders rendered together.
+pub(crate) fn read_transaction<T>(
+    conn: &Connection,
+    f: impl FnOnce(&Connection) -> Result<T, PrintError>,
+) -> Result<T, PrintError> {
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Deferred)?;
+    let value = f(&tx)?;
+    tx.commit()?;
+    Ok(value)
+}
+
+// A snapshot that can be restored must at least open and pass SQLite's own check.
+fn check_backup_file(path: &str) -> Result<(), PrintError> {
+    let backup = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let result: String = backup.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    if result != "ok" {
+        return Err(PrintError::new(ErrorCode::Database, format!("{} failed the integrity check: {}", path, result)));
+    }
+    Ok(())
+}
+
+// Copies the live database to `path` with SQLite's online backup, so sales and printing carry on
+// while it runs.
+#[tauri::command]
+pub async fn backup_database(path: String, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    let target = path.clone();
+    run(&state, move |conn| Ok(conn.backup(DatabaseName::Main, &target, None)?)).await?;
+    info!("Database backed up to {}", path);
+    Ok(())
+}
+
+// Replaces the live database with the snapshot at `path`. The current contents are saved next to
+// the database file first (when it can still be read), and older snapshots are migrated forward.
+#[tauri::command]
+pub async fn restore_database(path: String, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    let pool = state.0.clone();
+    let source = path.clone();
+    tokio::task::spawn_blocking(move || {
+        check_backup_file(&source)?;
+        let mut conn = checkout(&pool)?;
+        if let Some(current) = conn.path().filter(|p| !p.is_empty()).map(|p| format!("{}.before-restore", p)) {
+            if let Err(e) = conn.backup(DatabaseName::Main, &current, None) {
+                warn!("Could not save the current database to {} before restoring: {}", current, e);
+            }
+        }
+        conn.restore(DatabaseName::Main, &source, None::<fn(rusqlite::backup::Progress)>)?;
+        migrations::run(&conn)?;
+        config::load(&conn)
+    })
+    .await
+    .map_err(|e| PrintError::new(ErrorCode::Unknown, format!("Database task failed: {}", e)))??;
+    info!("Database restored from {}", path);
+    Ok(())
+}
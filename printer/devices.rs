@@ -0,0 +1,180 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+#[cfg(windows)]
+use windows::Win32::Graphics::Printing::{
+    PRINTER_CHANGE_ADD_PRINTER, PRINTER_CHANGE_DELETE_PRINTER, PRINTER_CHANGE_SET_PRINTER,
+};
+
+use crate::db::DbState;
+
+use super::database;
+use super::error::PrintError;
+use super::profile::{self, Transport};
+#[cfg(windows)]
+use super::spooler;
+#[cfg(windows)]
+use super::win32::Printer;
+
+pub const PRINTERS_CHANGED_EVENT: &str = "printer://printers-changed";
+pub const DEVICE_ATTACHED_EVENT: &str = "printer://device-attached";
+pub const DEVICE_DETACHED_EVENT: &str = "printer://device-detached";
+
+const PORT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Wake up this often to notice a stop request even when nothing changes
+#[cfg(windows)]
+const WATCH_WAIT_MS: u32 = 1000;
+
+// Renames arrive as one removal plus one addition.
+#[derive(Debug, Clone, Serialize)]
+pub struct PrintersChangedEvent {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub printers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceEvent {
+    pub port: String,
+    // Saved USB profiles using this port, so queued jobs for them can be resumed
+    pub printer_ids: Vec<i64>,
+}
+
+fn printer_watch_stop() -> &'static Mutex<Option<Arc<AtomicBool>>> {
+    static STOP: OnceLock<Mutex<Option<Arc<AtomicBool>>>> = OnceLock::new();
+    STOP.get_or_init(|| Mutex::new(None))
+}
+
+fn port_watch_stop() -> &'static Mutex<Option<Arc<AtomicBool>>> {
+    static STOP: OnceLock<Mutex<Option<Arc<AtomicBool>>>> = OnceLock::new();
+    STOP.get_or_init(|| Mutex::new(None))
+}
+
+#[cfg(windows)]
+fn emit_printer_changes<R: Runtime>(app: &AppHandle<R>, known: &mut Vec<String>) {
+    let printers = match spooler::installed_printers() {
+        Ok(printers) => printers,
+        Err(e) => {
+            error!("Could not list printers after a spooler change: {}", e);
+            return;
+        }
+    };
+    let added: Vec<String> = printers.iter().filter(|p| !known.contains(p)).cloned().collect();
+    let removed: Vec<String> = known.iter().filter(|p| !printers.contains(p)).cloned().collect();
+    *known = printers.clone();
+
+    // SET_PRINTER also fires for queue and status changes that leave the list untouched
+    if added.is_empty() && removed.is_empty() {
+        return;
+    }
+    info!("Installed printers changed: added {:?}, removed {:?}", added, removed);
+    if let Err(e) = app.emit(PRINTERS_CHANGED_EVENT, PrintersChangedEvent { added, removed, printers }) {
+        error!("Failed to emit printers changed event: {}", e);
+    }
+}
+
+#[cfg(windows)]
+fn watch_printers<R: Runtime>(app: AppHandle<R>, stop: Arc<AtomicBool>) -> Result<(), PrintError> {
+    let mut known = spooler::installed_printers().unwrap_or_default();
+    let filter = PRINTER_CHANGE_ADD_PRINTER | PRINTER_CHANGE_DELETE_PRINTER | PRINTER_CHANGE_SET_PRINTER;
+
+    // The local print server sees every queue
+    let server = Printer::open(None)?;
+    let changes = server.watch(filter)?;
+    while !stop.load(Ordering::Relaxed) {
+        if changes.wait(WATCH_WAIT_MS)? {
+            emit_printer_changes(&app, &mut known);
+        }
+    }
+    Ok(())
+}
+
+// There is no spooler to watch.
+#[cfg(not(windows))]
+fn watch_printers<R: Runtime>(_app: AppHandle<R>, _stop: Arc<AtomicBool>) -> Result<(), PrintError> {
+    Err(PrintError::windows_only("Watching for printer changes"))
+}
+
+// For host setup: emits PRINTERS_CHANGED_EVENT whenever a printer is added, removed or renamed.
+pub fn start_printer_watcher<R: Runtime>(app: &AppHandle<R>) {
+    stop_printer_watcher();
+    let stop = Arc::new(AtomicBool::new(false));
+    *printer_watch_stop().lock().unwrap_or_else(|e| e.into_inner()) = Some(stop.clone());
+
+    let app = app.clone();
+    thread::spawn(move || {
+        if let Err(e) = watch_printers(app, stop) {
+            error!("Printer change watcher stopped: {}", e);
+        }
+    });
+}
+
+pub fn stop_printer_watcher() {
+    if let Some(stop) = printer_watch_stop().lock().unwrap_or_else(|e| e.into_inner()).take() {
+        stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn serial_ports() -> Vec<String> {
+    match serialport::available_ports() {
+        Ok(ports) => ports.into_iter().map(|p| p.port_name).collect(),
+        Err(e) => {
+            debug!("Could not list serial ports: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn printers_on_port<R: Runtime>(app: &AppHandle<R>, port: &str) -> Vec<i64> {
+    let state = app.state::<DbState>();
+    let Ok(conn) = database::connection(&state) else {
+        return Vec::new();
+    };
+    profile::list(&conn)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|p| p.transport == Transport::Usb && p.address.eq_ignore_ascii_case(port))
+        .filter_map(|p| p.id)
+        .collect()
+}
+
+fn emit_device_event<R: Runtime>(app: &AppHandle<R>, event: &str, port: &str) {
+    info!("Serial port {} {}", port, if event == DEVICE_ATTACHED_EVENT { "attached" } else { "detached" });
+    let payload = DeviceEvent { port: port.to_string(), printer_ids: printers_on_port(app, port) };
+    if let Err(e) = app.emit(event, payload) {
+        error!("Failed to emit device event: {}", e);
+    }
+}
+
+// For host setup: emits DEVICE_ATTACHED_EVENT / DEVICE_DETACHED_EVENT as serial ports come and go.
+pub fn start_port_watcher<R: Runtime>(app: &AppHandle<R>) {
+    stop_port_watcher();
+    let stop = Arc::new(AtomicBool::new(false));
+    *port_watch_stop().lock().unwrap_or_else(|e| e.into_inner()) = Some(stop.clone());
+
+    let app = app.clone();
+    thread::spawn(move || {
+        let mut known = serial_ports();
+        while !stop.load(Ordering::Relaxed) {
+            thread::sleep(PORT_POLL_INTERVAL);
+            let ports = serial_ports();
+            for port in ports.iter().filter(|p| !known.contains(p)) {
+                emit_device_event(&app, DEVICE_ATTACHED_EVENT, port);
+            }
+            for port in known.iter().filter(|p| !ports.contains(p)) {
+                emit_device_event(&app, DEVICE_DETACHED_EVENT, port);
+            }
+            known = ports;
+        }
+    });
+}
+
+pub fn stop_port_watcher() {
+    if let Some(stop) = port_watch_stop().lock().unwrap_or_else(|e| e.into_inner()).take() {
+        stop.store(true, Ordering::Relaxed);
+    }
+}
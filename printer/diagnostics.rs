@@ -0,0 +1,179 @@
+// A zip the cashier can attach to a support ticket: enough to see what the printers were doing
+// without a remote desktop session. Anything that works as a credential is redacted before it is
+// written.
+
+use rusqlite::Connection;
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Runtime};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::db::DbState;
+
+use super::archive;
+use super::config::{self, PrinterConfig};
+use super::database;
+use super::error::{ErrorCode, PrintError};
+use super::health::{self, Health};
+use super::logging;
+use super::migrations;
+use super::profile::{self, PrinterProfile};
+use super::queue;
+use super::secrets::{self, REDACTED};
+use super::webhooks;
+
+// Failed payloads included as hex dumps, newest first
+const FAILED_PAYLOADS: u32 = 20;
+// Only the tail of each log file; support wants the last few hours, not the last year
+const LOG_TAIL_BYTES: u64 = 512 * 1024;
+
+#[derive(Serialize)]
+struct Summary {
+    terminal_id: String,
+    generated_at: String,
+    plugin_version: &'static str,
+    schema_version: u32,
+    health: Health,
+}
+
+// `user:password@` in printer URLs, e.g. an IPP queue behind basic auth
+fn redact_url(address: &str) -> String {
+    let Some((scheme, rest)) = address.split_once("://") else {
+        return address.to_string();
+    };
+    let authority_end = rest.find('/').unwrap_or(rest.len());
+    match rest[..authority_end].rfind('@') {
+        Some(at) => format!("{}://{}@{}", scheme, REDACTED, &rest[at + 1..]),
+        None => address.to_string(),
+    }
+}
+
+fn redact_profile(mut profile: PrinterProfile) -> PrinterProfile {
+    profile.address = redact_url(&profile.address);
+    if profile.snmp_community.is_some() {
+        profile.snmp_community = Some(REDACTED.to_string());
+    }
+    profile
+}
+
+// The credentials as get_printer_settings hides them, plus any in the URLs.
+fn redact_config(config: PrinterConfig) -> PrinterConfig {
+    let mut config = secrets::redacted(config);
+    if let Some(sms) = config.sms.as_mut() {
+        sms.url = webhooks::redact_url(&sms.url);
+    }
+    for hook in &mut config.webhooks {
+        hook.url = webhooks::redact_url(&hook.url);
+    }
+    config
+}
+
+// The classic 16-bytes-a-line layout: offset, hex, then printable ASCII.
+fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (line, chunk) in bytes.chunks(16).enumerate() {
+        let _ = write!(out, "{:08x}  ", line * 16);
+        for i in 0..16 {
+            match chunk.get(i) {
+                Some(byte) => {
+                    let _ = write!(out, "{:02x} ", byte);
+                }
+                None => out.push_str("   "),
+            }
+            if i == 7 {
+                out.push(' ');
+            }
+        }
+        out.push(' ');
+        out.extend(chunk.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }));
+        out.push('\n');
+    }
+    out
+}
+
+fn log_tail(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(LOG_TAIL_BYTES)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    Ok(tail)
+}
+
+fn to_json(value: &impl Serialize) -> Result<Vec<u8>, PrintError> {
+    serde_json::to_vec_pretty(value).map_err(|e| PrintError::new(ErrorCode::Unknown, e.to_string()))
+}
+
+fn write_bundle(conn: &Connection, path: &Path, health: Health, log_dirs: Vec<PathBuf>) -> Result<(), PrintError> {
+    let file = File::create(path).map_err(|e| PrintError::from_io(&format!("Could not create {}", path.display()), &e))?;
+    let mut zip = ZipWriter::new(file);
+    let mut add = |name: &str, contents: &[u8]| -> Result<(), PrintError> {
+        zip.start_file(name, SimpleFileOptions::default())
+            .map_err(|e| PrintError::new(ErrorCode::Unknown, format!("Could not add {} to the bundle: {}", name, e)))?;
+        zip.write_all(contents).map_err(|e| PrintError::from_io(&format!("Could not write {}", name), &e))
+    };
+
+    let summary = Summary {
+        terminal_id: config::terminal_id(),
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        plugin_version: env!("CARGO_PKG_VERSION"),
+        schema_version: migrations::schema_version(conn)?,
+        health,
+    };
+    add("summary.json", &to_json(&summary)?)?;
+
+    let profiles: Vec<PrinterProfile> = profile::list(conn)?.into_iter().map(redact_profile).collect();
+    add("printers.json", &to_json(&profiles)?)?;
+    add("config.json", &to_json(&redact_config(config::current()))?)?;
+    add("queue.json", &to_json(&queue::load(conn, None)?)?)?;
+
+    for (job, payload) in archive::recent_failures(conn, FAILED_PAYLOADS)? {
+        let header = format!(
+            "Job {} for order {} to {} at {}\nError: {}\n\n",
+            job.id,
+            job.order_id,
+            job.destination.key(),
+            job.created_at,
+            job.error.as_deref().unwrap_or("")
+        );
+        add(&format!("failed/{}.txt", job.id), (header + &hex_dump(&payload)).as_bytes())?;
+    }
+
+    let log_files = log_dirs
+        .into_iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().map_or(false, |ext| ext == "log"));
+    for log_file in log_files {
+        let name = log_file.file_name().unwrap_or_default().to_string_lossy().to_string();
+        match log_tail(&log_file) {
+            Ok(tail) => add(&format!("logs/{}", name), &tail)?,
+            Err(e) => warn!("Left {} out of the diagnostics bundle: {}", log_file.display(), e),
+        }
+    }
+
+    zip.finish().map_err(|e| PrintError::new(ErrorCode::Unknown, format!("Could not finish the bundle: {}", e)))?;
+    Ok(())
+}
+
+// Writes the bundle to `path` (a .zip the user picked) and returns where it went.
+#[tauri::command]
+pub async fn export_diagnostics<R: Runtime>(
+    app: AppHandle<R>,
+    path: String,
+    state: tauri::State<'_, DbState>,
+) -> Result<String, PrintError> {
+    let health = health::check(&state).await;
+    // The plugin's own log plus whatever the host app writes to its log dir
+    let mut log_dirs: Vec<PathBuf> = logging::directory().into_iter().chain(app.path().app_log_dir().ok()).collect();
+    log_dirs.dedup();
+    let target = PathBuf::from(&path);
+    database::run(&state, move |conn| write_bundle(conn, &target, health, log_dirs)).await?;
+    info!("Wrote diagnostics bundle to {}", path);
+    Ok(path)
+}
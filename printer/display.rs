@@ -0,0 +1,115 @@
+// The pole display facing the customer: a serial VFD speaking the ESC/POS display command set
+// (Epson DM-D and the many clones sold with POS terminals), two lines of `columns` characters.
+// The till calls display_item while ringing items up and display_thank_you once it is paid.
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use super::config::{self, PrinterConfig};
+use super::error::{ErrorCode, PrintError};
+use super::serial::{self, SerialSettings};
+
+const INITIALIZE: &[u8] = &[0x1B, 0x40];
+const CLEAR: &[u8] = &[0x0C];
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomerDisplay {
+    pub port_name: String,
+    pub baud_rate: u32,
+    pub serial: SerialSettings,
+    // Characters per line; 20 on nearly every 2x20 VFD
+    pub columns: usize,
+    pub thank_you_message: String,
+}
+
+impl Default for CustomerDisplay {
+    fn default() -> Self {
+        CustomerDisplay {
+            port_name: String::new(),
+            baud_rate: 9600,
+            serial: SerialSettings::default(),
+            columns: 20,
+            thank_you_message: "Thank you!".to_string(),
+        }
+    }
+}
+
+// One writer at a time; a second open of the COM port would fail with access denied
+fn port_lock() -> &'static Mutex<()> {
+    static PORT: OnceLock<Mutex<()>> = OnceLock::new();
+    PORT.get_or_init(|| Mutex::new(()))
+}
+
+// VFD character generators stop at ASCII; anything else would show as garbage.
+fn fit(text: &str, columns: usize) -> String {
+    text.chars().map(|c| if c.is_ascii() && !c.is_ascii_control() { c } else { '?' }).take(columns).collect()
+}
+
+fn spread(left: &str, right: &str, columns: usize) -> String {
+    let right = fit(right, columns);
+    let left = fit(left, columns.saturating_sub(right.len() + 1));
+    format!("{}{:>width$}", left, right, width = columns - left.len())
+}
+
+fn center(text: &str, columns: usize) -> String {
+    let text = fit(text, columns);
+    format!("{:^width$}", text, width = columns)
+}
+
+// Clears the screen and writes both lines from their first column.
+fn frame(top: &str, bottom: &str) -> Vec<u8> {
+    let mut bytes = [INITIALIZE, CLEAR].concat();
+    for (row, line) in [(1u8, top), (2u8, bottom)] {
+        // US $ n m: move the cursor to column n, row m
+        bytes.extend_from_slice(&[0x1F, 0x24, 1, row]);
+        bytes.extend_from_slice(line.as_bytes());
+    }
+    bytes
+}
+
+fn settings(config: &PrinterConfig) -> Result<CustomerDisplay, PrintError> {
+    config
+        .customer_display
+        .clone()
+        .filter(|display| !display.port_name.is_empty())
+        .ok_or_else(|| PrintError::new(ErrorCode::InvalidSettings, "No customer display configured"))
+}
+
+async fn show(display: &CustomerDisplay, bytes: &[u8]) -> Result<(), PrintError> {
+    let _guard = port_lock().lock().await;
+    let mut port = serial::open(&display.port_name, display.baud_rate, &display.serial, WRITE_TIMEOUT)?;
+    port.write_all(bytes)
+        .and_then(|_| port.flush())
+        .map_err(|e| PrintError::from_io(&format!("Failed to write to display on {}", display.port_name), &e))
+}
+
+// The item just rung up with its price on top, the running total below.
+#[tauri::command]
+pub async fn display_item(name: String, quantity: u32, price: f64, total: f64) -> Result<(), PrintError> {
+    let display = settings(&config::current())?;
+    let item = if quantity > 1 { format!("{} x{}", name, quantity) } else { name };
+    let top = spread(&item, &format!("{:.2}", price * quantity as f64), display.columns);
+    let bottom = spread("TOTAL", &format!("{:.2}", total), display.columns);
+    show(&display, &frame(&top, &bottom)).await
+}
+
+// After payment; `message` overrides the configured thank-you line.
+#[tauri::command]
+pub async fn display_thank_you(message: Option<String>) -> Result<(), PrintError> {
+    let config = config::current();
+    let display = settings(&config)?;
+    let top = center(message.as_deref().unwrap_or(&display.thank_you_message), display.columns);
+    let bottom = center(&config.business_name, display.columns);
+    show(&display, &frame(&top, &bottom)).await
+}
+
+#[tauri::command]
+pub async fn clear_display() -> Result<(), PrintError> {
+    let display = settings(&config::current())?;
+    show(&display, &[INITIALIZE, CLEAR].concat()).await
+}
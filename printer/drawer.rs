@@ -0,0 +1,109 @@
+// Kicking the cash drawer. Counters with two drawers on one printer wire them to pins 2 and 5 of
+// the kick-out connector, and each cashier is assigned their own in `drawer_assignments`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::DbState;
+
+use super::config;
+use super::database;
+use super::error::{ErrorCode, PrintError};
+use super::escpos;
+use super::profile;
+use super::snippets;
+use super::vendor::{self, DrawerPin};
+use super::{job_timeout, print_to_destination, DocumentType};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DrawerAssignment {
+    pub printer_id: i64,
+    #[serde(default)]
+    pub pin: DrawerPin,
+}
+
+// An explicit printer (and pin, default 2) wins; otherwise `username`'s assigned drawer opens.
+#[tauri::command]
+pub async fn open_cash_drawer(
+    printer_id: Option<i64>,
+    pin: Option<DrawerPin>,
+    username: Option<String>,
+    state: tauri::State<'_, DbState>,
+) -> Result<(), PrintError> {
+    let assignment = match (printer_id, &username) {
+        (Some(printer_id), _) => DrawerAssignment { printer_id, pin: pin.unwrap_or_default() },
+        (None, Some(username)) => config::current().drawer_assignments.get(username).copied().ok_or_else(|| {
+            PrintError::new(ErrorCode::InvalidSettings, format!("No cash drawer is assigned to {}", username))
+        })?,
+        (None, None) => return Err(PrintError::new(ErrorCode::InvalidSettings, "Name a printer or a cashier")),
+    };
+    let profile = database::run(&state, move |conn| profile::get(conn, assignment.printer_id)).await?;
+
+    let kick = vendor::commands(profile.vendor_profile).open_drawer_on(assignment.pin);
+    if kick.is_empty() {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, format!("{} has no cash drawer", profile.name)));
+    }
+    print_to_destination(&kick, &profile.destination(), job_timeout(None, Some(&profile)), None).await?;
+    info!(
+        "Opened cash drawer on {} ({:?}) for {}",
+        profile.name,
+        assignment.pin,
+        username.as_deref().unwrap_or("unnamed cashier")
+    );
+    Ok(())
+}
+
+// ESC p and DLE DC4 pulse the kick-out connector on ESC/POS printers; BEL does it on Star. Only
+// top-level commands count, so image data that happens to hold the same bytes doesn't.
+fn kicks(content: &[u8]) -> bool {
+    let mut i = 0;
+    while i < content.len() {
+        match content[i] {
+            0x07 => return true,
+            0x1B | 0x1D | 0x1C | 0x10 => {
+                let command = &content[i..];
+                if command.starts_with(b"\x1B\x70") || command.starts_with(b"\x10\x14") {
+                    return true;
+                }
+                i += escpos::command_length(command);
+            }
+            _ => i += 1,
+        }
+    }
+    false
+}
+
+// Jobs that only need allow-print, which doesn't grant the drawer: text jobs and replays of
+// archived ones. One that would kick it, with the bytes themselves or a snippet such as
+// `{{cmd "open_drawer"}}`, is refused. New byte jobs come under allow-print-raw and aren't checked.
+pub(crate) fn refuse_kick(content: &[u8], document_type: Option<DocumentType>) -> Result<(), PrintError> {
+    let expanded = snippets::expand(content, document_type)?;
+    if kicks(&expanded) {
+        warn!("Refused a print job that would open the cash drawer");
+        return Err(PrintError::new(
+            ErrorCode::InvalidContent,
+            "Print jobs can't open the cash drawer; use open_cash_drawer",
+        ));
+    }
+    Ok(())
+}
+
+// `None` removes the cashier's drawer.
+#[tauri::command]
+pub fn set_drawer_assignment(
+    username: String,
+    assignment: Option<DrawerAssignment>,
+    state: tauri::State<'_, DbState>,
+) -> Result<(), PrintError> {
+    if let Some(assignment) = &assignment {
+        let conn = database::connection(&state)?;
+        profile::get(&conn, assignment.printer_id)?;
+    }
+    config::update_persisted(&state, |config| match assignment {
+        Some(assignment) => {
+            config.drawer_assignments.insert(username, assignment);
+        }
+        None => {
+            config.drawer_assignments.remove(&username);
+        }
+    })
+}
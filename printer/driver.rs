@@ -0,0 +1,80 @@
+// Transports supplied by the host app, such as a cloud print service or a vendor bridge. They are
+// registered on the plugin Builder and addressed as `Destination::Driver { driver, address }`, so
+// profiles, routes, failover and status polling treat them like the built-in transports.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use super::error::{ErrorCode, PrintError};
+
+pub trait PrinterDriver: Send + Sync + 'static {
+    // Delivers one job. Runs on the blocking pool and should give up after `timeout`.
+    fn print(&self, address: &str, content: &[u8], timeout: Duration) -> Result<(), PrintError>;
+
+    // Whether `query_status` can answer; drivers without it are treated as write-only printers.
+    fn supports_status(&self) -> bool {
+        false
+    }
+
+    // The answer to DLE EOT n for each `n`, in order.
+    fn query_status(&self, address: &str, queries: &[u8], timeout: Duration) -> Result<Vec<u8>, PrintError> {
+        let _ = (queries, timeout);
+        Err(PrintError::new(ErrorCode::InvalidSettings, format!("{} cannot report its status", address)))
+    }
+
+    // Checks an address before it is saved or probed.
+    fn check(&self, address: &str) -> Result<(), PrintError> {
+        let _ = address;
+        Ok(())
+    }
+}
+
+fn drivers() -> &'static Mutex<HashMap<String, Arc<dyn PrinterDriver>>> {
+    static DRIVERS: OnceLock<Mutex<HashMap<String, Arc<dyn PrinterDriver>>>> = OnceLock::new();
+    DRIVERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub(crate) fn register(name: String, driver: Arc<dyn PrinterDriver>) {
+    info!("Registered printer driver {}", name);
+    drivers().lock().unwrap_or_else(|e| e.into_inner()).insert(name, driver);
+}
+
+pub(crate) fn get(name: &str) -> Result<Arc<dyn PrinterDriver>, PrintError> {
+    drivers()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned()
+        .ok_or_else(|| PrintError::new(ErrorCode::InvalidSettings, format!("No printer driver named {} is registered", name)))
+}
+
+pub(crate) fn supports_status(name: &str) -> bool {
+    get(name).map_or(false, |driver| driver.supports_status())
+}
+
+pub(crate) async fn print(name: &str, address: &str, content: &[u8], timeout: Duration) -> Result<(), PrintError> {
+    let driver = get(name)?;
+    let address = address.to_string();
+    let content = content.to_vec();
+    tokio::task::spawn_blocking(move || driver.print(&address, &content, timeout))
+        .await
+        .map_err(|e| PrintError::new(ErrorCode::Unknown, format!("Printer driver {} failed: {}", name, e)))?
+}
+
+pub(crate) async fn query_status(name: &str, address: &str, queries: &[u8], timeout: Duration) -> Result<Vec<u8>, PrintError> {
+    let driver = get(name)?;
+    let address = address.to_string();
+    let queries = queries.to_vec();
+    tokio::task::spawn_blocking(move || driver.query_status(&address, &queries, timeout))
+        .await
+        .map_err(|e| PrintError::new(ErrorCode::Unknown, format!("Printer driver {} failed: {}", name, e)))?
+}
+
+// For the settings UI's transport picker.
+#[tauri::command]
+pub fn list_printer_drivers() -> Vec<String> {
+    let mut names: Vec<String> = drivers().lock().unwrap_or_else(|e| e.into_inner()).keys().cloned().collect();
+    names.sort();
+    names
+}
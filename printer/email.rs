@@ -0,0 +1,166 @@
+// Emails the PDF receipt to a customer who asked for one instead of (or as well as) the paper
+// slip. Sent through the outlet's SMTP account or a transactional email API, per `email` in the
+// settings; every attempt is recorded in receipt_emails so the till can show whether it went.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::db::DbState;
+
+use super::config::{self, EmailSettings, EmailTransport};
+use super::database;
+use super::error::{ErrorCode, PrintError};
+use super::fetch_order_details;
+use super::receipt;
+use super::sequences::{self, BILL_SERIES};
+
+const API_TIMEOUT: Duration = Duration::from_secs(30);
+// Implicit TLS; any other port upgrades with STARTTLS
+const SMTPS_PORT: u16 = 465;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReceiptEmail {
+    pub id: i64,
+    pub order_id: i64,
+    pub address: String,
+    pub sent: bool,
+    pub error: Option<String>,
+    pub created_at: String,
+}
+
+impl ReceiptEmail {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(ReceiptEmail {
+            id: row.get("id")?,
+            order_id: row.get("order_id")?,
+            address: row.get("address")?,
+            sent: row.get("sent")?,
+            error: row.get("error")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+}
+
+struct Email {
+    to: String,
+    subject: String,
+    text: String,
+    attachment_name: String,
+    pdf: Vec<u8>,
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, PrintError> {
+    address
+        .parse()
+        .map_err(|_| PrintError::new(ErrorCode::InvalidContent, format!("{} is not an email address", address)))
+}
+
+fn send_failed(e: impl std::fmt::Display) -> PrintError {
+    PrintError::new(ErrorCode::ConnectionFailed, format!("Could not send the receipt email: {}", e))
+}
+
+async fn send_smtp(settings: &EmailSettings, host: &str, port: u16, username: &str, password: &str, email: Email) -> Result<(), PrintError> {
+    let pdf_type = ContentType::parse("application/pdf").map_err(send_failed)?;
+    let message = Message::builder()
+        .from(mailbox(&settings.from)?)
+        .to(mailbox(&email.to)?)
+        .subject(email.subject)
+        .multipart(
+            MultiPart::mixed()
+                .singlepart(SinglePart::plain(email.text))
+                .singlepart(Attachment::new(email.attachment_name).body(email.pdf, pdf_type)),
+        )
+        .map_err(send_failed)?;
+
+    let builder = if port == SMTPS_PORT {
+        AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+    }
+    .map_err(send_failed)?;
+    let mailer = builder.port(port).credentials(Credentials::new(username.to_string(), password.to_string())).build();
+    mailer.send(message).await.map_err(send_failed)?;
+    Ok(())
+}
+
+// The JSON shape Resend, Postmark-style relays and most in-house mail services accept.
+async fn send_api(settings: &EmailSettings, url: &str, api_key: &str, email: Email) -> Result<(), PrintError> {
+    mailbox(&email.to)?;
+    let body = serde_json::json!({
+        "from": settings.from,
+        "to": [email.to],
+        "subject": email.subject,
+        "text": email.text,
+        "attachments": [{ "filename": email.attachment_name, "content": STANDARD.encode(&email.pdf) }],
+    });
+    let client = reqwest::Client::builder().timeout(API_TIMEOUT).build().map_err(send_failed)?;
+    let response = client.post(url).bearer_auth(api_key).json(&body).send().await.map_err(send_failed)?;
+    if !response.status().is_success() {
+        return Err(send_failed(format!("the email service answered {}", response.status())));
+    }
+    Ok(())
+}
+
+fn record(conn: &Connection, order_id: i64, address: &str, error: Option<String>) -> Result<ReceiptEmail, PrintError> {
+    conn.execute(
+        "INSERT INTO receipt_emails (order_id, address, sent, error) VALUES (?1, ?2, ?3, ?4)",
+        params![order_id, address, error.is_none(), error],
+    )?;
+    let email = conn.query_row("SELECT * FROM receipt_emails WHERE id = ?1", params![conn.last_insert_rowid()], ReceiptEmail::from_row)?;
+    Ok(email)
+}
+
+// Sends the order's PDF receipt to `address`. A failed send is recorded and returned as the error.
+#[tauri::command]
+pub async fn email_receipt(order_id: i64, address: String, state: tauri::State<'_, DbState>) -> Result<ReceiptEmail, PrintError> {
+    let config = config::current();
+    let Some(settings) = config.email.clone() else {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "Receipt email is not set up"));
+    };
+    let address = address.trim().to_string();
+    let (order, bill_number) = database::run(&state, move |conn| {
+        Ok((fetch_order_details(conn, order_id)?, sequences::number_for_order(conn, BILL_SERIES, order_id)?))
+    })
+    .await?;
+
+    let email = Email {
+        to: address.clone(),
+        subject: format!("{} #{}", settings.subject, bill_number),
+        text: format!("Thank you for your order at {}. Your receipt is attached.", config.business_name),
+        attachment_name: format!("receipt-{}.pdf", bill_number.replace(['/', '\\'], "-")),
+        pdf: receipt::render(&order, &bill_number, &config),
+    };
+    let sent = match &settings.transport {
+        EmailTransport::Smtp { host, port, username, password } => send_smtp(&settings, host, *port, username, password, email).await,
+        EmailTransport::Api { url, api_key } => send_api(&settings, url, api_key, email).await,
+    };
+
+    let error = sent.as_ref().err().map(|e| e.to_string());
+    let recorded = database::run(&state, move |conn| record(conn, order_id, &address, error)).await?;
+    match sent {
+        Ok(()) => {
+            info!("Emailed receipt for order {} to {}", order_id, recorded.address);
+            Ok(recorded)
+        }
+        Err(e) => {
+            warn!("Receipt email for order {} failed: {}", order_id, e);
+            Err(e)
+        }
+    }
+}
+
+// Every attempt for the order, newest first.
+#[tauri::command]
+pub fn list_receipt_emails(order_id: i64, state: tauri::State<'_, DbState>) -> Result<Vec<ReceiptEmail>, PrintError> {
+    let conn = database::connection(&state)?;
+    let mut stmt = conn.prepare("SELECT * FROM receipt_emails WHERE order_id = ?1 ORDER BY id DESC")?;
+    let emails = stmt.query_map(params![order_id], ReceiptEmail::from_row)?.collect::<rusqlite::Result<_>>()?;
+    Ok(emails)
+}
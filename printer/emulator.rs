@@ -0,0 +1,84 @@
+// The virtual printer: instead of printing, each job is rendered the way a receipt printer would
+// lay it out and written to a directory as `.txt` and `.png`, next to the raw `.bin` payload.
+// Queueing, retries, failover and status all run as they would against real hardware.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use super::clock;
+use super::config::PrinterConfig;
+use super::error::{ErrorCode, PrintError};
+use super::{preview, raster, Destination};
+
+const DEFAULT_COLUMNS: u32 = 48;
+
+// Jobs landing in the same second still get distinct file names.
+static JOB_NUMBER: AtomicU32 = AtomicU32::new(0);
+static SIMULATED_JOBS: AtomicU32 = AtomicU32::new(0);
+
+pub(crate) fn check_directory(directory: &str) -> Result<(), PrintError> {
+    if directory.trim().is_empty() {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "The virtual printer needs an output directory"));
+    }
+    std::fs::create_dir_all(directory).map_err(|e| PrintError::from_io(&format!("Could not create {}", directory), &e))
+}
+
+fn write_job(content: &[u8], directory: &str, columns: usize) -> Result<String, PrintError> {
+    check_directory(directory)?;
+    let number = JOB_NUMBER.fetch_add(1, Ordering::Relaxed) % 10_000;
+    let stem = Path::new(directory).join(format!("{}-{:04}", clock::now().format("%Y%m%d-%H%M%S"), number));
+
+    let write = |extension: &str, data: &[u8]| {
+        let path = stem.with_extension(extension);
+        std::fs::write(&path, data).map_err(|e| PrintError::from_io(&format!("Could not write {}", path.display()), &e))
+    };
+    write("bin", content)?;
+    let mut text = preview::render_text(content, columns).join("\n");
+    text.push('\n');
+    write("txt", text.as_bytes())?;
+    write("png", &raster::render_png(content, columns)?)?;
+    Ok(stem.display().to_string())
+}
+
+pub(crate) async fn print(content: &[u8], directory: &str, columns: u32) -> Result<(), PrintError> {
+    let content = content.to_vec();
+    let directory = directory.to_string();
+    let stem = tokio::task::spawn_blocking(move || write_job(&content, &directory, columns as usize))
+        .await
+        .map_err(|e| PrintError::new(ErrorCode::Unknown, format!("Virtual printer task failed: {}", e)))??;
+    info!("Virtual printer wrote {}.txt/.png", stem);
+    Ok(())
+}
+
+// Simulation mode stand-in for any destination: each printer gets its own folder, named after
+// its key, under the simulation directory.
+pub(crate) async fn simulate(
+    content: &[u8],
+    destination: &Destination,
+    config: &PrinterConfig,
+    job_timeout: Duration,
+) -> Result<(), PrintError> {
+    let latency = Duration::from_millis(config.simulation_latency_ms);
+    if latency > job_timeout {
+        tokio::time::sleep(job_timeout).await;
+        return Err(PrintError::new(ErrorCode::Timeout, format!("Simulated printer {} timed out", destination.key())));
+    }
+    tokio::time::sleep(latency).await;
+
+    let job = SIMULATED_JOBS.fetch_add(1, Ordering::Relaxed) + 1;
+    if config.simulation_fail_every > 0 && job % config.simulation_fail_every == 0 {
+        return Err(PrintError::new(config.simulation_failure, format!("Simulated failure on {}", destination.key())));
+    }
+
+    let root = match config.simulation_directory.as_str() {
+        "" => std::env::temp_dir().join("printer-simulation"),
+        directory => PathBuf::from(directory),
+    };
+    let folder: String = destination.key().chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    let columns = match destination {
+        Destination::Virtual { columns, .. } => *columns,
+        _ => DEFAULT_COLUMNS,
+    };
+    print(content, &root.join(folder).display().to_string(), columns).await
+}
@@ -0,0 +1,164 @@
+use rusqlite::types::ToSqlOutput;
+use rusqlite::ToSql;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+
+use crate::db::Error;
+
+use super::config;
+use super::messages;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ErrorCode {
+    InvalidContent,
+    InvalidSettings,
+    PortNotFound,
+    Timeout,
+    PaperOut,
+    CoverOpen,
+    AccessDenied,
+    SpoolerError,
+    ConnectionFailed,
+    DuplicateJob,
+    Database,
+    Unknown,
+}
+
+impl ErrorCode {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidContent => "InvalidContent",
+            ErrorCode::InvalidSettings => "InvalidSettings",
+            ErrorCode::PortNotFound => "PortNotFound",
+            ErrorCode::Timeout => "Timeout",
+            ErrorCode::PaperOut => "PaperOut",
+            ErrorCode::CoverOpen => "CoverOpen",
+            ErrorCode::AccessDenied => "AccessDenied",
+            ErrorCode::SpoolerError => "SpoolerError",
+            ErrorCode::ConnectionFailed => "ConnectionFailed",
+            ErrorCode::DuplicateJob => "DuplicateJob",
+            ErrorCode::Database => "Database",
+            ErrorCode::Unknown => "Unknown",
+        }
+    }
+
+    pub(crate) fn parse(value: &str) -> Self {
+        match value {
+            "InvalidContent" => ErrorCode::InvalidContent,
+            "InvalidSettings" => ErrorCode::InvalidSettings,
+            "PortNotFound" => ErrorCode::PortNotFound,
+            "Timeout" => ErrorCode::Timeout,
+            "PaperOut" => ErrorCode::PaperOut,
+            "CoverOpen" => ErrorCode::CoverOpen,
+            "AccessDenied" => ErrorCode::AccessDenied,
+            "SpoolerError" => ErrorCode::SpoolerError,
+            "ConnectionFailed" => ErrorCode::ConnectionFailed,
+            "DuplicateJob" => ErrorCode::DuplicateJob,
+            "Database" => ErrorCode::Database,
+            _ => ErrorCode::Unknown,
+        }
+    }
+}
+
+impl ToSql for ErrorCode {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintError {
+    pub code: ErrorCode,
+    // Technical detail for logs and support
+    pub message: String,
+    // What the cashier sees, in the configured language
+    #[serde(default)]
+    pub user_message: String,
+}
+
+impl PrintError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        PrintError {
+            code,
+            message: message.into(),
+            user_message: messages::user_message(code, config::language()).to_string(),
+        }
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn from_win32(context: &str, error_code: u32) -> Self {
+        // Win32 error codes we can map to something the UI understands
+        const ERROR_FILE_NOT_FOUND: u32 = 2;
+        const ERROR_ACCESS_DENIED: u32 = 5;
+        const ERROR_OUT_OF_PAPER: u32 = 28;
+        const ERROR_SEM_TIMEOUT: u32 = 121;
+        const ERROR_INVALID_PRINTER_NAME: u32 = 1801;
+        const ERROR_TIMEOUT: u32 = 1460;
+        const ERROR_PRINTER_NOT_FOUND: u32 = 3012;
+
+        let code = match error_code {
+            ERROR_FILE_NOT_FOUND | ERROR_INVALID_PRINTER_NAME | ERROR_PRINTER_NOT_FOUND => ErrorCode::PortNotFound,
+            ERROR_ACCESS_DENIED => ErrorCode::AccessDenied,
+            ERROR_OUT_OF_PAPER => ErrorCode::PaperOut,
+            ERROR_SEM_TIMEOUT | ERROR_TIMEOUT => ErrorCode::Timeout,
+            _ => ErrorCode::SpoolerError,
+        };
+        PrintError::new(code, format!("{} failed with error code: {}", context, error_code))
+    }
+
+    // For the spooler and GDI paths when running anywhere but Windows.
+    #[cfg(not(windows))]
+    pub(crate) fn windows_only(action: &str) -> Self {
+        PrintError::new(ErrorCode::SpoolerError, format!("{} needs the Windows print spooler", action))
+    }
+
+    pub(crate) fn from_io(context: &str, e: &io::Error) -> Self {
+        PrintError::new(code_for_io_kind(e.kind()), format!("{}: {}", context, e))
+    }
+
+    pub(crate) fn from_serial(context: &str, e: &serialport::Error) -> Self {
+        let code = match e.kind() {
+            serialport::ErrorKind::NoDevice => ErrorCode::PortNotFound,
+            serialport::ErrorKind::InvalidInput => ErrorCode::InvalidSettings,
+            serialport::ErrorKind::Io(kind) => code_for_io_kind(kind),
+            serialport::ErrorKind::Unknown => ErrorCode::Unknown,
+        };
+        PrintError::new(code, format!("{}: {}", context, e))
+    }
+}
+
+fn code_for_io_kind(kind: io::ErrorKind) -> ErrorCode {
+    match kind {
+        io::ErrorKind::NotFound => ErrorCode::PortNotFound,
+        io::ErrorKind::PermissionDenied => ErrorCode::AccessDenied,
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => ErrorCode::Timeout,
+        io::ErrorKind::ConnectionRefused
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::NotConnected
+        | io::ErrorKind::AddrNotAvailable
+        | io::ErrorKind::BrokenPipe => ErrorCode::ConnectionFailed,
+        _ => ErrorCode::Unknown,
+    }
+}
+
+impl fmt::Display for PrintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for PrintError {}
+
+impl From<rusqlite::Error> for PrintError {
+    fn from(e: rusqlite::Error) -> Self {
+        PrintError::new(ErrorCode::Database, e.to_string())
+    }
+}
+
+impl From<Error> for PrintError {
+    fn from(e: Error) -> Self {
+        PrintError::new(ErrorCode::Database, e.to_string())
+    }
+}
@@ -0,0 +1,357 @@
+// Byte-level ESC/POS builder. Every method appends to the buffer and returns `&mut Self` so
+// documents read top to bottom. Built for a Star Line Mode vendor, the same calls emit Star commands.
+
+use super::vendor::{DrawerPin, Protocol, VendorCommands};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    // 12x24, 48 columns on 80mm paper
+    A,
+    // 9x17, 64 columns on 80mm paper
+    B,
+}
+
+// Where page mode starts printing from and which way text runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageDirection {
+    LeftToRight,
+    BottomToTop,
+    RightToLeft,
+    TopToBottom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Density {
+    Light,
+    Normal,
+    Dark,
+}
+
+// Argument bytes after the ESC/GS/FS/DLE commands printers commonly receive. `None` means
+// the length is encoded in the arguments and `command_length` works it out.
+fn fixed_arguments(prefix: u8, command: u8) -> Option<usize> {
+    match (prefix, command) {
+        (0x1B, b'p') => Some(3),
+        (0x1B, b'W') => Some(8),
+        (0x1B, b'$') | (0x1B, b'\\') => Some(2),
+        (0x1B, b'@' | b'2' | b'L' | b'S' | b'<' | b'i' | b'm' | 0x0C) => Some(0),
+        (0x1B, b'*' | b'D' | 0x1D | 0x1E) => None,
+        (0x1B, _) => Some(1),
+        (0x1D, b'$' | b'\\' | b'L' | b'W' | b'P') => Some(2),
+        (0x1D, b'(' | b'k' | b'v' | b'V') => None,
+        (0x1D, _) => Some(1),
+        (0x1C, b'p') => Some(2),
+        (0x1C, b'&' | b'.') => Some(0),
+        (0x1C, _) => Some(1),
+        (0x10, 0x14) => Some(3),
+        (0x10, _) => Some(1),
+        _ => Some(0),
+    }
+}
+
+// Length of the command starting at `bytes[0]`, including its prefix.
+pub(crate) fn command_length(bytes: &[u8]) -> usize {
+    let (prefix, command) = match bytes {
+        [prefix, command, ..] => (*prefix, *command),
+        _ => return bytes.len(),
+    };
+    let args = &bytes[2..];
+    let arg = |i: usize| args.get(i).copied().unwrap_or(0) as usize;
+    let length = match fixed_arguments(prefix, command) {
+        Some(n) => n,
+        None => match (prefix, command) {
+            // Bit image: m nL nH, then nL+nH*256 columns of 1 or 3 bytes
+            (0x1B, b'*') => 3 + (arg(1) + arg(2) * 256) * if arg(0) >= 32 { 3 } else { 1 },
+            // Tab stops, NUL-terminated
+            (0x1B, b'D') => args.iter().position(|b| *b == 0).map_or(args.len(), |i| i + 1),
+            // Star-style ESC GS / ESC RS commands: one selector byte and one argument
+            (0x1B, _) => 2,
+            // Function commands: fn pL pH, then pL+pH*256 bytes
+            (0x1D, b'(') => 3 + arg(1) + arg(2) * 256,
+            // Barcodes: m 0-6 are NUL-terminated, the rest carry a length byte
+            (0x1D, b'k') if arg(0) <= 6 => args.iter().skip(1).position(|b| *b == 0).map_or(args.len(), |i| i + 2),
+            (0x1D, b'k') => 2 + arg(1),
+            // Raster image: m xL xH yL yH, then (xL+xH*256)*(yL+yH*256) bytes
+            (0x1D, b'v') => 6 + (arg(2) + arg(3) * 256) * (arg(4) + arg(5) * 256),
+            // Cut: modes 65/66 take a feed amount
+            (0x1D, b'V') if arg(0) == 65 || arg(0) == 66 => 2,
+            _ => 1,
+        },
+    };
+    (2 + length).min(bytes.len())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EscPosBuilder {
+    buf: Vec<u8>,
+    commands: VendorCommands,
+}
+
+impl EscPosBuilder {
+    // Starts with ESC @ so every document begins from a known printer state.
+    pub fn new() -> Self {
+        Self::for_vendor(VendorCommands::default())
+    }
+
+    // Cut, drawer and density use the given vendor's sequences instead of plain ESC/POS.
+    pub fn for_vendor(commands: VendorCommands) -> Self {
+        let mut builder = EscPosBuilder { buf: Vec::new(), commands };
+        builder.init();
+        builder
+    }
+
+    pub fn init(&mut self) -> &mut Self {
+        self.raw(&[0x1B, 0x40])
+    }
+
+    pub fn raw(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buf.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        self.raw(text.as_bytes())
+    }
+
+    pub fn line(&mut self, text: &str) -> &mut Self {
+        self.text(text).raw(b"\n")
+    }
+
+    pub fn separator(&mut self, ch: char, width: usize) -> &mut Self {
+        self.line(&ch.to_string().repeat(width))
+    }
+
+    fn star_line(&self) -> bool {
+        self.commands.protocol == Protocol::StarLine
+    }
+
+    pub fn feed(&mut self, lines: u8) -> &mut Self {
+        if self.star_line() {
+            return self.raw(&[0x1B, 0x61, lines]);
+        }
+        self.raw(&[0x1B, 0x64, lines])
+    }
+
+    pub fn bold(&mut self, on: bool) -> &mut Self {
+        let bold = self.commands.bold(on);
+        self.raw(bold)
+    }
+
+    pub fn underline(&mut self, on: bool) -> &mut Self {
+        self.raw(&[0x1B, 0x2D, on as u8])
+    }
+
+    pub fn align(&mut self, align: Align) -> &mut Self {
+        let n = match align {
+            Align::Left => 0,
+            Align::Center => 1,
+            Align::Right => 2,
+        };
+        if self.star_line() {
+            return self.raw(&[0x1B, 0x1D, 0x61, n]);
+        }
+        self.raw(&[0x1B, 0x61, n])
+    }
+
+    pub fn font(&mut self, font: Font) -> &mut Self {
+        let n = match font {
+            Font::A => 0,
+            Font::B => 1,
+        };
+        if self.star_line() {
+            return self.raw(&[0x1B, 0x1E, 0x46, n]);
+        }
+        self.raw(&[0x1B, 0x4D, n])
+    }
+
+    // Character magnification, 1-8 in each direction (1-6 in Star Line Mode).
+    pub fn size(&mut self, width: u8, height: u8) -> &mut Self {
+        if self.star_line() {
+            let w = width.clamp(1, 6) - 1;
+            let h = height.clamp(1, 6) - 1;
+            return self.raw(&[0x1B, 0x69, h, w]);
+        }
+        let w = width.clamp(1, 8) - 1;
+        let h = height.clamp(1, 8) - 1;
+        self.raw(&[0x1D, 0x21, (w << 4) | h])
+    }
+
+    pub fn code_page(&mut self, page: u8) -> &mut Self {
+        if self.star_line() {
+            return self.raw(&[0x1B, 0x1D, 0x74, page]);
+        }
+        self.raw(&[0x1B, 0x74, page])
+    }
+
+    // QR model 2 with error correction level M.
+    pub fn qr(&mut self, data: &str, module_size: u8) -> &mut Self {
+        if self.star_line() {
+            let (nl, nh) = ((data.len() % 256) as u8, (data.len() / 256) as u8);
+            return self.raw(&[0x1B, 0x1D, 0x79, 0x53, 0x30, 0x02])
+                .raw(&[0x1B, 0x1D, 0x79, 0x53, 0x31, 0x01])
+                .raw(&[0x1B, 0x1D, 0x79, 0x53, 0x32, module_size.clamp(1, 8)])
+                .raw(&[0x1B, 0x1D, 0x79, 0x44, 0x31, 0x00, nl, nh])
+                .text(data)
+                .raw(&[0x1B, 0x1D, 0x79, 0x50]);
+        }
+        let len = data.len() + 3;
+        let (pl, ph) = ((len % 256) as u8, (len / 256) as u8);
+        self.raw(&[0x1D, 0x28, 0x6B, 0x04, 0x00, 0x31, 0x41, 0x32, 0x00])
+            .raw(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x31, 0x43, module_size.clamp(1, 16)])
+            .raw(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x31, 0x45, 0x31])
+            .raw(&[0x1D, 0x28, 0x6B, pl, ph, 0x31, 0x50, 0x30])
+            .text(data)
+            .raw(&[0x1D, 0x28, 0x6B, 0x03, 0x00, 0x31, 0x51, 0x30])
+    }
+
+    // CODE128 using code set B, human readable text below the bars.
+    pub fn barcode_code128(&mut self, data: &str, height: u8) -> &mut Self {
+        if self.star_line() {
+            // ESC b: type 6 (CODE128), text below, mode 2 (medium bars), height in dots, RS-terminated
+            return self.raw(&[0x1B, 0x62, 0x06, 0x02, 0x02, height]).text(data).raw(&[0x1E]);
+        }
+        let len = (data.len() + 2).min(255) as u8;
+        self.raw(&[0x1D, 0x68, height])
+            .raw(&[0x1D, 0x77, 0x02])
+            .raw(&[0x1D, 0x48, 0x02])
+            .raw(&[0x1D, 0x6B, 0x49, len, b'{', b'B'])
+            .text(data)
+    }
+
+    pub fn cut(&mut self) -> &mut Self {
+        let cut = self.commands.cut.clone();
+        self.raw(&cut)
+    }
+
+    pub fn partial_cut(&mut self) -> &mut Self {
+        let cut = self.commands.partial_cut.clone();
+        self.raw(&cut)
+    }
+
+    pub fn open_drawer(&mut self) -> &mut Self {
+        let drawer = self.commands.open_drawer.clone();
+        self.raw(&drawer)
+    }
+
+    pub fn open_drawer_on(&mut self, pin: DrawerPin) -> &mut Self {
+        let drawer = self.commands.open_drawer_on(pin);
+        self.raw(&drawer)
+    }
+
+    // A no-op on printers without a density command.
+    pub fn density(&mut self, density: Density) -> &mut Self {
+        let sequence = match density {
+            Density::Light => self.commands.density_light.clone(),
+            Density::Normal => self.commands.density_normal.clone(),
+            Density::Dark => self.commands.density_dark.clone(),
+        };
+        self.raw(&sequence)
+    }
+
+    // Page mode: lay out a region with absolute positions, then print it in one go with
+    // `print_page`. ESC/POS only; Star Line Mode has no equivalent.
+    pub fn page_mode(&mut self) -> &mut Self {
+        self.raw(&[0x1B, 0x4C])
+    }
+
+    // Printable region in dots, relative to the paper's top-left corner.
+    pub fn print_area(&mut self, x: u16, y: u16, width: u16, height: u16) -> &mut Self {
+        let [xl, xh] = x.to_le_bytes();
+        let [yl, yh] = y.to_le_bytes();
+        let [wl, wh] = width.to_le_bytes();
+        let [hl, hh] = height.to_le_bytes();
+        self.raw(&[0x1B, 0x57, xl, xh, yl, yh, wl, wh, hl, hh])
+    }
+
+    pub fn page_direction(&mut self, direction: PageDirection) -> &mut Self {
+        let n = match direction {
+            PageDirection::LeftToRight => 0,
+            PageDirection::BottomToTop => 1,
+            PageDirection::RightToLeft => 2,
+            PageDirection::TopToBottom => 3,
+        };
+        self.raw(&[0x1B, 0x54, n])
+    }
+
+    // Absolute position in dots within the print area (ESC $ and GS $).
+    pub fn position(&mut self, x: u16, y: u16) -> &mut Self {
+        let [xl, xh] = x.to_le_bytes();
+        let [yl, yh] = y.to_le_bytes();
+        self.raw(&[0x1B, 0x24, xl, xh]).raw(&[0x1D, 0x24, yl, yh])
+    }
+
+    // FF prints everything laid out since `page_mode` and returns to standard mode.
+    pub fn print_page(&mut self) -> &mut Self {
+        self.raw(&[0x0C])
+    }
+
+    // Drops the page buffer without printing.
+    pub fn cancel_page(&mut self) -> &mut Self {
+        self.raw(&[0x18])
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        self.buf.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::vendor::{self, VendorProfile};
+    use super::*;
+
+    #[test]
+    fn fixed_length_commands() {
+        assert_eq!(command_length(b"\x1B\x40x"), 2);
+        assert_eq!(command_length(b"\x1B\x45\x01x"), 3);
+        assert_eq!(command_length(b"\x1B\x70\x00\x19\xFAx"), 5);
+        assert_eq!(command_length(b"\x10\x14\x01\x00\x05x"), 5);
+    }
+
+    #[test]
+    fn cut_takes_a_feed_amount_only_in_modes_65_and_66() {
+        assert_eq!(command_length(b"\x1D\x56\x00x"), 3);
+        assert_eq!(command_length(b"\x1D\x56\x41\x00x"), 4);
+        assert_eq!(command_length(b"\x1D\x56\x42\x03x"), 4);
+    }
+
+    #[test]
+    fn lengths_encoded_in_the_arguments() {
+        // GS ( k with pL = 3
+        assert_eq!(command_length(b"\x1D\x28\x6B\x03\x00\x31\x43\x05x"), 8);
+        // GS v 0, 2 bytes wide and 2 dots high
+        assert_eq!(command_length(b"\x1D\x76\x30\x00\x02\x00\x02\x00\x1B\x40\x1D\x56x"), 12);
+        // ESC * in 24-dot mode, 2 columns of 3 bytes
+        assert_eq!(command_length(b"\x1B\x2A\x21\x02\x00\x01\x02\x03\x04\x05\x06x"), 11);
+        // ESC D tab stops up to the NUL
+        assert_eq!(command_length(b"\x1B\x44\x08\x10\x00x"), 5);
+    }
+
+    #[test]
+    fn barcodes_end_at_a_nul_or_carry_their_length() {
+        assert_eq!(command_length(b"\x1D\x6B\x04\x31\x32\x00x"), 6);
+        assert_eq!(command_length(b"\x1D\x6B\x49\x02\x7B\x42x"), 6);
+    }
+
+    #[test]
+    fn truncated_commands_stop_at_the_end() {
+        assert_eq!(command_length(b"\x1B\x2A\x21\x10\x00"), 5);
+        assert_eq!(command_length(b"\x1B"), 1);
+    }
+
+    #[test]
+    fn star_line_bold_has_no_argument() {
+        let mut star = EscPosBuilder::for_vendor(vendor::commands(VendorProfile::StarLine));
+        let mut generic = EscPosBuilder::new();
+
+        assert_eq!(star.bold(true).bold(false).build(), b"\x1B\x40\x1B\x45\x1B\x46");
+        assert_eq!(generic.bold(true).bold(false).build(), b"\x1B\x40\x1B\x45\x01\x1B\x45\x00");
+    }
+}
@@ -0,0 +1,88 @@
+// The expeditor's ticket: the whole order on one slip, one line per item with the station making
+// it, so the pass can check every plate off before the order goes out. Printed on
+// `expo_printer_id` alongside the station KOTs.
+
+use rusqlite::Connection;
+
+use super::addons;
+use super::clock;
+use super::error::PrintError;
+use super::profile::{self, PrinterProfile};
+use super::{fetch_order_details, PrintedItems, StationFilter, StationRoute, BOLD_OFF, BOLD_ON, CUT_PAPER, INIT};
+
+const LINE_WIDTH: usize = 48;
+// Reported as this station in print_kot_to_stations results
+pub(crate) const EXPO_STATION: &str = "expo";
+
+// Which station's ticket the item is on.
+fn station_for<'a>(routes: &'a [StationRoute], item_type: &str) -> Option<&'a str> {
+    routes
+        .iter()
+        .find(|route| StationFilter { routes, route, ticket: None }.accepts(item_type))
+        .map(|route| route.station.as_str())
+}
+
+// `qty x name` on the left and the station on the right, the name cut short to fit.
+fn item_line(quantity: &str, name: &str, station: &str) -> String {
+    let left = format!("{:>3} x ", quantity);
+    let station = station.to_uppercase();
+    let room = LINE_WIDTH.saturating_sub(left.len() + station.chars().count() + 1);
+    let name: String = name.chars().take(room).collect();
+    let padding = room.saturating_sub(name.chars().count()) + 1;
+    format!("{}{}{}{}\n", left, name, " ".repeat(padding), station)
+}
+
+// `None`, with a warning, when the printer is gone, so the station tickets still go out without it.
+pub(crate) fn printer(conn: &Connection, printer_id: i64) -> rusqlite::Result<Option<PrinterProfile>> {
+    let found = profile::find(conn, printer_id)?;
+    if found.is_none() {
+        warn!("Expo printer {} no longer exists; leaving out the expo ticket", printer_id);
+    }
+    Ok(found)
+}
+
+// `None` when `printed` leaves nothing new, as for the ADD-ON station tickets.
+pub(crate) fn build(
+    conn: &Connection,
+    order_id: i64,
+    routes: &[StationRoute],
+    printed: Option<&PrintedItems>,
+) -> Result<Option<String>, PrintError> {
+    let order = fetch_order_details(conn, order_id)?;
+    let mut lines = Vec::new();
+    for (item_type, name, quantity, ..) in &order.item_data {
+        let already = printed.map_or(0, |printed| addons::already_printed(printed, item_type, name));
+        if printed.is_some() && *quantity <= already {
+            continue;
+        }
+        let quantity = if already > 0 { format!("+{}", quantity - already) } else { quantity.to_string() };
+        lines.push(item_line(&quantity, name, station_for(routes, item_type).unwrap_or("-")));
+    }
+    if printed.is_some() && lines.is_empty() {
+        return Ok(None);
+    }
+
+    let token = order.order_number.split('-').last().unwrap_or("");
+    let order_type = if order.has_table { "Table" } else { "Pack" };
+    let mut content = String::from(INIT);
+    content.push_str(&format!("{}EXPO{}{}\n", BOLD_ON, if printed.is_some() { " - ADD-ON" } else { "" }, BOLD_OFF));
+    content.push_str(&format!(
+        "Kot: {}{}{}  [{}]  {}\n",
+        BOLD_ON,
+        token,
+        BOLD_OFF,
+        order_type,
+        clock::now().format("%I:%M %p")
+    ));
+    content.push_str(&("-".repeat(LINE_WIDTH) + "\n"));
+    for line in lines {
+        content.push_str(&line);
+    }
+    if !order.notes.trim().is_empty() {
+        content.push_str(&("-".repeat(LINE_WIDTH) + "\n"));
+        content.push_str(&format!("{}Notes: {}{}\n", BOLD_ON, order.notes.trim(), BOLD_OFF));
+    }
+    content.push_str("\n\n");
+    content.push_str(CUT_PAPER);
+    Ok(Some(content))
+}
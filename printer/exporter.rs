@@ -0,0 +1,131 @@
+// Serves the printer metrics in Prometheus text format at `GET /metrics` for sites that scrape
+// every terminal centrally. Off unless `metrics_listen_address` is set; it answers anyone who can
+// reach the port, so bind it to an address only the monitoring network sees.
+
+use std::fmt::Write as _;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Runtime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::db::DbState;
+
+use super::config;
+use super::database;
+use super::error::PrintError;
+use super::metrics::{self, MetricsRange, PrinterMetrics};
+
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Label values may contain anything a printer can be named
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn labels(printer: &PrinterMetrics) -> String {
+    format!("printer=\"{}\",destination=\"{}\"", escape(&printer.name), escape(&printer.destination.key()))
+}
+
+// Counters cover the whole job log, so they only drop when old jobs age out of retention.
+fn render(printers: &[PrinterMetrics]) -> String {
+    let mut out = String::new();
+    let counters: [(&str, &str, fn(&PrinterMetrics) -> u32); 4] = [
+        ("printer_jobs_total", "Jobs sent to the printer.", |p| p.jobs),
+        ("printer_successes_total", "Jobs the printer took.", |p| p.successes),
+        ("printer_queued_total", "Jobs parked in the offline queue.", |p| p.queued),
+        ("printer_retries_total", "Jobs for an order document the printer had already been sent.", |p| p.retries),
+    ];
+    for (name, help, value) in counters {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter", name, help, name);
+        for printer in printers {
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels(printer), value(printer));
+        }
+    }
+
+    let _ = writeln!(out, "# HELP printer_failures_total Failed jobs by error code.\n# TYPE printer_failures_total counter");
+    for printer in printers {
+        let mut failures: Vec<_> = printer.failures.iter().collect();
+        failures.sort_by_key(|(code, _)| code.as_str());
+        for (code, count) in failures {
+            let _ = writeln!(out, "printer_failures_total{{{},code=\"{}\"}} {}", labels(printer), code.as_str(), count);
+        }
+    }
+
+    let gauges: [(&str, &str, fn(&PrinterMetrics) -> Option<u64>); 2] = [
+        ("printer_latency_p50_milliseconds", "Median time to send a job.", |p| p.p50_ms),
+        ("printer_latency_p95_milliseconds", "95th percentile time to send a job.", |p| p.p95_ms),
+    ];
+    for (name, help, value) in gauges {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge", name, help, name);
+        for printer in printers {
+            if let Some(value) = value(printer) {
+                let _ = writeln!(out, "{}{{{}}} {}", name, labels(printer), value);
+            }
+        }
+    }
+    out
+}
+
+async fn scrape(state: &DbState) -> Result<String, PrintError> {
+    let printers = database::run(state, |conn| metrics::compute(conn, MetricsRange::All)).await?;
+    Ok(render(&printers))
+}
+
+// One request per connection; only the request line is looked at.
+async fn serve<R: Runtime>(app: &AppHandle<R>, mut stream: TcpStream) -> std::io::Result<()> {
+    let mut request = [0u8; 1024];
+    let read = tokio::time::timeout(READ_TIMEOUT, stream.read(&mut request))
+        .await
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::TimedOut))??;
+    let request = String::from_utf8_lossy(&request[..read]);
+
+    let (status, body) = if request.starts_with("GET /metrics ") || request.starts_with("GET /metrics?") {
+        match scrape(&app.state::<DbState>()).await {
+            Ok(body) => ("200 OK", body),
+            Err(e) => ("500 Internal Server Error", format!("{}\n", e)),
+        }
+    } else {
+        ("404 Not Found", "Not found\n".to_string())
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+// For plugin setup; a listener that can't bind is logged and printing carries on without it.
+pub(crate) fn start<R: Runtime>(app: &AppHandle<R>) {
+    let Some(address) = config::current().metrics_listen_address else {
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let listener = match TcpListener::bind(&address).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Could not serve printer metrics on {}: {}", address, e);
+                return;
+            }
+        };
+        info!("Serving printer metrics on http://{}/metrics", address);
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("Metrics connection failed: {}", e);
+                    continue;
+                }
+            };
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = serve(&app, stream).await {
+                    debug!("Metrics request failed: {}", e);
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,183 @@
+// Audit mode for bills, as the tax authority requires: every print carries the order's sequential
+// bill number at the top. A bill's first successful print is the original and is recorded with its
+// bill number and a SHA-256 of exactly the bytes sent to the printer, after that printer's layout
+// (a fallback printer's down banner aside); every later print of it goes out watermarked "COPY n".
+// The record can't be changed or deleted once written (triggers in migration 10 refuse it), so an
+// audit can match the till roll to the log.
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+
+use crate::db::DbState;
+
+use super::config;
+use super::database;
+use super::error::PrintError;
+use super::sequences::{self, BILL_SERIES};
+use super::DocumentType;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FiscalBill {
+    pub order_id: i64,
+    pub bill_number: String,
+    pub payload_hash: String,
+    pub printed_at: String,
+    pub copies: u32,
+}
+
+impl FiscalBill {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(FiscalBill {
+            order_id: row.get("order_id")?,
+            bill_number: row.get("bill_number")?,
+            payload_hash: row.get("payload_hash")?,
+            printed_at: row.get("printed_at")?,
+            copies: row.get("copies")?,
+        })
+    }
+}
+
+// A bill job on its way to the printer; `copy` is 0 for the original.
+pub(crate) struct FiscalJob {
+    order_id: i64,
+    bill_number: String,
+    copy: u32,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hex = String::new();
+    for byte in Sha256::digest(bytes) {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
+
+// The bill number, centered, and for a copy the "COPY n" mark in bold double size under it, right
+// after the payload's own ESC @ if it starts with one.
+fn stamp(payload: &[u8], bill_number: &str, copy: u32) -> Vec<u8> {
+    let init: &[u8] = b"\x1B\x40";
+    let (head, body) = if payload.starts_with(init) { payload.split_at(init.len()) } else { (&payload[..0], payload) };
+    let mut marked = head.to_vec();
+    marked.extend_from_slice(b"\x1B\x61\x01");
+    marked.extend_from_slice(format!("Bill No: {}\n", bill_number).as_bytes());
+    if copy > 0 {
+        marked.extend_from_slice(b"\x1B\x45\x01\x1D\x21\x11");
+        marked.extend_from_slice(format!("*** COPY {} ***\n", copy).as_bytes());
+        marked.extend_from_slice(b"\x1D\x21\x00\x1B\x45\x00");
+    }
+    marked.extend_from_slice(b"\x1B\x61\x00");
+    marked.extend_from_slice(body);
+    marked
+}
+
+fn find(conn: &Connection, order_id: i64) -> rusqlite::Result<Option<FiscalBill>> {
+    conn.query_row("SELECT * FROM fiscal_bills WHERE order_id = ?1", params![order_id], FiscalBill::from_row).optional()
+}
+
+// Stamps `payload` with the bill number, and watermarks it when this bill was printed before.
+// `None` when fiscal mode is off or the job isn't a bill; staging and training runs are never recorded.
+pub(crate) async fn prepare(
+    state: &DbState,
+    order_id: i64,
+    document_type: Option<DocumentType>,
+    payload: &mut Vec<u8>,
+) -> Result<Option<FiscalJob>, PrintError> {
+    let config = config::current();
+    if !config.fiscal_mode || document_type != Some(DocumentType::Bill) || config.dry_run || config.simulation_mode {
+        return Ok(None);
+    }
+    let (bill_number, copy) = database::run(state, move |conn| {
+        let bill_number = sequences::number_for_order(conn, BILL_SERIES, order_id)?;
+        // One statement, so two tills printing the same bill at once can't both take the original
+        let prints: u32 = conn.query_row(
+            "INSERT INTO fiscal_prints (order_id, prints) VALUES (?1, 1)
+             ON CONFLICT (order_id) DO UPDATE SET prints = prints + 1
+             RETURNING prints",
+            params![order_id],
+            |row| row.get(0),
+        )?;
+        Ok((bill_number, prints - 1))
+    })
+    .await?;
+
+    *payload = stamp(payload, &bill_number, copy);
+    Ok(Some(FiscalJob { order_id, bill_number, copy }))
+}
+
+// Once the job has gone out (or been queued to), with the payload as sent to the first printer
+// that took it. When every printer failed the print is given back, so the retry is still the
+// original (unless another print of the bill was taken since).
+pub(crate) async fn record(state: &DbState, job: FiscalJob, printed: Option<&[u8]>) {
+    let payload_hash = printed.map(sha256_hex);
+    let order_id = job.order_id;
+    let recorded = database::run(state, move |conn| {
+        match payload_hash {
+            None => {
+                conn.execute(
+                    "UPDATE fiscal_prints SET prints = prints - 1 WHERE order_id = ?1 AND prints = ?2",
+                    params![job.order_id, job.copy + 1],
+                )?;
+            }
+            Some(payload_hash) if job.copy == 0 => {
+                conn.execute(
+                    "INSERT INTO fiscal_bills (order_id, bill_number, payload_hash) VALUES (?1, ?2, ?3)",
+                    params![job.order_id, job.bill_number, payload_hash],
+                )?;
+            }
+            Some(_) => {
+                conn.execute(
+                    "UPDATE fiscal_bills SET copies = MAX(copies, ?1) WHERE order_id = ?2",
+                    params![job.copy, job.order_id],
+                )?;
+            }
+        }
+        Ok(())
+    })
+    .await;
+    if let Err(e) = recorded {
+        error!("Failed to record fiscal bill for order {}: {}", order_id, e);
+    }
+}
+
+#[tauri::command]
+pub fn get_fiscal_bill(order_id: i64, state: tauri::State<'_, DbState>) -> Result<Option<FiscalBill>, PrintError> {
+    let conn = database::connection(&state)?;
+    Ok(find(&conn, order_id)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn original_gets_the_bill_number_after_the_reset() {
+        let marked = stamp(b"\x1B\x40Total 120\n", "B-0042", 0);
+
+        assert_eq!(marked, b"\x1B\x40\x1B\x61\x01Bill No: B-0042\n\x1B\x61\x00Total 120\n");
+    }
+
+    #[test]
+    fn copy_is_marked_in_bold_double_size() {
+        let marked = stamp(b"\x1B\x40Total 120\n", "B-0042", 2);
+
+        assert_eq!(
+            marked,
+            [
+                b"\x1B\x40\x1B\x61\x01Bill No: B-0042\n".as_slice(),
+                b"\x1B\x45\x01\x1D\x21\x11*** COPY 2 ***\n\x1D\x21\x00\x1B\x45\x00",
+                b"\x1B\x61\x00Total 120\n",
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn payload_without_a_reset_is_stamped_at_the_top() {
+        let marked = stamp(b"Total 120\n", "B-0042", 0);
+
+        assert!(marked.starts_with(b"\x1B\x61\x01Bill No: B-0042\n"));
+        assert!(marked.ends_with(b"Total 120\n"));
+    }
+}
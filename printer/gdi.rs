@@ -0,0 +1,249 @@
+use rusqlite::types::ToSqlOutput;
+use rusqlite::ToSql;
+use serde::{Deserialize, Serialize};
+#[cfg(windows)]
+use std::collections::HashMap;
+#[cfg(windows)]
+use std::sync::{Mutex, OnceLock};
+#[cfg(windows)]
+use windows::Win32::Foundation::{COLORREF, RECT};
+#[cfg(windows)]
+use windows::Win32::Graphics::Gdi::{
+    FF_MODERN, FF_SWISS, FIXED_PITCH, FW_BOLD, FW_NORMAL, HORZRES, HORZSIZE, LOGPIXELSX, LOGPIXELSY, VARIABLE_PITCH,
+    VERTRES,
+};
+
+use super::error::PrintError;
+use super::escpos;
+#[cfg(windows)]
+use super::pdf::{Canvas, Weight};
+#[cfg(windows)]
+use super::win32::{self, DeviceContext, GdiObject};
+
+// Anything wider than this can't be a receipt printer (80mm paper prints about 72mm)
+#[cfg(windows)]
+const MAX_ROLL_WIDTH_MM: i32 = 120;
+#[cfg(windows)]
+const FONT_SIZE_PT: i32 = 10;
+
+// How a spooled Windows printer is fed. Office printers get the ticket drawn as plain text
+// through GDI, because ESC/POS codes sent RAW print as garbage on them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrinterType {
+    // Decided from the page width the driver reports
+    #[default]
+    Auto,
+    Thermal,
+    Office,
+}
+
+impl PrinterType {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            PrinterType::Auto => "auto",
+            PrinterType::Thermal => "thermal",
+            PrinterType::Office => "office",
+        }
+    }
+
+    pub(crate) fn parse(value: &str) -> Self {
+        match value {
+            "thermal" => PrinterType::Thermal,
+            "office" => PrinterType::Office,
+            _ => PrinterType::Auto,
+        }
+    }
+}
+
+impl ToSql for PrinterType {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+// Detection opens a device context, so each printer is only measured once per run.
+#[cfg(windows)]
+fn detected() -> &'static Mutex<HashMap<String, bool>> {
+    static DETECTED: OnceLock<Mutex<HashMap<String, bool>>> = OnceLock::new();
+    DETECTED.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[cfg(windows)]
+fn is_office_printer(printer_name: &str) -> bool {
+    let mut detected = detected().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(office) = detected.get(printer_name) {
+        return *office;
+    }
+    // Printers we can't open are left to the RAW path, which reports the real error
+    let Ok(dc) = DeviceContext::create(printer_name) else {
+        return false;
+    };
+    let office = dc.caps(HORZSIZE) > MAX_ROLL_WIDTH_MM;
+    if office {
+        info!("{} looks like an office printer; tickets will print as plain text", printer_name);
+    }
+    detected.insert(printer_name.to_string(), office);
+    office
+}
+
+// Without a Windows driver to ask, only printers set to Office print as text.
+#[cfg(not(windows))]
+fn is_office_printer(_printer_name: &str) -> bool {
+    false
+}
+
+pub(crate) fn use_text_mode(printer_name: &str, printer_type: PrinterType) -> bool {
+    match printer_type {
+        PrinterType::Auto => is_office_printer(printer_name),
+        PrinterType::Thermal => false,
+        PrinterType::Office => true,
+    }
+}
+
+// Strips ESC/POS commands from a payload and returns the printable text, one entry per line.
+pub(crate) fn plain_text(content: &[u8]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = Vec::new();
+    let mut i = 0;
+    while i < content.len() {
+        match content[i] {
+            b'\n' => {
+                lines.push(String::from_utf8_lossy(&line).into_owned());
+                line.clear();
+                i += 1;
+            }
+            b'\t' => {
+                line.extend_from_slice(b"    ");
+                i += 1;
+            }
+            0x1B | 0x1D | 0x1C | 0x10 => i += escpos::command_length(&content[i..]),
+            byte if byte < 0x20 => i += 1,
+            byte => {
+                line.push(byte);
+                i += 1;
+            }
+        }
+    }
+    if !line.is_empty() {
+        lines.push(String::from_utf8_lossy(&line).into_owned());
+    }
+    // Trailing feeds before the cut would otherwise spill onto an extra page
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+// Draws the ticket in a fixed-pitch font through the printer's own driver.
+#[cfg(windows)]
+pub(crate) fn print_text(content: &[u8], printer_name: &str) -> Result<(), PrintError> {
+    let lines = plain_text(content);
+    let mut dc = DeviceContext::create(printer_name)?;
+    let page_height = dc.caps(VERTRES);
+    let font = GdiObject::font(
+        -(FONT_SIZE_PT * dc.caps(LOGPIXELSY) / 72),
+        FW_NORMAL as i32,
+        (FIXED_PITCH.0 | FF_MODERN.0) as u32,
+        "Courier New",
+    );
+
+    dc.start_document("KOT Print")?;
+    {
+        let _font = dc.select(&font);
+        let line_height = dc.line_height();
+        dc.start_page();
+        let mut y = 0;
+        for line in &lines {
+            if y + line_height > page_height {
+                dc.end_page();
+                dc.start_page();
+                y = 0;
+            }
+            dc.text(0, y, line);
+            y += line_height;
+        }
+        dc.end_page();
+    }
+    dc.end_document()
+}
+
+#[cfg(not(windows))]
+pub(crate) fn print_text(_content: &[u8], printer_name: &str) -> Result<(), PrintError> {
+    Err(PrintError::windows_only(&format!("Printing text on {}", printer_name)))
+}
+
+// A Canvas on a printer device context, for page layouts like the A4 invoice. Positions are
+// in points from the top-left of the printable area. Images are skipped.
+#[cfg(windows)]
+pub(crate) struct GdiCanvas {
+    dc: DeviceContext,
+    // Device pixels per point
+    scale: f32,
+    width: f32,
+    height: f32,
+}
+
+#[cfg(windows)]
+impl GdiCanvas {
+    pub(crate) fn start(printer_name: &str, document_name: &str) -> Result<Self, PrintError> {
+        let mut dc = DeviceContext::create(printer_name)?;
+        dc.start_document(document_name)?;
+        let scale = dc.caps(LOGPIXELSX) as f32 / 72.0;
+        let width = dc.caps(HORZRES) as f32 / scale;
+        let height = dc.caps(VERTRES) as f32 / scale;
+        let canvas = GdiCanvas { dc, scale, width, height };
+        canvas.start_page();
+        Ok(canvas)
+    }
+
+    fn px(&self, points: f32) -> i32 {
+        (points * self.scale).round() as i32
+    }
+
+    // Drivers may reset the DC on every page, so the text settings are applied each time.
+    fn start_page(&self) {
+        self.dc.start_page();
+        self.dc.align_to_baseline();
+    }
+
+    pub(crate) fn finish(mut self) -> Result<(), PrintError> {
+        self.dc.end_page();
+        self.dc.end_document()
+    }
+}
+
+#[cfg(windows)]
+impl Canvas for GdiCanvas {
+    fn width(&self) -> f32 {
+        self.width
+    }
+
+    fn height(&self) -> f32 {
+        self.height
+    }
+
+    fn text(&mut self, x: f32, y: f32, size: f32, weight: Weight, text: &str) {
+        let font_weight = if weight == Weight::Bold { FW_BOLD } else { FW_NORMAL };
+        let font = GdiObject::font(-self.px(size), font_weight as i32, (VARIABLE_PITCH.0 | FF_SWISS.0) as u32, "Arial");
+        let _font = self.dc.select(&font);
+        self.dc.text(self.px(x), self.px(y), text);
+    }
+
+    fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32) {
+        let pen = GdiObject::pen(self.px(thickness).max(1), COLORREF(0));
+        let _pen = self.dc.select(&pen);
+        self.dc.line(self.px(x1), self.px(y1), self.px(x2), self.px(y2));
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, gray: f32) {
+        let level = (gray.clamp(0.0, 1.0) * 255.0) as u8;
+        let rect = RECT { left: self.px(x), top: self.px(y), right: self.px(x + width), bottom: self.px(y + height) };
+        self.dc.fill_rect(rect, &GdiObject::brush(win32::gray(level)));
+    }
+
+    fn new_page(&mut self) {
+        self.dc.end_page();
+        self.start_page();
+    }
+}
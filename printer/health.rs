@@ -0,0 +1,101 @@
+// One snapshot of everything a remote monitoring agent checks, so it can poll a single command
+// over IPC instead of stitching together the status, queue and archive commands.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::db::DbState;
+
+use super::archive;
+use super::breaker;
+use super::database;
+use super::error::PrintError;
+use super::monitor;
+use super::profile::{self, PrinterProfile};
+use super::queue;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PrinterHealth {
+    // `None` for destinations without a saved profile, such as a station route's own printer
+    pub printer_id: Option<i64>,
+    pub name: String,
+    pub destination_key: String,
+    // From the status monitor's last poll; `None` when it hasn't polled this printer
+    pub online: Option<bool>,
+    pub queued_jobs: u32,
+    pub breaker_open: bool,
+    pub consecutive_failures: u32,
+    pub last_success_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Health {
+    pub database_ok: bool,
+    pub database_error: Option<String>,
+    pub queued_jobs: u32,
+    pub printers: Vec<PrinterHealth>,
+}
+
+struct Stored {
+    profiles: Vec<PrinterProfile>,
+    depths: HashMap<String, u32>,
+    last_printed: HashMap<String, String>,
+}
+
+fn printer_entry<'a>(printers: &'a mut BTreeMap<String, PrinterHealth>, key: &str) -> &'a mut PrinterHealth {
+    printers
+        .entry(key.to_string())
+        .or_insert_with(|| PrinterHealth { name: key.to_string(), destination_key: key.to_string(), ..Default::default() })
+}
+
+// A database error doesn't fail the check: it is part of the answer, and the in-memory breaker
+// states are still reported.
+pub(crate) async fn check(state: &DbState) -> Health {
+    let stored = database::run(state, |conn| {
+        Ok(Stored { profiles: profile::list(conn)?, depths: queue::depths(conn)?, last_printed: archive::last_printed(conn)? })
+    })
+    .await;
+    let mut health = Health::default();
+    let stored = match stored {
+        Ok(stored) => {
+            health.database_ok = true;
+            stored
+        }
+        Err(e) => {
+            error!("Health check could not read the database: {}", e);
+            health.database_error = Some(e.to_string());
+            Stored { profiles: Vec::new(), depths: HashMap::new(), last_printed: HashMap::new() }
+        }
+    };
+
+    // Keyed by destination so saved printers, queued jobs and breakers for the same printer line up
+    let mut printers: BTreeMap<String, PrinterHealth> = BTreeMap::new();
+    for profile in &stored.profiles {
+        let key = profile.destination().key();
+        let online = profile.id.and_then(monitor::last_known_status).map(|status| status.map_or(false, |s| s.online));
+        printers.insert(
+            key.clone(),
+            PrinterHealth { printer_id: profile.id, name: profile.name.clone(), destination_key: key, online, ..Default::default() },
+        );
+    }
+    for (key, depth) in &stored.depths {
+        health.queued_jobs += depth;
+        printer_entry(&mut printers, key).queued_jobs = *depth;
+    }
+    for (key, failures, open) in breaker::snapshot() {
+        let printer = printer_entry(&mut printers, &key);
+        printer.consecutive_failures = failures;
+        printer.breaker_open = open;
+    }
+    for (key, printer) in printers.iter_mut() {
+        printer.last_success_at = stored.last_printed.get(key).cloned();
+    }
+
+    health.printers = printers.into_values().collect();
+    health
+}
+
+#[tauri::command]
+pub async fn health(state: tauri::State<'_, DbState>) -> Result<Health, PrintError> {
+    Ok(check(&state).await)
+}
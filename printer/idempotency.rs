@@ -0,0 +1,56 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+fn recent_jobs() -> &'static Mutex<HashMap<String, Instant>> {
+    static JOBS: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+    JOBS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Used when the frontend doesn't send its own key: same order + same bytes = same job.
+pub(crate) fn derive_key(order_id: i64, content: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    format!("{}:{:016x}", order_id, hasher.finish())
+}
+
+// A claimed key, given back when dropped so a job that fails or errors out part way can be retried
+// straight away. `keep` holds on to it once a printer has accepted the job.
+pub(crate) struct Claim(Option<String>);
+
+impl Claim {
+    pub(crate) fn keep(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if let Some(key) = self.0.take() {
+            release(&key);
+        }
+    }
+}
+
+// `None` when the key was already claimed inside the window.
+pub(crate) fn claim(key: &str, window: Duration) -> Option<Claim> {
+    if window.is_zero() {
+        return Some(Claim(None));
+    }
+
+    let now = Instant::now();
+    let mut jobs = recent_jobs().lock().unwrap_or_else(|e| e.into_inner());
+    jobs.retain(|_, claimed_at| now.duration_since(*claimed_at) < window);
+    if jobs.contains_key(key) {
+        return None;
+    }
+    jobs.insert(key.to_string(), now);
+    Some(Claim(Some(key.to_string())))
+}
+
+fn release(key: &str) {
+    let mut jobs = recent_jobs().lock().unwrap_or_else(|e| e.into_inner());
+    jobs.remove(key);
+}
@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::DbState;
+
+use super::clock;
+use super::config::{self, PrinterConfig};
+use super::database;
+use super::error::{ErrorCode, PrintError};
+#[cfg(windows)]
+use super::gdi::GdiCanvas;
+use super::letterhead;
+use super::pdf::{Canvas, PdfWriter, Weight, A4};
+use super::receipt::{draw_letterhead, item_details};
+use super::sequences::{self, BILL_SERIES};
+use super::{fetch_order_details, spooler, DocumentType, OrderDetails};
+
+const MARGIN: f32 = 48.0;
+const LINE_HEIGHT: f32 = 16.0;
+const ROW_PADDING: f32 = 4.0;
+
+// Who the invoice is billed to; corporate customers need their PAN/VAT number on it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceCustomer {
+    pub name: String,
+    #[serde(default)]
+    pub address: String,
+    #[serde(default)]
+    pub pan: String,
+}
+
+// The order with the bill number it was given the first time it was invoiced.
+struct Bill {
+    number: String,
+    order: OrderDetails,
+}
+
+fn shaded_header<C: Canvas>(canvas: &mut C, y: f32, columns: &[(f32, &str)]) -> f32 {
+    let right = canvas.width() - MARGIN;
+    canvas.fill_rect(MARGIN, y, right - MARGIN, LINE_HEIGHT + ROW_PADDING, 0.85);
+    for (x, title) in columns {
+        canvas.text(*x, y + LINE_HEIGHT - 2.0, 10.0, Weight::Bold, title);
+    }
+    y + LINE_HEIGHT + ROW_PADDING * 2.0
+}
+
+// Lays out one invoice from the top of the current page; pages are added as the table grows.
+fn draw_invoice<C: Canvas>(canvas: &mut C, bill: &Bill, customer: &InvoiceCustomer, config: &PrinterConfig) {
+    let Bill { number, order } = bill;
+    let right = canvas.width() - MARGIN;
+    let bottom = canvas.height() - MARGIN;
+    let mut y = draw_letterhead(canvas, config);
+
+    canvas.text(MARGIN, y, 16.0, Weight::Bold, "TAX INVOICE");
+    canvas.text_right(right, y, 10.0, Weight::Regular, &format!("Invoice No: {}", number));
+    y += LINE_HEIGHT;
+    if !config.business_pan.is_empty() {
+        canvas.text(MARGIN, y, 10.0, Weight::Regular, &format!("PAN/VAT No: {}", config.business_pan));
+    }
+    canvas.text_right(right, y, 10.0, Weight::Regular, &format!("Date: {}", clock::now().format("%Y-%m-%d")));
+    y += LINE_HEIGHT * 1.5;
+
+    y = shaded_header(canvas, y, &[(MARGIN + ROW_PADDING, "Bill To")]);
+    canvas.text(MARGIN + ROW_PADDING, y, 11.0, Weight::Bold, &customer.name);
+    for line in customer.address.lines() {
+        y += LINE_HEIGHT;
+        canvas.text(MARGIN + ROW_PADDING, y, 10.0, Weight::Regular, line);
+    }
+    if !customer.pan.is_empty() {
+        y += LINE_HEIGHT;
+        canvas.text(MARGIN + ROW_PADDING, y, 10.0, Weight::Regular, &format!("PAN/VAT No: {}", customer.pan));
+    }
+    y += LINE_HEIGHT * 2.0;
+
+    let columns = [(MARGIN + ROW_PADDING, "S.N."), (MARGIN + 50.0, "Particulars"), (right - 40.0, "Qty")];
+    y = shaded_header(canvas, y, &columns);
+    for (index, (item_type, name, quantity, dinein_json, pack_json)) in order.item_data.iter().enumerate() {
+        let details = item_details(item_type, dinein_json, pack_json);
+        if y + LINE_HEIGHT * (details.len() + 1) as f32 > bottom {
+            canvas.new_page();
+            y = shaded_header(canvas, MARGIN, &columns);
+        }
+        canvas.text(MARGIN + ROW_PADDING, y, 10.0, Weight::Regular, &(index + 1).to_string());
+        canvas.text(MARGIN + 50.0, y, 10.0, Weight::Regular, name);
+        canvas.text_right(right - ROW_PADDING, y, 10.0, Weight::Regular, &quantity.to_string());
+        for detail in details {
+            y += LINE_HEIGHT - 3.0;
+            canvas.text(MARGIN + 62.0, y, 8.0, Weight::Regular, &detail);
+        }
+        y += ROW_PADDING;
+        canvas.line(MARGIN, y, right, y, 0.25);
+        y += LINE_HEIGHT;
+    }
+
+    if y + LINE_HEIGHT * 7.0 > bottom {
+        canvas.new_page();
+        y = MARGIN;
+    }
+    // Menu prices include VAT, so the taxable amount is backed out of the total
+    let mut totals = Vec::new();
+    if order.discount_amount > 0.0 {
+        totals.push(("Discount".to_string(), format!("-{:.2}", order.discount_amount)));
+    }
+    if config.vat_rate_percent > 0.0 {
+        let taxable = order.total_amount / (1.0 + config.vat_rate_percent / 100.0);
+        totals.push(("Taxable amount".to_string(), format!("{:.2}", taxable)));
+        totals.push((format!("VAT ({}%)", config.vat_rate_percent), format!("{:.2}", order.total_amount - taxable)));
+    }
+    for (label, amount) in totals {
+        canvas.text_right(right - 110.0, y, 10.0, Weight::Regular, &label);
+        canvas.text_right(right - ROW_PADDING, y, 10.0, Weight::Regular, &amount);
+        y += LINE_HEIGHT;
+    }
+    canvas.line(right - 220.0, y - 10.0, right, y - 10.0, 0.5);
+    y += 2.0;
+    canvas.text_right(right - 110.0, y, 12.0, Weight::Bold, "Grand total");
+    canvas.text_right(right - ROW_PADDING, y, 12.0, Weight::Bold, &format!("{:.2}", order.total_amount));
+
+    if let Some(footer) = letterhead::footer(config, DocumentType::Invoice) {
+        let mut y = bottom - LINE_HEIGHT * (footer.lines().count() + 2) as f32;
+        for line in footer.lines() {
+            canvas.text(MARGIN, y, 9.0, Weight::Regular, line);
+            y += LINE_HEIGHT;
+        }
+    }
+    let signature = bottom - LINE_HEIGHT;
+    canvas.line(right - 160.0, signature, right, signature, 0.5);
+    canvas.text_center(right - 80.0, signature + LINE_HEIGHT, 9.0, Weight::Regular, "Authorised signature");
+}
+
+async fn load_order(order_id: i64, state: &DbState) -> Result<Bill, PrintError> {
+    database::run(state, move |conn| {
+        let order = fetch_order_details(conn, order_id)?;
+        Ok(Bill { number: sequences::number_for_order(conn, BILL_SERIES, order_id)?, order })
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn generate_invoice_pdf(
+    order_id: i64,
+    customer: InvoiceCustomer,
+    path: String,
+    state: tauri::State<'_, DbState>,
+) -> Result<(), PrintError> {
+    let bill = load_order(order_id, &state).await?;
+    let mut pdf = PdfWriter::new(A4);
+    draw_invoice(&mut pdf, &bill, &customer, &config::current());
+    std::fs::write(&path, pdf.finish()).map_err(|e| PrintError::from_io(&format!("Could not write {}", path), &e))?;
+    info!("Invoice PDF for order {} written to {}", order_id, path);
+    Ok(())
+}
+
+#[cfg(windows)]
+fn spool_invoice(
+    printer_name: &str,
+    bill: &Bill,
+    customer: &InvoiceCustomer,
+    config: &PrinterConfig,
+) -> Result<(), PrintError> {
+    let mut canvas = GdiCanvas::start(printer_name, &format!("Invoice {}", bill.number))?;
+    for copy in 0..config.copies_for(DocumentType::Invoice) {
+        if copy > 0 {
+            canvas.new_page();
+        }
+        draw_invoice(&mut canvas, bill, customer, config);
+    }
+    canvas.finish()
+}
+
+// Use generate_invoice_pdf off Windows.
+#[cfg(not(windows))]
+fn spool_invoice(
+    printer_name: &str,
+    _bill: &Bill,
+    _customer: &InvoiceCustomer,
+    _config: &PrinterConfig,
+) -> Result<(), PrintError> {
+    Err(PrintError::windows_only(&format!("Printing an invoice on {}", printer_name)))
+}
+
+// Spools the invoice to an office printer: the one given, else the configured invoice printer,
+// else the Windows default.
+#[tauri::command]
+pub async fn print_invoice(
+    order_id: i64,
+    customer: InvoiceCustomer,
+    printer_name: Option<String>,
+    state: tauri::State<'_, DbState>,
+) -> Result<(), PrintError> {
+    let config = config::current();
+    let printer_name = match printer_name.or_else(|| config.invoice_printer_name.clone()) {
+        Some(name) => name,
+        None => spooler::get_default_printer()?
+            .ok_or_else(|| PrintError::new(ErrorCode::InvalidSettings, "No invoice printer configured"))?,
+    };
+    let bill = load_order(order_id, &state).await?;
+
+    spool_invoice(&printer_name, &bill, &customer, &config)?;
+    info!("Invoice for order {} sent to {}", order_id, printer_name);
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use super::error::{ErrorCode, PrintError};
+
+const PRINT_JOB: u16 = 0x0002;
+
+// Attribute value tags (RFC 8010 section 3.5)
+const OPERATION_ATTRIBUTES: u8 = 0x01;
+const END_OF_ATTRIBUTES: u8 = 0x03;
+const NAME_WITHOUT_LANGUAGE: u8 = 0x42;
+const URI: u8 = 0x45;
+const CHARSET: u8 = 0x47;
+const NATURAL_LANGUAGE: u8 = 0x48;
+const MIME_MEDIA_TYPE: u8 = 0x49;
+
+struct PrinterUrl {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+// ipp://host[:port]/path or http://host[:port]/path; IPP over TLS (ipps) isn't supported.
+fn parse_url(url: &str) -> Result<PrinterUrl, PrintError> {
+    let invalid = |reason: &str| PrintError::new(ErrorCode::InvalidSettings, format!("Invalid IPP URL {}: {}", url, reason));
+    let (rest, default_port) = if let Some(rest) = url.strip_prefix("ipp://") {
+        (rest, 631)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        (rest, 80)
+    } else {
+        return Err(invalid("expected ipp:// or http://"));
+    };
+
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/ipp/print"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|_| invalid("bad port"))?),
+        None => (authority, default_port),
+    };
+    if host.is_empty() {
+        return Err(invalid("missing host"));
+    }
+    Ok(PrinterUrl { host: host.to_string(), port, path: path.to_string() })
+}
+
+// host:port of the printer behind the URL, for reachability checks.
+pub(crate) fn socket_address(url: &str) -> Result<String, PrintError> {
+    let target = parse_url(url)?;
+    Ok(format!("{}:{}", target.host, target.port))
+}
+
+fn push_attribute(buf: &mut Vec<u8>, tag: u8, name: &str, value: &str) {
+    buf.push(tag);
+    buf.extend_from_slice(&(name.len() as u16).to_be_bytes());
+    buf.extend_from_slice(name.as_bytes());
+    buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buf.extend_from_slice(value.as_bytes());
+}
+
+fn print_job_request(printer_uri: &str, content: &[u8]) -> Vec<u8> {
+    let mut request = vec![0x01, 0x01];
+    request.extend_from_slice(&PRINT_JOB.to_be_bytes());
+    request.extend_from_slice(&1u32.to_be_bytes());
+    request.push(OPERATION_ATTRIBUTES);
+    push_attribute(&mut request, CHARSET, "attributes-charset", "utf-8");
+    push_attribute(&mut request, NATURAL_LANGUAGE, "attributes-natural-language", "en");
+    push_attribute(&mut request, URI, "printer-uri", printer_uri);
+    push_attribute(&mut request, NAME_WITHOUT_LANGUAGE, "requesting-user-name", "tauri-plugin-printer");
+    push_attribute(&mut request, NAME_WITHOUT_LANGUAGE, "job-name", "KOT Print");
+    push_attribute(&mut request, MIME_MEDIA_TYPE, "document-format", "application/octet-stream");
+    request.push(END_OF_ATTRIBUTES);
+    request.extend_from_slice(content);
+    request
+}
+
+// IPP status codes 0x0000-0x00FF are successful; the rest map onto our codes where they can.
+fn check_ipp_status(status: u16, url: &str) -> Result<(), PrintError> {
+    let code = match status {
+        0x0000..=0x00FF => return Ok(()),
+        0x0400 | 0x0408 => ErrorCode::InvalidContent,
+        0x0401 | 0x0402 | 0x0403 => ErrorCode::AccessDenied,
+        0x0406 => ErrorCode::PortNotFound,
+        0x040A => ErrorCode::InvalidContent,
+        0x0500..=0x05FF => ErrorCode::SpoolerError,
+        _ => ErrorCode::Unknown,
+    };
+    Err(PrintError::new(code, format!("{} rejected the job with IPP status 0x{:04X}", url, status)))
+}
+
+pub(crate) async fn print(content: &[u8], url: &str, job_timeout: Duration) -> Result<(), PrintError> {
+    let target = parse_url(url)?;
+    let printer_uri = format!("ipp://{}:{}{}", target.host, target.port, target.path);
+    let body = print_job_request(&printer_uri, content);
+
+    let exchange = async {
+        let mut stream = TcpStream::connect((target.host.as_str(), target.port))
+            .await
+            .map_err(|e| PrintError::from_io("Connection failed", &e))?;
+        let header = format!(
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/ipp\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            target.path,
+            target.host,
+            target.port,
+            body.len()
+        );
+        stream.write_all(header.as_bytes()).await.map_err(|e| PrintError::from_io("Write failed", &e))?;
+        stream.write_all(&body).await.map_err(|e| PrintError::from_io("Write failed", &e))?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.map_err(|e| PrintError::from_io("Read failed", &e))?;
+        Ok::<_, PrintError>(response)
+    };
+    let response = match timeout(job_timeout, exchange).await {
+        Ok(result) => result?,
+        Err(_) => return Err(PrintError::new(ErrorCode::Timeout, format!("{} did not answer in time", url))),
+    };
+
+    // Status line first, then the IPP response after the blank line
+    let status_line = response.split(|b| *b == b'\n').next().map(String::from_utf8_lossy).unwrap_or_default();
+    if !status_line.contains(" 200") {
+        return Err(PrintError::new(ErrorCode::ConnectionFailed, format!("{} answered {}", url, status_line.trim())));
+    }
+    let Some(header_end) = response.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return Err(PrintError::new(ErrorCode::Unknown, format!("{} sent a malformed response", url)));
+    };
+    let headers = String::from_utf8_lossy(&response[..header_end]).to_ascii_lowercase();
+    let mut body_start = header_end + 4;
+    // CUPS answers chunked; the IPP header sits right after the first chunk-size line
+    if headers.contains("transfer-encoding: chunked") {
+        body_start += response[body_start..].windows(2).position(|w| w == b"\r\n").map_or(0, |i| i + 2);
+    }
+    match response.get(body_start + 2..body_start + 4) {
+        Some(status) => check_ipp_status(u16::from_be_bytes([status[0], status[1]]), url),
+        None => Err(PrintError::new(ErrorCode::Unknown, format!("{} sent an empty IPP response", url))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_get_their_scheme_defaults() {
+        assert_eq!(socket_address("ipp://192.168.1.70").expect("url"), "192.168.1.70:631");
+        assert_eq!(socket_address("http://printer.local:8631/ipp/kitchen").expect("url"), "printer.local:8631");
+        let target = parse_url("ipp://192.168.1.70").expect("url");
+        assert_eq!(target.path, "/ipp/print");
+    }
+
+    #[test]
+    fn unsupported_urls_are_rejected() {
+        assert!(parse_url("ipps://192.168.1.70/ipp/print").is_err());
+        assert!(parse_url("ipp://:631/ipp/print").is_err());
+        assert!(parse_url("ipp://printer:port/ipp/print").is_err());
+    }
+
+    #[test]
+    fn print_job_request_encoding() {
+        let request = print_job_request("ipp://printer/ipp/print", b"payload");
+
+        // Version 1.1, Print-Job, request id 1, then the operation attributes
+        assert_eq!(&request[..9], &[0x01, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, OPERATION_ATTRIBUTES]);
+        let mut uri = vec![URI, 0x00, 0x0B];
+        uri.extend_from_slice(b"printer-uri");
+        uri.extend_from_slice(&[0x00, 0x17]);
+        uri.extend_from_slice(b"ipp://printer/ipp/print");
+        assert!(request.windows(uri.len()).any(|window| window == uri.as_slice()));
+        assert!(request.ends_with(&[&[END_OF_ATTRIBUTES][..], b"payload"].concat()));
+    }
+
+    #[test]
+    fn ipp_status_codes_map_onto_error_codes() {
+        assert!(check_ipp_status(0x0000, "ipp://printer").is_ok());
+        assert!(check_ipp_status(0x0001, "ipp://printer").is_ok());
+        assert_eq!(check_ipp_status(0x0401, "ipp://printer").err().map(|e| e.code), Some(ErrorCode::AccessDenied));
+        assert_eq!(check_ipp_status(0x040A, "ipp://printer").err().map(|e| e.code), Some(ErrorCode::InvalidContent));
+        assert_eq!(check_ipp_status(0x0502, "ipp://printer").err().map(|e| e.code), Some(ErrorCode::SpoolerError));
+    }
+}
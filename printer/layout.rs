@@ -0,0 +1,210 @@
+// Per-printer line spacing and paper feed around the cut. Documents are rendered once for every
+// printer, so each profile's settings are applied to the bytes on their way to that printer: ESC 3
+// after every ESC @, the blank lines in front of each GS V cut swapped for the profile's count, and
+// plain ESC/POS bold and cut swapped for the vendor's. The init sequence is added by the transport
+// instead, so raw, label and drawer jobs get it too. Only top-level commands are looked at, so
+// raster and logo data passes through untouched.
+
+use super::escpos;
+use super::vendor::{self, Protocol, VendorProfile};
+use super::PrinterProfile;
+
+const INIT: &[u8] = b"\x1B\x40";
+const LINE_FEED: u8 = b'\n';
+
+const BOLD: &[u8] = b"\x1B\x45";
+const CUT: &[u8] = b"\x1D\x56\x41\x00";
+const PARTIAL_CUT: &[u8] = b"\x1D\x56\x42\x00";
+
+// GS V m, or GS V m n for the feed-and-cut forms; returns whether the cut is partial.
+fn cut(command: &[u8]) -> Option<bool> {
+    match command {
+        [0x1D, 0x56, 0 | 48] | [0x1D, 0x56, 65, _] => Some(false),
+        [0x1D, 0x56, 1 | 49] | [0x1D, 0x56, 66, _] => Some(true),
+        _ => None,
+    }
+}
+
+// ESC E n; returns whether it turns bold on.
+fn bold(command: &[u8]) -> Option<bool> {
+    match command {
+        [0x1B, 0x45, n] => Some(n & 1 == 1),
+        _ => None,
+    }
+}
+
+// ESC d n feeds n lines in one command.
+fn feed(lines: u8) -> Vec<u8> {
+    if lines == 0 {
+        Vec::new()
+    } else {
+        vec![0x1B, 0x64, lines]
+    }
+}
+
+// Star Line mode uses other commands for spacing and feeds, so only ESC/POS printers get those; TSPL
+// labels have no ESC @ and are left alone.
+pub(crate) fn apply(payload: &[u8], profile: &PrinterProfile) -> Vec<u8> {
+    let commands = vendor::commands(profile.vendor_profile);
+    if commands.protocol == Protocol::Tspl {
+        return payload.to_vec();
+    }
+    let escpos = commands.cut.starts_with(b"\x1D\x56");
+    let adjusts_cut = escpos && (profile.feed_lines_before_cut.is_some() || profile.feed_lines_after_cut > 0);
+    let spacing = profile.line_spacing.filter(|_| escpos).map(|dots| vec![0x1B, 0x33, dots]).unwrap_or_default();
+    // Star Line Mode and the budget models that ignore GS V A get their own bold and cut
+    let translates = commands.protocol != Protocol::EscPos || commands.cut != CUT || commands.partial_cut != PARTIAL_CUT;
+    if spacing.is_empty() && !adjusts_cut && !translates {
+        return payload.to_vec();
+    }
+
+    let mut out = Vec::with_capacity(payload.len() + spacing.len() + 16);
+    if !payload.starts_with(INIT) {
+        out.extend_from_slice(&spacing);
+    }
+    // Line feeds printed as text since the last command; only these may be trimmed before a cut
+    let mut blank_lines = 0;
+    let mut i = 0;
+    while i < payload.len() {
+        if !matches!(payload[i], 0x1B | 0x1D | 0x1C | 0x10) {
+            blank_lines = if payload[i] == LINE_FEED { blank_lines + 1 } else { 0 };
+            out.push(payload[i]);
+            i += 1;
+            continue;
+        }
+        let command = &payload[i..i + escpos::command_length(&payload[i..])];
+        i += command.len();
+        let trailing = std::mem::take(&mut blank_lines);
+        if command == INIT {
+            // ESC @ resets the spacing too
+            out.extend_from_slice(INIT);
+            out.extend_from_slice(&spacing);
+        } else if let Some(on) = bold(command).filter(|_| translates) {
+            out.extend_from_slice(commands.bold(on));
+        } else if let Some(partial) = cut(command).filter(|_| adjusts_cut || translates) {
+            if let Some(lines) = profile.feed_lines_before_cut.filter(|_| adjusts_cut) {
+                out.truncate(out.len() - trailing);
+                // The last printed line still needs ending
+                out.push(LINE_FEED);
+                out.extend(feed(lines));
+            }
+            match (translates, partial) {
+                (false, _) => out.extend_from_slice(command),
+                (true, false) => out.extend_from_slice(&commands.cut),
+                (true, true) => out.extend_from_slice(&commands.partial_cut),
+            }
+            if adjusts_cut {
+                out.extend(feed(profile.feed_lines_after_cut));
+            }
+        } else {
+            out.extend_from_slice(command);
+        }
+    }
+    out
+}
+
+// The printer's init sequence after every top-level ESC @, since the reset would undo it. Content
+// without its own reset gets one so the sequence always starts from a known state; TSPL has no ESC @
+// and just gets the sequence in front.
+pub(crate) fn with_init(payload: &[u8], vendor: VendorProfile, init_sequence: &[u8]) -> Vec<u8> {
+    if init_sequence.is_empty() {
+        return payload.to_vec();
+    }
+    if vendor::commands(vendor).protocol == Protocol::Tspl {
+        return [init_sequence, payload].concat();
+    }
+    let mut out = Vec::with_capacity(payload.len() + init_sequence.len() + INIT.len());
+    if !payload.starts_with(INIT) {
+        out.extend_from_slice(INIT);
+        out.extend_from_slice(init_sequence);
+    }
+    let mut i = 0;
+    while i < payload.len() {
+        if !matches!(payload[i], 0x1B | 0x1D | 0x1C | 0x10) {
+            out.push(payload[i]);
+            i += 1;
+            continue;
+        }
+        let command = &payload[i..i + escpos::command_length(&payload[i..])];
+        i += command.len();
+        out.extend_from_slice(command);
+        if command == INIT {
+            out.extend_from_slice(init_sequence);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(vendor: VendorProfile) -> PrinterProfile {
+        let mut profile: PrinterProfile =
+            serde_json::from_value(serde_json::json!({ "name": "test", "transport": "network", "address": "127.0.0.1:9100" }))
+                .expect("profile");
+        profile.vendor_profile = vendor;
+        profile
+    }
+
+    #[test]
+    fn spacing_follows_every_reset() {
+        let mut printer = profile(VendorProfile::Generic);
+        printer.line_spacing = Some(24);
+
+        let out = apply(b"\x1B\x40a\n\x1B\x40b\n", &printer);
+
+        assert_eq!(out, b"\x1B\x40\x1B\x33\x18a\n\x1B\x40\x1B\x33\x18b\n");
+    }
+
+    #[test]
+    fn raster_data_is_left_alone() {
+        let mut printer = profile(VendorProfile::Xprinter);
+        printer.line_spacing = Some(24);
+        printer.feed_lines_before_cut = Some(3);
+        // GS v 0, 4 bytes wide and 1 dot high, whose data looks like ESC @ and GS V
+        let raster = [0x1D, 0x76, 0x30, 0x00, 0x04, 0x00, 0x01, 0x00, 0x1B, 0x40, 0x1D, 0x56];
+
+        let out = apply(&[b"\x1B\x40".as_slice(), &raster].concat(), &printer);
+
+        assert_eq!(out, [b"\x1B\x40\x1B\x33\x18".as_slice(), &raster].concat());
+    }
+
+    #[test]
+    fn only_printed_blank_lines_are_trimmed_before_the_cut() {
+        let mut printer = profile(VendorProfile::Generic);
+        printer.feed_lines_before_cut = Some(3);
+
+        assert_eq!(apply(b"a\n\n\n\x1D\x56\x41\x00", &printer), b"a\n\x1B\x64\x03\x1D\x56\x41\x00");
+        // The 0x0A here is ESC d's line count, not a blank line
+        assert_eq!(apply(b"a\x1B\x64\x0A\x1D\x56\x41\x00", &printer), b"a\x1B\x64\x0A\n\x1B\x64\x03\x1D\x56\x41\x00");
+    }
+
+    #[test]
+    fn star_line_gets_its_own_bold_and_cut() {
+        let out = apply(b"\x1B\x40\x1B\x45\x01x\x1B\x45\x00\x1D\x56\x41\x00", &profile(VendorProfile::StarLine));
+
+        assert_eq!(out, b"\x1B\x40\x1B\x45x\x1B\x46\x1B\x64\x02");
+    }
+
+    #[test]
+    fn budget_printers_get_the_short_cut() {
+        assert_eq!(apply(b"x\x1D\x56\x41\x00", &profile(VendorProfile::Rongta)), b"x\x1D\x56\x00");
+        assert_eq!(apply(b"x\x1D\x56\x42\x00", &profile(VendorProfile::Rongta)), b"x\x1D\x56\x01");
+    }
+
+    #[test]
+    fn init_sequence_follows_each_reset() {
+        let out = with_init(b"\x1B\x40a\x1B\x40b", VendorProfile::Generic, b"\x1C\x2E");
+
+        assert_eq!(out, b"\x1B\x40\x1C\x2Ea\x1B\x40\x1C\x2Eb");
+    }
+
+    #[test]
+    fn content_without_a_reset_gets_one_before_the_init_sequence() {
+        let kick = b"\x1B\x70\x00\x19\xFA";
+
+        assert_eq!(with_init(kick, VendorProfile::Generic, b"\x1C\x2E"), [b"\x1B\x40\x1C\x2E".as_slice(), kick].concat());
+        assert_eq!(with_init(b"CLS\r\n", VendorProfile::Tspl, b"CODEPAGE 1252\r\n"), b"CODEPAGE 1252\r\nCLS\r\n");
+    }
+}
@@ -0,0 +1,124 @@
+// The business details printed at the top of thermal documents and the closing text at the
+// bottom. Both live in the settings (so every terminal sharing the database prints the same) and
+// the PDF letterhead reads the same name, address, phone and PAN. Footers are per document type:
+// the KOT, PDF receipt and invoice generators add their own, and content the host app renders
+// (bills, refunds, reports) gets it wherever it puts `{{footer}}`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::db::DbState;
+
+use super::config::{self, PrinterConfig};
+use super::error::{ErrorCode, PrintError};
+use super::{DocumentType, BOLD_OFF, BOLD_ON};
+
+const ALIGN_CENTER: &str = "\x1B\x61\x01";
+const ALIGN_LEFT: &str = "\x1B\x61\x00";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReceiptHeader {
+    pub name: String,
+    // One printed line per line
+    pub address: String,
+    pub phone: String,
+    pub pan: String,
+    // Which thermal documents print it
+    pub document_types: Vec<DocumentType>,
+}
+
+pub(crate) fn default_footers() -> HashMap<DocumentType, String> {
+    [
+        (DocumentType::Kot, "Note: This is not a bill. Please contact cash counter for the bill.".to_string()),
+        (DocumentType::Bill, "Thank you for your order!".to_string()),
+    ]
+    .into()
+}
+
+// The letterhead as plain lines, skipping whatever isn't filled in.
+pub(crate) fn header_lines(config: &PrinterConfig) -> Vec<String> {
+    let mut lines = Vec::new();
+    if !config.business_name.trim().is_empty() {
+        lines.push(config.business_name.trim().to_string());
+    }
+    lines.extend(config.business_address.lines().map(str::trim).filter(|line| !line.is_empty()).map(String::from));
+    if !config.business_phone.trim().is_empty() {
+        lines.push(format!("Phone: {}", config.business_phone.trim()));
+    }
+    if !config.business_pan.trim().is_empty() {
+        lines.push(format!("PAN: {}", config.business_pan.trim()));
+    }
+    lines
+}
+
+// Centered, with the business name in bold; empty when `document_type` doesn't print a header.
+pub(crate) fn escpos_header(config: &PrinterConfig, document_type: DocumentType) -> String {
+    if !config.header_document_types.contains(&document_type) {
+        return String::new();
+    }
+    let lines = header_lines(config);
+    if lines.is_empty() {
+        return String::new();
+    }
+    let mut header = String::from(ALIGN_CENTER);
+    for (i, line) in lines.iter().enumerate() {
+        // Only the name is bold, and only when there is a name
+        if i == 0 && !config.business_name.trim().is_empty() {
+            header.push_str(&format!("{}{}{}\n", BOLD_ON, line, BOLD_OFF));
+        } else {
+            header.push_str(&format!("{}\n", line));
+        }
+    }
+    header.push_str(ALIGN_LEFT);
+    header
+}
+
+// The closing text for `document_type`, if it has one.
+pub(crate) fn footer(config: &PrinterConfig, document_type: DocumentType) -> Option<&str> {
+    config.document_footers.get(&document_type).map(|text| text.trim_end()).filter(|text| !text.is_empty())
+}
+
+#[tauri::command]
+pub fn get_receipt_header() -> ReceiptHeader {
+    let config = config::current();
+    ReceiptHeader {
+        name: config.business_name,
+        address: config.business_address,
+        phone: config.business_phone,
+        pan: config.business_pan,
+        document_types: config.header_document_types,
+    }
+}
+
+#[tauri::command]
+pub fn set_receipt_header(header: ReceiptHeader, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    config::update_persisted(&state, |config| {
+        config.business_name = header.name;
+        config.business_address = header.address;
+        config.business_phone = header.phone;
+        config.business_pan = header.pan;
+        config.header_document_types = header.document_types;
+    })
+}
+
+// `None` (or blank text) leaves the document without a footer.
+#[tauri::command]
+pub fn set_document_footer(
+    document_type: DocumentType,
+    text: Option<String>,
+    state: tauri::State<'_, DbState>,
+) -> Result<(), PrintError> {
+    let text = text.filter(|text| !text.trim().is_empty());
+    if text.as_deref().is_some_and(|text| text.contains('\x1B') || text.contains('\x1D')) {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "Footers are plain text; printer commands aren't allowed"));
+    }
+    config::update_persisted(&state, |config| match text {
+        Some(text) => {
+            config.document_footers.insert(document_type, text);
+        }
+        None => {
+            config.document_footers.remove(&document_type);
+        }
+    })
+}
@@ -0,0 +1,152 @@
+// The plugin's own log, kept apart from whatever the host app does with `log`: every record from
+// the plugin's `error!`/`warn!`/`info!`/`debug!` macros is passed on to the host's logger and
+// also appended as one JSON line to printer.log under the app data dir. Files rotate at
+// MAX_FILE_BYTES and the newest KEEP_FILES are kept, so the support screen can read recent printer
+// errors with get_recent_logs.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use super::error::{ErrorCode, PrintError};
+
+const FILE_NAME: &str = "printer";
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+// printer.log plus printer.1.log .. printer.4.log
+const KEEP_FILES: usize = 5;
+const DEFAULT_LINES: usize = 200;
+const MAX_LINES: usize = 5000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+// The plugin's log_level, applied to its records here rather than with log::set_max_level, which
+// would change it for the whole app. Everything passes until setup sets it.
+static LEVEL: AtomicUsize = AtomicUsize::new(log::LevelFilter::Trace as usize);
+
+struct LogFile {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+}
+
+fn log_file() -> &'static Mutex<Option<LogFile>> {
+    static LOG_FILE: OnceLock<Mutex<Option<LogFile>>> = OnceLock::new();
+    LOG_FILE.get_or_init(|| Mutex::new(None))
+}
+
+// Index 0 is the live file.
+fn file_path(dir: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => dir.join(format!("{}.log", FILE_NAME)),
+        n => dir.join(format!("{}.{}.log", FILE_NAME, n)),
+    }
+}
+
+fn open(dir: &Path) -> std::io::Result<LogFile> {
+    fs::create_dir_all(dir)?;
+    let file = OpenOptions::new().create(true).append(true).open(file_path(dir, 0))?;
+    let size = file.metadata()?.len();
+    Ok(LogFile { dir: dir.to_path_buf(), file, size })
+}
+
+fn rotate(log: &mut LogFile) -> std::io::Result<()> {
+    let _ = fs::remove_file(file_path(&log.dir, KEEP_FILES - 1));
+    for index in (0..KEEP_FILES - 1).rev() {
+        let from = file_path(&log.dir, index);
+        if from.exists() {
+            fs::rename(from, file_path(&log.dir, index + 1))?;
+        }
+    }
+    *log = open(&log.dir)?;
+    Ok(())
+}
+
+// Called from plugin setup. Records logged before this only reach the host's logger.
+pub(crate) fn init(dir: PathBuf, level: log::LevelFilter) {
+    LEVEL.store(level as usize, Ordering::Relaxed);
+    match open(&dir) {
+        Ok(file) => *log_file().lock().unwrap_or_else(|e| e.into_inner()) = Some(file),
+        Err(e) => log::error!("Could not open the printer log in {}: {}", dir.display(), e),
+    }
+}
+
+pub(crate) fn directory() -> Option<PathBuf> {
+    log_file().lock().unwrap_or_else(|e| e.into_inner()).as_ref().map(|log| log.dir.clone())
+}
+
+// Backs the logging macros; use those rather than calling this directly.
+pub(crate) fn record(level: log::Level, target: &str, args: fmt::Arguments) {
+    if level as usize > LEVEL.load(Ordering::Relaxed) {
+        return;
+    }
+    log::log!(target: target, level, "{}", args);
+
+    let mut guard = log_file().lock().unwrap_or_else(|e| e.into_inner());
+    let Some(log) = guard.as_mut() else {
+        return;
+    };
+    let entry = LogEntry {
+        timestamp: chrono::Local::now().to_rfc3339(),
+        level: level.to_string(),
+        target: target.to_string(),
+        message: args.to_string(),
+    };
+    let Ok(mut line) = serde_json::to_string(&entry) else {
+        return;
+    };
+    line.push('\n');
+
+    // Logging about logging would recurse; stderr is the last resort
+    if log.size + line.len() as u64 > MAX_FILE_BYTES {
+        if let Err(e) = rotate(log) {
+            eprintln!("Could not rotate the printer log: {}", e);
+        }
+    }
+    match log.file.write_all(line.as_bytes()) {
+        Ok(()) => log.size += line.len() as u64,
+        Err(e) => eprintln!("Could not write the printer log: {}", e),
+    }
+}
+
+// The newest `lines` entries at `level` or more severe, oldest first. Defaults to 200 at any level.
+#[tauri::command]
+pub fn get_recent_logs(lines: Option<usize>, level: Option<String>) -> Result<Vec<LogEntry>, PrintError> {
+    let level = match level {
+        Some(level) => log::Level::from_str(&level)
+            .map_err(|_| PrintError::new(ErrorCode::InvalidSettings, format!("Unknown log level {}", level)))?,
+        None => log::Level::Trace,
+    };
+    let wanted = lines.unwrap_or(DEFAULT_LINES).min(MAX_LINES);
+    let Some(dir) = directory() else {
+        return Ok(Vec::new());
+    };
+
+    let mut entries = Vec::new();
+    for index in 0..KEEP_FILES {
+        let Ok(contents) = fs::read_to_string(file_path(&dir, index)) else {
+            break;
+        };
+        let matching = contents
+            .lines()
+            .rev()
+            .filter_map(|line| serde_json::from_str::<LogEntry>(line).ok())
+            .filter(|entry| log::Level::from_str(&entry.level).map_or(false, |l| l <= level));
+        entries.extend(matching.take(wanted - entries.len()));
+        if entries.len() >= wanted {
+            break;
+        }
+    }
+    entries.reverse();
+    Ok(entries)
+}
@@ -0,0 +1,166 @@
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use super::error::{ErrorCode, PrintError};
+
+const DEFAULT_PORT: u16 = 515;
+
+// LPD job numbers are three digits and only need to be unique per host for a while.
+static JOB_NUMBER: AtomicU16 = AtomicU16::new(0);
+
+struct Queue {
+    address: String,
+    name: String,
+}
+
+// host[:port]/queue; print servers usually call their queue "lp" or "PORT1".
+fn parse_address(address: &str) -> Result<Queue, PrintError> {
+    let Some((authority, name)) = address.split_once('/') else {
+        return Err(PrintError::new(
+            ErrorCode::InvalidSettings,
+            format!("{} is missing a queue name, e.g. 192.168.1.60/lp", address),
+        ));
+    };
+    if authority.is_empty() || name.is_empty() {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, format!("Invalid LPR address {}", address)));
+    }
+    let address = if authority.contains(':') { authority.to_string() } else { format!("{}:{}", authority, DEFAULT_PORT) };
+    Ok(Queue { address, name: name.to_string() })
+}
+
+// host:port of the print server, for reachability checks.
+pub(crate) fn socket_address(address: &str) -> Result<String, PrintError> {
+    Ok(parse_address(address)?.address)
+}
+
+fn host_name() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "pos".to_string())
+}
+
+// Every LPD command and file transfer is answered with a single zero byte on success.
+async fn send(stream: &mut TcpStream, bytes: &[u8], step: &str) -> Result<(), PrintError> {
+    stream.write_all(bytes).await.map_err(|e| PrintError::from_io("Write failed", &e))?;
+    let mut ack = [0u8; 1];
+    stream.read_exact(&mut ack).await.map_err(|e| PrintError::from_io("Read failed", &e))?;
+    if ack[0] != 0 {
+        return Err(PrintError::new(ErrorCode::SpoolerError, format!("Print server refused the {} (0x{:02X})", step, ack[0])));
+    }
+    Ok(())
+}
+
+// RFC 1179 receive-job: control file first, then the data file printed as-is ('l').
+pub(crate) async fn print(content: &[u8], address: &str, job_timeout: Duration) -> Result<(), PrintError> {
+    let queue = parse_address(address)?;
+    let host = host_name();
+    let job = JOB_NUMBER.fetch_add(1, Ordering::Relaxed) % 1000;
+    let data_name = format!("dfA{:03}{}", job, host);
+    let control = format!("H{}\nPpos\nJKOT Print\nl{}\nU{}\nN{}\n", host, data_name, data_name, data_name);
+
+    let exchange = async {
+        let mut stream = TcpStream::connect(&queue.address)
+            .await
+            .map_err(|e| PrintError::from_io("Connection failed", &e))?;
+        send(&mut stream, format!("\x02{}\n", queue.name).as_bytes(), "queue").await?;
+        send(&mut stream, format!("\x02{} cfA{:03}{}\n", control.len(), job, host).as_bytes(), "control file").await?;
+        send(&mut stream, &[control.as_bytes(), &[0]].concat(), "control file").await?;
+        send(&mut stream, format!("\x03{} {}\n", content.len(), data_name).as_bytes(), "data file").await?;
+        send(&mut stream, &[content, &[0]].concat(), "data file").await
+    };
+    match timeout(job_timeout, exchange).await {
+        Ok(result) => result,
+        Err(_) => Err(PrintError::new(ErrorCode::Timeout, format!("{} did not accept the job in time", address))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn queue_address_defaults_to_the_lpd_port() {
+        let queue = parse_address("192.168.1.60/lp").expect("address");
+        assert_eq!((queue.address.as_str(), queue.name.as_str()), ("192.168.1.60:515", "lp"));
+
+        let queue = parse_address("printserver:9515/PORT1").expect("address");
+        assert_eq!((queue.address.as_str(), queue.name.as_str()), ("printserver:9515", "PORT1"));
+    }
+
+    #[test]
+    fn queue_name_is_required() {
+        assert_eq!(parse_address("192.168.1.60").err().map(|e| e.code), Some(ErrorCode::InvalidSettings));
+        assert_eq!(parse_address("/lp").err().map(|e| e.code), Some(ErrorCode::InvalidSettings));
+    }
+
+    async fn read_line(stream: &mut TcpStream) -> Vec<u8> {
+        let mut line = Vec::new();
+        loop {
+            let byte = stream.read_u8().await.expect("read");
+            line.push(byte);
+            if byte == b'\n' {
+                return line;
+            }
+        }
+    }
+
+    async fn read_file(stream: &mut TcpStream, length: usize) -> Vec<u8> {
+        let mut file = vec![0; length + 1];
+        stream.read_exact(&mut file).await.expect("read file");
+        assert_eq!(file.pop(), Some(0), "files end with a NUL");
+        file
+    }
+
+    // Length announced in a "\x02<len> cfA..." or "\x03<len> dfA..." line
+    fn announced_length(line: &[u8]) -> usize {
+        let line = std::str::from_utf8(&line[1..]).expect("utf-8");
+        line.split(' ').next().and_then(|length| length.parse().ok()).expect("length")
+    }
+
+    #[tokio::test]
+    async fn job_is_sent_as_a_control_file_then_the_data() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind fake print server");
+        let address = format!("{}/lp", listener.local_addr().expect("address"));
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.expect("accept");
+            let queue = read_line(&mut stream).await;
+            stream.write_all(&[0]).await.expect("ack");
+            let control_header = read_line(&mut stream).await;
+            stream.write_all(&[0]).await.expect("ack");
+            let control = read_file(&mut stream, announced_length(&control_header)).await;
+            stream.write_all(&[0]).await.expect("ack");
+            let data_header = read_line(&mut stream).await;
+            stream.write_all(&[0]).await.expect("ack");
+            let data = read_file(&mut stream, announced_length(&data_header)).await;
+            stream.write_all(&[0]).await.expect("ack");
+            (queue, control, data_header, data)
+        });
+
+        print(b"\x1B\x40ticket\n", &address, Duration::from_secs(2)).await.expect("print");
+        let (queue, control, data_header, data) = server.await.expect("fake print server");
+
+        assert_eq!(queue, b"\x02lp\n");
+        let control = String::from_utf8(control).expect("control file");
+        let data_name = control.lines().find_map(|line| line.strip_prefix('l')).expect("print-file line");
+        assert!(data_name.starts_with("dfA"));
+        assert_eq!(data_header, format!("\x03{} {}\n", data.len(), data_name).into_bytes());
+        assert_eq!(data, b"\x1B\x40ticket\n");
+    }
+
+    #[tokio::test]
+    async fn refused_job_is_a_spooler_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind fake print server");
+        let address = format!("{}/lp", listener.local_addr().expect("address"));
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.expect("accept");
+            read_line(&mut stream).await;
+            stream.write_all(&[1]).await.expect("refuse");
+        });
+
+        let error = print(b"ticket", &address, Duration::from_secs(2)).await.expect_err("refused");
+
+        assert_eq!(error.code, ErrorCode::SpoolerError);
+    }
+}
@@ -0,0 +1,84 @@
+// In-memory printers for the print pipeline's tests. `MemoryDriver` is a `PrinterDriver`, so a
+// `Destination::Driver { driver: "memory", address: name }` goes through the same transport path
+// as any host-registered one; it keeps every payload instead of printing it, and can be told to
+// fail or stall so retries, failover and the circuit breaker can be exercised without hardware.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use super::breaker;
+use super::driver::{self, PrinterDriver};
+use super::error::{ErrorCode, PrintError};
+use super::Destination;
+
+pub(crate) const DRIVER_NAME: &str = "memory";
+
+#[derive(Debug, Default)]
+struct MemoryPrinter {
+    written: Vec<Vec<u8>>,
+    // Each job pops the next injected failure, if any
+    failures: Vec<ErrorCode>,
+    latency: Duration,
+}
+
+fn printers() -> &'static Mutex<HashMap<String, MemoryPrinter>> {
+    static PRINTERS: OnceLock<Mutex<HashMap<String, MemoryPrinter>>> = OnceLock::new();
+    PRINTERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+struct MemoryDriver;
+
+impl PrinterDriver for MemoryDriver {
+    fn print(&self, address: &str, content: &[u8], timeout: Duration) -> Result<(), PrintError> {
+        let latency = {
+            let printers = printers().lock().unwrap_or_else(|e| e.into_inner());
+            printers.get(address).map_or(Duration::ZERO, |printer| printer.latency)
+        };
+        if latency > timeout {
+            std::thread::sleep(timeout);
+            return Err(PrintError::new(ErrorCode::Timeout, format!("Memory printer {} timed out", address)));
+        }
+        std::thread::sleep(latency);
+
+        let mut printers = printers().lock().unwrap_or_else(|e| e.into_inner());
+        let printer = printers.entry(address.to_string()).or_default();
+        if !printer.failures.is_empty() {
+            let code = printer.failures.remove(0);
+            return Err(PrintError::new(code, format!("Injected failure on memory printer {}", address)));
+        }
+        printer.written.push(content.to_vec());
+        Ok(())
+    }
+}
+
+// Registers the driver (once is enough) and returns the destination for printer `name`.
+pub(crate) fn destination(name: &str) -> Destination {
+    static REGISTERED: OnceLock<()> = OnceLock::new();
+    REGISTERED.get_or_init(|| driver::register(DRIVER_NAME.to_string(), Arc::new(MemoryDriver)));
+    Destination::Driver { driver: DRIVER_NAME.to_string(), address: name.to_string() }
+}
+
+// The next `count` jobs sent to `name` fail with `code`; nothing is recorded for them.
+pub(crate) fn fail_next(name: &str, count: usize, code: ErrorCode) {
+    let mut printers = printers().lock().unwrap_or_else(|e| e.into_inner());
+    printers.entry(name.to_string()).or_default().failures.extend(std::iter::repeat(code).take(count));
+}
+
+// Every job waits this long before it is accepted; longer than the job timeout means a timeout.
+pub(crate) fn set_latency(name: &str, latency: Duration) {
+    let mut printers = printers().lock().unwrap_or_else(|e| e.into_inner());
+    printers.entry(name.to_string()).or_default().latency = latency;
+}
+
+// Payloads accepted so far, oldest first; the record is cleared.
+pub(crate) fn take_written(name: &str) -> Vec<Vec<u8>> {
+    let mut printers = printers().lock().unwrap_or_else(|e| e.into_inner());
+    printers.get_mut(name).map(|printer| std::mem::take(&mut printer.written)).unwrap_or_default()
+}
+
+// Forgets printer `name` along with its breaker. Tests run in parallel, so each resets only its own.
+pub(crate) fn reset(name: &str) {
+    breaker::reset(&destination(name).key());
+    printers().lock().unwrap_or_else(|e| e.into_inner()).remove(name);
+}
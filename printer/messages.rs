@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+use super::error::ErrorCode;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    Ne,
+}
+
+pub fn user_message(code: ErrorCode, language: Language) -> &'static str {
+    match language {
+        Language::En => english(code),
+        Language::Ne => nepali(code),
+    }
+}
+
+fn english(code: ErrorCode) -> &'static str {
+    match code {
+        ErrorCode::InvalidContent => "There is nothing to print.",
+        ErrorCode::InvalidSettings => "Printer settings are incomplete or invalid.",
+        ErrorCode::PortNotFound => "Printer not found. Check the cable and the printer name.",
+        ErrorCode::Timeout => "The printer did not respond in time.",
+        ErrorCode::PaperOut => "The printer is out of paper.",
+        ErrorCode::CoverOpen => "The printer cover is open. Close it and print again.",
+        ErrorCode::AccessDenied => "Access to the printer was denied.",
+        ErrorCode::SpoolerError => "Windows could not send the job to the printer.",
+        ErrorCode::ConnectionFailed => "Could not connect to the network printer.",
+        ErrorCode::DuplicateJob => "This ticket was already sent to the printer.",
+        ErrorCode::Database => "A database error occurred while printing.",
+        ErrorCode::Unknown => "Printing failed.",
+    }
+}
+
+fn nepali(code: ErrorCode) -> &'static str {
+    match code {
+        ErrorCode::InvalidContent => "प्रिन्ट गर्ने सामग्री खाली छ।",
+        ErrorCode::InvalidSettings => "प्रिन्टर सेटिङ अपूर्ण वा गलत छ।",
+        ErrorCode::PortNotFound => "प्रिन्टर भेटिएन। केबल र प्रिन्टरको नाम जाँच गर्नुहोस्।",
+        ErrorCode::Timeout => "प्रिन्टरले समयमै जवाफ दिएन।",
+        ErrorCode::PaperOut => "प्रिन्टरमा कागज सकियो।",
+        ErrorCode::CoverOpen => "प्रिन्टरको ढकनी खुला छ। बन्द गरेर फेरि प्रिन्ट गर्नुहोस्।",
+        ErrorCode::AccessDenied => "प्रिन्टर प्रयोग गर्ने अनुमति छैन।",
+        ErrorCode::SpoolerError => "विन्डोजले प्रिन्टरमा काम पठाउन सकेन।",
+        ErrorCode::ConnectionFailed => "नेटवर्क प्रिन्टरमा जडान हुन सकेन।",
+        ErrorCode::DuplicateJob => "यो टिकट पहिल्यै प्रिन्टरमा पठाइसकिएको छ।",
+        ErrorCode::Database => "प्रिन्ट गर्दा डाटाबेसमा त्रुटि भयो।",
+        ErrorCode::Unknown => "प्रिन्ट गर्न सकिएन।",
+    }
+}
@@ -0,0 +1,147 @@
+// Per-printer counters worked out from the job log (print_jobs), for finding the station printer
+// that keeps dropping tickets.
+
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::db::DbState;
+
+use super::clock;
+use super::database;
+use super::error::{ErrorCode, PrintError};
+use super::profile;
+use super::Destination;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsRange {
+    LastHour,
+    // Since midnight in the configured timezone
+    #[default]
+    Today,
+    #[serde(rename = "last_24_hours")]
+    Last24Hours,
+    #[serde(rename = "last_7_days")]
+    Last7Days,
+    All,
+}
+
+impl MetricsRange {
+    // Lower bound for created_at, which SQLite writes in UTC
+    fn since(self) -> Option<String> {
+        let now = Utc::now();
+        let since = match self {
+            MetricsRange::LastHour => now - chrono::Duration::hours(1),
+            MetricsRange::Today => clock::start_of_day(clock::today()),
+            MetricsRange::Last24Hours => now - chrono::Duration::hours(24),
+            MetricsRange::Last7Days => now - chrono::Duration::days(7),
+            MetricsRange::All => return None,
+        };
+        Some(since.format("%Y-%m-%d %H:%M:%S").to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PrinterMetrics {
+    pub destination: Destination,
+    // `None` for destinations without a saved profile; `name` is then the destination key
+    pub printer_id: Option<i64>,
+    pub name: String,
+    pub jobs: u32,
+    pub successes: u32,
+    // Accepted into the offline queue rather than printed
+    pub queued: u32,
+    pub failures: HashMap<ErrorCode, u32>,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    // Jobs for an order document this printer had already been sent
+    pub retries: u32,
+}
+
+// Nearest-rank percentile of an ascending list.
+fn percentile(sorted: &[u64], p: f64) -> Option<u64> {
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.max(1) - 1).copied()
+}
+
+pub(crate) fn compute(conn: &Connection, range: MetricsRange) -> Result<Vec<PrinterMetrics>, PrintError> {
+    let profiles: HashMap<String, (Option<i64>, String)> = profile::list(conn)?
+        .into_iter()
+        .map(|p| (p.destination().key(), (p.id, p.name)))
+        .collect();
+
+    let mut stmt = conn.prepare(
+        "SELECT order_id, document_type, destination, printed, error, error_code, duration_ms FROM print_jobs
+         WHERE (?1 IS NULL OR created_at >= ?1) ORDER BY id",
+    )?;
+    let mut rows = stmt.query(params![range.since()])?;
+
+    let mut metrics: HashMap<String, PrinterMetrics> = HashMap::new();
+    let mut durations: HashMap<String, Vec<u64>> = HashMap::new();
+    let mut sent: HashSet<(i64, String, String)> = HashSet::new();
+    while let Some(row) = rows.next()? {
+        let stored: String = row.get("destination")?;
+        let Ok(destination) = serde_json::from_str::<Destination>(&stored) else {
+            continue;
+        };
+        let key = destination.key();
+        let entry = metrics.entry(key.clone()).or_insert_with(|| {
+            let (printer_id, name) = profiles.get(&key).cloned().unwrap_or_else(|| (None, key.clone()));
+            PrinterMetrics {
+                destination,
+                printer_id,
+                name,
+                jobs: 0,
+                successes: 0,
+                queued: 0,
+                failures: HashMap::new(),
+                p50_ms: None,
+                p95_ms: None,
+                retries: 0,
+            }
+        });
+
+        entry.jobs += 1;
+        let printed: bool = row.get("printed")?;
+        let error: Option<String> = row.get("error")?;
+        // Jobs logged before error codes were kept count as Unknown
+        let error_code: Option<String> = row.get("error_code")?;
+        match (printed, error) {
+            (true, _) => entry.successes += 1,
+            (false, None) => entry.queued += 1,
+            (false, Some(_)) => {
+                let code = error_code.as_deref().map_or(ErrorCode::Unknown, ErrorCode::parse);
+                *entry.failures.entry(code).or_default() += 1;
+            }
+        }
+        if !sent.insert((row.get("order_id")?, row.get("document_type")?, key.clone())) {
+            entry.retries += 1;
+        }
+        if let Some(duration_ms) = row.get::<_, Option<i64>>("duration_ms")? {
+            durations.entry(key).or_default().push(duration_ms.max(0) as u64);
+        }
+    }
+
+    let mut metrics: Vec<PrinterMetrics> = metrics
+        .into_iter()
+        .map(|(key, mut entry)| {
+            if let Some(mut durations) = durations.remove(&key) {
+                durations.sort_unstable();
+                entry.p50_ms = percentile(&durations, 0.5);
+                entry.p95_ms = percentile(&durations, 0.95);
+            }
+            entry
+        })
+        .collect();
+    metrics.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(metrics)
+}
+
+// Today's figures unless a range is given.
+#[tauri::command]
+pub fn get_printer_metrics(range: Option<MetricsRange>, state: tauri::State<'_, DbState>) -> Result<Vec<PrinterMetrics>, PrintError> {
+    let conn = database::connection(&state)?;
+    compute(&conn, range.unwrap_or_default())
+}
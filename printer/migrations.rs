@@ -0,0 +1,248 @@
+use rusqlite::{params, Connection};
+
+use crate::db::DbState;
+
+use super::database;
+use super::error::{ErrorCode, PrintError};
+use super::{archive, config, profile, secrets};
+
+type Migration = fn(&Connection) -> rusqlite::Result<()>;
+
+// Migration N takes the printer schema from version N-1 to N. Shipped entries are never edited;
+// schema changes are appended as a new entry.
+const MIGRATIONS: &[Migration] = &[
+    // 1: the tables as they stood before versioning. Idempotent, so databases from those releases
+    // (with or without the later columns) come through it intact
+    baseline,
+    // 2: printers and archived jobs belong to an outlet (branch); NULL is shared by all
+    outlets,
+    // 3: printers on a transport registered by the host app name their driver
+    drivers,
+    // 4: jobs accepted but not yet delivered, e.g. still in flight when the app closed
+    print_queue,
+    // 5: queued jobs remember their order so it can be marked printed once they go out
+    queue_orders,
+    // 6: which order documents the plugin was asked to print and whether they all went out
+    print_attempts,
+    // 7: every job is logged with its error code and latency, for the printer metrics
+    job_metrics,
+    // 8: receipts emailed to customers and whether the send went through
+    receipt_emails,
+    // 9: bill number counters, and the number each order was given
+    bill_sequences,
+    // 10: the audit record of each bill's original print, which can't be edited or deleted
+    fiscal_bills,
+    // 11: jobs printed while the system clock was known to be wrong record by how much
+    clock_drift,
+    // 12: per-printer line spacing and feed around the cut
+    printer_feeds,
+    // 13: how much of each item an order's KOTs have sent to the kitchen, for ADD-ON tickets
+    kot_printed_items,
+    // 14: per-printer setup bytes sent after ESC @
+    printer_init_sequence,
+    // 15: credentials move out of the settings JSON into their own table
+    printer_secrets,
+    // 16: archived jobs keep what the caller sent as well, before copies, watermark and layout
+    archive_sources,
+    // 17: how many times each fiscal bill has been sent, taken atomically so only one is the original
+    fiscal_prints,
+];
+
+fn baseline(conn: &Connection) -> rusqlite::Result<()> {
+    profile::create_table(conn)?;
+    config::create_table(conn)?;
+    archive::create_table(conn)
+}
+
+fn outlets(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE printers ADD COLUMN outlet_id TEXT;
+        ALTER TABLE print_jobs ADD COLUMN outlet_id TEXT;
+        CREATE INDEX IF NOT EXISTS print_jobs_outlet_id ON print_jobs (outlet_id);",
+    )
+}
+
+fn drivers(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch("ALTER TABLE printers ADD COLUMN driver TEXT;")
+}
+
+fn print_queue(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE print_queue (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            destination_key TEXT NOT NULL,
+            destination TEXT NOT NULL,
+            payload BLOB NOT NULL,
+            reason TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX print_queue_destination_key ON print_queue (destination_key);",
+    )
+}
+
+fn queue_orders(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch("ALTER TABLE print_queue ADD COLUMN order_id INTEGER;")
+}
+
+fn print_attempts(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE print_attempts (
+            order_id INTEGER NOT NULL,
+            document_type TEXT NOT NULL,
+            printed INTEGER NOT NULL,
+            error TEXT,
+            updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+            PRIMARY KEY (order_id, document_type)
+        );
+        CREATE INDEX print_attempts_updated_at ON print_attempts (updated_at);",
+    )
+}
+
+fn job_metrics(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE print_jobs ADD COLUMN error_code TEXT;
+        ALTER TABLE print_jobs ADD COLUMN duration_ms INTEGER;",
+    )
+}
+
+fn receipt_emails(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE receipt_emails (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            order_id INTEGER NOT NULL,
+            address TEXT NOT NULL,
+            sent INTEGER NOT NULL,
+            error TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX receipt_emails_order_id ON receipt_emails (order_id);",
+    )
+}
+
+fn bill_sequences(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE bill_sequences (
+            series TEXT PRIMARY KEY,
+            period TEXT NOT NULL,
+            last_number INTEGER NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE TABLE bill_numbers (
+            series TEXT NOT NULL,
+            order_id INTEGER NOT NULL,
+            number TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            PRIMARY KEY (series, order_id)
+        );",
+    )
+}
+
+fn fiscal_bills(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE fiscal_bills (
+            order_id INTEGER PRIMARY KEY,
+            bill_number TEXT NOT NULL UNIQUE,
+            payload_hash TEXT NOT NULL,
+            printed_at TEXT NOT NULL DEFAULT (datetime('now')),
+            copies INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE TRIGGER fiscal_bills_immutable BEFORE UPDATE OF order_id, bill_number, payload_hash, printed_at ON fiscal_bills
+        BEGIN
+            SELECT RAISE(ABORT, 'fiscal bill records cannot be changed');
+        END;
+        CREATE TRIGGER fiscal_bills_permanent BEFORE DELETE ON fiscal_bills
+        BEGIN
+            SELECT RAISE(ABORT, 'fiscal bill records cannot be deleted');
+        END;",
+    )
+}
+
+fn clock_drift(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch("ALTER TABLE print_jobs ADD COLUMN clock_drift_secs INTEGER;")
+}
+
+fn printer_feeds(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE printers ADD COLUMN line_spacing INTEGER;
+         ALTER TABLE printers ADD COLUMN feed_lines_before_cut INTEGER;
+         ALTER TABLE printers ADD COLUMN feed_lines_after_cut INTEGER NOT NULL DEFAULT 0;",
+    )
+}
+
+fn kot_printed_items(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS kot_printed_items (
+            order_id INTEGER NOT NULL,
+            item_type TEXT NOT NULL,
+            name TEXT NOT NULL,
+            quantity INTEGER NOT NULL,
+            PRIMARY KEY (order_id, item_type, name)
+        );",
+    )
+}
+
+fn printer_init_sequence(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch("ALTER TABLE printers ADD COLUMN init_sequence BLOB NOT NULL DEFAULT x'';")
+}
+
+fn printer_secrets(conn: &Connection) -> rusqlite::Result<()> {
+    secrets::move_out_of_settings(conn)
+}
+
+fn archive_sources(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch("ALTER TABLE print_jobs ADD COLUMN source BLOB NOT NULL DEFAULT x'';")
+}
+
+fn fiscal_prints(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE fiscal_prints (
+            order_id INTEGER PRIMARY KEY,
+            prints INTEGER NOT NULL
+        );
+        INSERT INTO fiscal_prints (order_id, prints) SELECT order_id, copies + 1 FROM fiscal_bills;",
+    )
+}
+
+// Kept in our own table rather than PRAGMA user_version, which belongs to the app's database.
+fn create_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS printer_migrations (
+            version INTEGER PRIMARY KEY,
+            applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );",
+    )
+}
+
+pub(crate) fn schema_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM printer_migrations", [], |row| row.get(0))
+}
+
+// Brings the schema up to date, one transaction per migration so a failure leaves the database
+// at the last version that completed.
+pub(crate) fn run(conn: &Connection) -> Result<u32, PrintError> {
+    create_table(conn)?;
+    let current = schema_version(conn)? as usize;
+    if current > MIGRATIONS.len() {
+        return Err(PrintError::new(
+            ErrorCode::Database,
+            format!("Printer schema version {} is newer than this app supports ({})", current, MIGRATIONS.len()),
+        ));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+        let version = index as u32 + 1;
+        database::write_transaction(conn, |conn| {
+            migration(conn)?;
+            conn.execute("INSERT INTO printer_migrations (version) VALUES (?1)", params![version])?;
+            Ok(())
+        })?;
+        info!("Printer schema migrated to version {}", version);
+    }
+    Ok(MIGRATIONS.len() as u32)
+}
+
+#[tauri::command]
+pub fn get_schema_version(state: tauri::State<'_, DbState>) -> Result<u32, PrintError> {
+    let conn = database::connection(&state)?;
+    Ok(schema_version(&conn)?)
+}
@@ -0,0 +1,230 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use crate::db::DbState;
+
+use super::alerts;
+use super::config;
+use super::database;
+use super::error::{ErrorCode, PrintError};
+use super::profile::{self, PaperStatus, PrinterProfile};
+use super::queue;
+use super::status::{self, PrinterStatus};
+use super::webhooks;
+
+pub const STATUS_EVENT: &str = "printer://status";
+pub const DRAWER_EVENT: &str = "printer://drawer";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusTransition {
+    Online,
+    Offline,
+    CoverOpened,
+    CoverClosed,
+    PaperNearEnd,
+    PaperOut,
+    PaperRestored,
+    Error,
+    ErrorCleared,
+    DrawerOpened,
+    DrawerClosed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusEvent {
+    pub printer_id: i64,
+    pub name: String,
+    // `None` when the printer didn't answer at all
+    pub status: Option<PrinterStatus>,
+    pub transitions: Vec<StatusTransition>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DrawerEvent {
+    pub printer_id: i64,
+    pub name: String,
+    pub open: bool,
+}
+
+fn last_statuses() -> &'static Mutex<HashMap<i64, Option<PrinterStatus>>> {
+    static STATUSES: OnceLock<Mutex<HashMap<i64, Option<PrinterStatus>>>> = OnceLock::new();
+    STATUSES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn monitor_task() -> &'static Mutex<Option<JoinHandle<()>>> {
+    static TASK: OnceLock<Mutex<Option<JoinHandle<()>>>> = OnceLock::new();
+    TASK.get_or_init(|| Mutex::new(None))
+}
+
+pub(crate) fn last_known_status(printer_id: i64) -> Option<Option<PrinterStatus>> {
+    last_statuses().lock().unwrap_or_else(|e| e.into_inner()).get(&printer_id).cloned()
+}
+
+fn transitions(previous: &Option<PrinterStatus>, current: &Option<PrinterStatus>) -> Vec<StatusTransition> {
+    let mut changes = Vec::new();
+    let was_online = previous.as_ref().map_or(false, |s| s.online);
+    let is_online = current.as_ref().map_or(false, |s| s.online);
+    if !was_online && is_online {
+        changes.push(StatusTransition::Online);
+    } else if was_online && !is_online {
+        changes.push(StatusTransition::Offline);
+    }
+
+    // An unreachable printer tells us nothing about paper or cover
+    let (Some(previous), Some(current)) = (previous, current) else {
+        return changes;
+    };
+
+    if current.cover_open != previous.cover_open {
+        changes.push(if current.cover_open { StatusTransition::CoverOpened } else { StatusTransition::CoverClosed });
+    }
+
+    if current.paper_out && !previous.paper_out {
+        changes.push(StatusTransition::PaperOut);
+    } else if current.paper_near_end && !previous.paper_near_end && !current.paper_out {
+        changes.push(StatusTransition::PaperNearEnd);
+    } else if (previous.paper_out || previous.paper_near_end) && !(current.paper_out || current.paper_near_end) {
+        changes.push(StatusTransition::PaperRestored);
+    }
+
+    if current.drawer_open != previous.drawer_open {
+        changes.push(if current.drawer_open { StatusTransition::DrawerOpened } else { StatusTransition::DrawerClosed });
+    }
+
+    let had_error = previous.cutter_error || previous.unrecoverable_error;
+    let has_error = current.cutter_error || current.unrecoverable_error;
+    if has_error && !had_error {
+        changes.push(StatusTransition::Error);
+    } else if had_error && !has_error {
+        changes.push(StatusTransition::ErrorCleared);
+    }
+
+    changes
+}
+
+async fn poll_printer<R: Runtime>(app: &AppHandle<R>, printer: &PrinterProfile) {
+    let Some(printer_id) = printer.id else {
+        return;
+    };
+    let current = match status::query_profile_status(printer).await {
+        Ok(status) => Some(status),
+        Err(e) => {
+            debug!("Status poll for {} failed: {}", printer.name, e);
+            None
+        }
+    };
+
+    // First sighting is compared against a healthy printer so only problems are reported
+    let previous = last_known_status(printer_id).unwrap_or(Some(PrinterStatus { online: true, ..Default::default() }));
+    let changes = transitions(&previous, &current);
+    last_statuses().lock().unwrap_or_else(|e| e.into_inner()).insert(printer_id, current.clone());
+
+    let paper_change = changes.iter().find_map(|change| match change {
+        StatusTransition::PaperOut => Some(PaperStatus::Out),
+        StatusTransition::PaperNearEnd => Some(PaperStatus::NearEnd),
+        StatusTransition::PaperRestored => Some(PaperStatus::Ok),
+        _ => None,
+    });
+    if let Some(paper_status) = paper_change {
+        alerts::record_paper_status(app, &app.state::<DbState>(), &printer.destination(), paper_status).await;
+    }
+
+    let drawer_change = changes.iter().find_map(|change| match change {
+        StatusTransition::DrawerOpened => Some(true),
+        StatusTransition::DrawerClosed => Some(false),
+        _ => None,
+    });
+    if let Some(open) = drawer_change {
+        let event = DrawerEvent { printer_id, name: printer.name.clone(), open };
+        if let Err(e) = app.emit(DRAWER_EVENT, event) {
+            error!("Failed to emit drawer event: {}", e);
+        }
+    }
+
+    // Drained off the poll loop so a long backlog doesn't hold up the other printers' polls
+    if changes.contains(&StatusTransition::Online) {
+        let (app, printer) = (app.clone(), printer.clone());
+        tauri::async_runtime::spawn(async move {
+            queue::resume(&app, &printer).await;
+        });
+    }
+
+    if changes.contains(&StatusTransition::Offline) {
+        webhooks::printer_offline(&printer.name);
+    }
+
+    if !changes.is_empty() {
+        info!("Printer {} status changed: {:?}", printer.name, changes);
+        let event = StatusEvent { printer_id, name: printer.name.clone(), status: current, transitions: changes };
+        if let Err(e) = app.emit(STATUS_EVENT, event) {
+            error!("Failed to emit printer status event: {}", e);
+        }
+    }
+}
+
+async fn poll_once<R: Runtime>(app: &AppHandle<R>) {
+    let state = app.state::<DbState>();
+    let printers = match database::run(&state, |conn| Ok(profile::list(conn)?)).await {
+        Ok(printers) => printers,
+        Err(e) => {
+            error!("Status poll could not load printers: {}", e);
+            return;
+        }
+    };
+
+    for printer in printers.iter().filter(|p| status::profile_supports_status(p)) {
+        poll_printer(app, printer).await;
+    }
+}
+
+fn spawn_monitor<R: Runtime>(app: AppHandle<R>, interval: Duration) {
+    let mut task = monitor_task().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(previous) = task.take() {
+        previous.abort();
+    }
+    *task = Some(tauri::async_runtime::spawn(async move {
+        loop {
+            poll_once(&app).await;
+            tokio::time::sleep(interval).await;
+        }
+    }));
+}
+
+fn stop_monitor() {
+    if let Some(task) = monitor_task().lock().unwrap_or_else(|e| e.into_inner()).take() {
+        task.abort();
+    }
+}
+
+// For host setup: starts polling if an interval was saved in the settings.
+pub fn resume_status_monitor<R: Runtime>(app: &AppHandle<R>) {
+    let interval = config::current().status_poll_interval_secs;
+    if interval > 0 {
+        spawn_monitor(app.clone(), Duration::from_secs(interval));
+    }
+}
+
+#[tauri::command]
+pub fn start_status_monitor<R: Runtime>(
+    app: AppHandle<R>,
+    interval_secs: u64,
+    state: tauri::State<'_, DbState>,
+) -> Result<(), PrintError> {
+    if interval_secs == 0 {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "Poll interval must be at least one second"));
+    }
+    config::update_persisted(&state, |config| config.status_poll_interval_secs = interval_secs)?;
+    spawn_monitor(app, Duration::from_secs(interval_secs));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_status_monitor(state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    stop_monitor();
+    config::update_persisted(&state, |config| config.status_poll_interval_secs = 0)
+}
@@ -0,0 +1,273 @@
+// Minimal PDF writer for receipts and invoices: the built-in Helvetica faces, rules, shaded boxes
+// and one JPEG image. Coordinates are points measured from the top-left corner of the page.
+
+use std::fmt::Write;
+
+pub const A4: (f32, f32) = (595.0, 842.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weight {
+    Regular,
+    Bold,
+}
+
+impl Weight {
+    fn font(&self) -> &'static str {
+        match self {
+            Weight::Regular => "F1",
+            Weight::Bold => "F2",
+        }
+    }
+}
+
+// JPEG data is embedded as-is (DCTDecode); only the frame header is read for its size.
+#[derive(Debug, Clone)]
+pub struct Jpeg {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    components: u8,
+}
+
+impl Jpeg {
+    pub fn parse(data: Vec<u8>) -> Result<Self, String> {
+        if !data.starts_with(&[0xFF, 0xD8]) {
+            return Err("not a JPEG file".to_string());
+        }
+        let mut i = 2;
+        while i + 9 < data.len() {
+            if data[i] != 0xFF {
+                return Err("corrupt JPEG marker".to_string());
+            }
+            let marker = data[i + 1];
+            let length = u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+            // SOF0-SOF15 except DHT (C4), JPG (C8) and DAC (CC) carry the frame size
+            if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                let height = u16::from_be_bytes([data[i + 5], data[i + 6]]) as u32;
+                let width = u16::from_be_bytes([data[i + 7], data[i + 8]]) as u32;
+                let components = data[i + 9];
+                return Ok(Jpeg { data, width, height, components });
+            }
+            i += 2 + length;
+        }
+        Err("JPEG has no frame header".to_string())
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+
+    fn color_space(&self) -> &'static str {
+        match self.components {
+            1 => "DeviceGray",
+            4 => "DeviceCMYK",
+            _ => "DeviceRGB",
+        }
+    }
+}
+
+// Helvetica advance widths in 1/1000 em for the characters receipts are mostly made of;
+// everything else is treated as a digit-width glyph, which is close enough for alignment.
+fn glyph_width(c: char, weight: Weight) -> u32 {
+    match (c, weight) {
+        (' ' | '.' | ',' | ':' | ';' | '!' | 'i' | 'j' | 'l' | '|', Weight::Regular) => 278,
+        (' ' | '.' | ',' | 'i' | 'j' | 'l', Weight::Bold) => 278,
+        (':' | ';' | '!' | 't' | 'f' | '|', Weight::Bold) => 333,
+        ('-' | '(' | ')' | 'r' | 't' | 'f', _) => 333,
+        ('m' | 'M', _) => 833,
+        ('w' | 'W', _) => 722,
+        ('%', _) => 889,
+        (c, _) if c.is_ascii_uppercase() => 667,
+        _ => 556,
+    }
+}
+
+pub fn text_width(text: &str, size: f32, weight: Weight) -> f32 {
+    text.chars().map(|c| glyph_width(c, weight)).sum::<u32>() as f32 * size / 1000.0
+}
+
+// WinAnsi string literal; characters outside Latin-1 print as '?'.
+fn encode(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len() + 2);
+    bytes.push(b'(');
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => bytes.extend_from_slice(&[b'\\', c as u8]),
+            c if (c as u32) < 256 => bytes.push(c as u8),
+            _ => bytes.push(b'?'),
+        }
+    }
+    bytes.push(b')');
+    bytes
+}
+
+// Drawing surface shared by the PDF writer and GDI pages, so a layout is written once and
+// either saved or sent to a Windows printer. Same coordinates as PdfWriter.
+pub trait Canvas {
+    fn width(&self) -> f32;
+    fn height(&self) -> f32;
+    fn text(&mut self, x: f32, y: f32, size: f32, weight: Weight, text: &str);
+    fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32);
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, gray: f32);
+    fn new_page(&mut self);
+
+    // Surfaces that can't embed JPEGs leave the space blank.
+    fn image(&mut self, _jpeg: &Jpeg, _x: f32, _y: f32, _width: f32, _height: f32) {}
+
+    fn text_right(&mut self, right: f32, y: f32, size: f32, weight: Weight, text: &str) {
+        self.text(right - text_width(text, size, weight), y, size, weight, text);
+    }
+
+    fn text_center(&mut self, center: f32, y: f32, size: f32, weight: Weight, text: &str) {
+        self.text(center - text_width(text, size, weight) / 2.0, y, size, weight, text);
+    }
+}
+
+pub struct PdfWriter {
+    width: f32,
+    height: f32,
+    pages: Vec<Vec<u8>>,
+    current: Vec<u8>,
+    image: Option<Jpeg>,
+}
+
+impl PdfWriter {
+    pub fn new((width, height): (f32, f32)) -> Self {
+        PdfWriter { width, height, pages: Vec::new(), current: Vec::new(), image: None }
+    }
+
+    fn op(&mut self, operator: &str) -> &mut Self {
+        self.current.extend_from_slice(operator.as_bytes());
+        self.current.push(b'\n');
+        self
+    }
+
+    // `y` is the text baseline.
+    pub fn text(&mut self, x: f32, y: f32, size: f32, weight: Weight, text: &str) -> &mut Self {
+        let start = format!("BT /{} {:.1} Tf {:.2} {:.2} Td ", weight.font(), size, x, self.height - y);
+        self.current.extend_from_slice(start.as_bytes());
+        self.current.extend(encode(text));
+        self.op(" Tj ET")
+    }
+
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32) -> &mut Self {
+        let (y1, y2) = (self.height - y1, self.height - y2);
+        self.op(&format!("{:.2} w {:.2} {:.2} m {:.2} {:.2} l S", thickness, x1, y1, x2, y2))
+    }
+
+    // `gray` runs from 0 (black) to 1 (white).
+    pub fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, gray: f32) -> &mut Self {
+        let bottom = self.height - y - height;
+        self.op(&format!("q {:.2} g {:.2} {:.2} {:.2} {:.2} re f Q", gray, x, bottom, width, height))
+    }
+
+    // Only one image per document; later calls redraw it at a new spot.
+    pub fn image(&mut self, jpeg: &Jpeg, x: f32, y: f32, width: f32, height: f32) -> &mut Self {
+        if self.image.is_none() {
+            self.image = Some(jpeg.clone());
+        }
+        let bottom = self.height - y - height;
+        self.op(&format!("q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im1 Do Q", width, height, x, bottom))
+    }
+
+    pub fn new_page(&mut self) -> &mut Self {
+        let page = std::mem::take(&mut self.current);
+        self.pages.push(page);
+        self
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        if !self.current.is_empty() || self.pages.is_empty() {
+            self.new_page();
+        }
+
+        // 1 catalog, 2 page tree, 3-4 fonts, 5 the image if any, then a page and its contents per page
+        let first_page = if self.image.is_some() { 6 } else { 5 };
+        let kids: Vec<String> = (0..self.pages.len()).map(|i| format!("{} 0 R", first_page + 2 * i)).collect();
+        let mut objects: Vec<Vec<u8>> = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), self.pages.len()).into_bytes(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_vec(),
+        ];
+        let mut resources = "<< /Font << /F1 3 0 R /F2 4 0 R >>".to_string();
+        if let Some(image) = &self.image {
+            let mut object = format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{} /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+                image.width,
+                image.height,
+                image.color_space(),
+                image.data.len()
+            )
+            .into_bytes();
+            object.extend_from_slice(&image.data);
+            object.extend_from_slice(b"\nendstream");
+            objects.push(object);
+            resources.push_str(" /XObject << /Im1 5 0 R >>");
+        }
+        resources.push_str(" >>");
+
+        for (i, content) in self.pages.iter().enumerate() {
+            let contents_id = first_page + 2 * i + 1;
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.0} {:.0}] /Resources {} /Contents {} 0 R >>",
+                    self.width, self.height, resources, contents_id
+                )
+                .into_bytes(),
+            );
+            let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+            stream.extend_from_slice(content);
+            stream.extend_from_slice(b"endstream");
+            objects.push(stream);
+        }
+
+        let mut output = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(output.len());
+            output.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            output.extend_from_slice(object);
+            output.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref_offset = output.len();
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(xref, "{:010} 00000 n ", offset);
+        }
+        let _ = writeln!(xref, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF", objects.len() + 1, xref_offset);
+        output.extend_from_slice(xref.as_bytes());
+        output
+    }
+}
+
+impl Canvas for PdfWriter {
+    fn width(&self) -> f32 {
+        self.width
+    }
+
+    fn height(&self) -> f32 {
+        self.height
+    }
+
+    fn text(&mut self, x: f32, y: f32, size: f32, weight: Weight, text: &str) {
+        PdfWriter::text(self, x, y, size, weight, text);
+    }
+
+    fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32) {
+        PdfWriter::line(self, x1, y1, x2, y2, thickness);
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, gray: f32) {
+        PdfWriter::fill_rect(self, x, y, width, height, gray);
+    }
+
+    fn new_page(&mut self) {
+        PdfWriter::new_page(self);
+    }
+
+    fn image(&mut self, jpeg: &Jpeg, x: f32, y: f32, width: f32, height: f32) {
+        PdfWriter::image(self, jpeg, x, y, width, height);
+    }
+}
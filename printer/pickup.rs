@@ -0,0 +1,67 @@
+// The slip handed to a waiting pack customer once the kitchen marks their order done: the token
+// big enough to read across the counter, "ORDER READY" and the time.
+
+use tauri::{AppHandle, Runtime};
+
+use crate::db::DbState;
+
+use super::clock;
+use super::config;
+use super::database;
+use super::error::{ErrorCode, PrintError};
+use super::escpos::{Align, EscPosBuilder};
+use super::letterhead;
+use super::profile;
+use super::{fetch_order_details, send_to_profiles, DocumentType, PrinterPrintResult};
+
+fn build(token: &str, has_table: bool, footer: Option<&str>) -> Vec<u8> {
+    let mut slip = EscPosBuilder::new();
+    slip.align(Align::Center)
+        .bold(true)
+        .size(2, 2)
+        .line("ORDER READY")
+        .size(1, 1)
+        .bold(false)
+        .feed(1)
+        .line("Token")
+        .bold(true)
+        .size(6, 6)
+        .line(token)
+        .size(1, 1)
+        .bold(false)
+        .feed(1)
+        .line(if has_table { "Table" } else { "Pack" })
+        .line(&clock::now().format("%I:%M %p").to_string());
+    if let Some(footer) = footer {
+        slip.feed(1).line(footer);
+    }
+    slip.align(Align::Left).feed(3).cut();
+    slip.build()
+}
+
+// Printed on demand, on the given printers or the default (counter) ones.
+#[tauri::command]
+pub async fn print_pickup_slip<R: Runtime>(
+    app: AppHandle<R>,
+    order_id: i64,
+    printer_ids: Option<Vec<i64>>,
+    state: tauri::State<'_, DbState>,
+) -> Result<Vec<PrinterPrintResult>, PrintError> {
+    let config = config::current();
+    let printer_ids = printer_ids.unwrap_or_else(|| config.default_printer_ids.clone());
+    if printer_ids.is_empty() {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "No printers selected and no default printers saved"));
+    }
+
+    let (profiles, content) = database::run(&state, move |conn| {
+        let profiles = printer_ids.iter().map(|id| profile::get(conn, *id)).collect::<Result<Vec<_>, _>>()?;
+        let order = fetch_order_details(conn, order_id)?;
+        let token = order.order_number.split('-').last().unwrap_or("");
+        Ok((profiles, build(token, order.has_table, letterhead::footer(&config, DocumentType::Pickup))))
+    })
+    .await?;
+
+    let results = send_to_profiles(&app, order_id, profiles, &content, Some(DocumentType::Pickup), None, None, &state).await?;
+    info!("Pickup slip for order {} sent", order_id);
+    Ok(results)
+}
@@ -0,0 +1,223 @@
+// One-line integration for host apps:
+//
+//     tauri::Builder::default().plugin(printer::Builder::new().db_path("pos.db").build())
+//
+// registers every command under `plugin:printer|...`, opens the plugin's log file and the database
+// (unless the app already manages a DbState), runs migrations, resumes the status monitor, starts
+// the metrics endpoint if one is configured and resends jobs parked when the app last closed. On
+// exit, jobs still in flight are given a grace period and then parked.
+
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::plugin::TauriPlugin;
+use tauri::{Manager, RunEvent, Runtime};
+
+use crate::db::DbState;
+
+use super::driver::{self, PrinterDriver};
+use super::{clock, config, database, exporter, init_printer_tables, logging, monitor, profile, queue, shutdown, DEFAULT_TIMEOUT};
+
+const DEFAULT_DB_FILE: &str = "printer.db";
+// Under the app data dir, next to the database by default
+const LOG_DIR: &str = "printer-logs";
+
+// `plugins.printer` in tauri.conf.json. Anything set here wins over the Builder, so a deployment
+// can be reconfigured without a rebuild; simulation mode is only the starting value and settings
+// saved at runtime take over once they exist.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginConfig {
+    db_path: Option<PathBuf>,
+    default_paper_width: Option<u32>,
+    // error, warn, info, debug or trace
+    log_level: Option<String>,
+    simulation_mode: Option<bool>,
+}
+
+#[derive(Default)]
+pub struct Builder {
+    default_timeout: Option<Duration>,
+    db_path: Option<PathBuf>,
+    terminal_id: Option<String>,
+    drivers: Vec<(String, Arc<dyn PrinterDriver>)>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Connect/write timeout for printers whose profile doesn't set one.
+    pub fn default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    // Defaults to printer.db in the app data directory.
+    pub fn db_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.db_path = Some(path.into());
+        self
+    }
+
+    // Defaults to the machine name; see config::terminal_id.
+    pub fn terminal_id(mut self, id: impl Into<String>) -> Self {
+        self.terminal_id = Some(id.into());
+        self
+    }
+
+    // Makes `Destination::Driver { driver: name, .. }` and printers with that driver usable.
+    pub fn driver(mut self, name: impl Into<String>, driver: impl PrinterDriver) -> Self {
+        self.drivers.push((name.into(), Arc::new(driver)));
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R, Option<PluginConfig>> {
+        tauri::plugin::Builder::<R, Option<PluginConfig>>::new("printer")
+            .invoke_handler(tauri::generate_handler![
+                super::print_to_printers,
+                super::print_bytes_to_printers,
+                super::print_raw,
+                super::print_to_all_printers,
+                super::print_bytes_to_all_printers,
+                super::print_kot_to_stations,
+                super::generate_kot_content_from_db,
+                super::generate_station_kots,
+                super::print_pack_labels,
+                super::addons::mark_kot_printed,
+                super::archive::list_archived_jobs,
+                super::archive::get_archived_payload,
+                super::archive::reprint_job,
+                super::batch::print_orders,
+                super::clock::get_clock_check,
+                super::consolidated::generate_consolidated_kot,
+                super::consolidated::print_consolidated_kot,
+                super::clock::set_timezone,
+                super::config::get_terminal_id,
+                super::config::list_terminals,
+                super::config::get_printer_settings,
+                super::config::save_printer_settings,
+                super::config::set_printer_language,
+                super::config::set_document_copies,
+                super::config::set_kot_copy_templates,
+                super::config::set_fallback_printer,
+                super::config::set_station_routes,
+                super::config::set_expo_printer,
+                super::config::set_webhooks,
+                super::config::set_idempotency_window,
+                super::config::set_simulation_mode,
+                super::database::backup_database,
+                super::database::restore_database,
+                super::diagnostics::export_diagnostics,
+                super::display::display_item,
+                super::display::display_thank_you,
+                super::display::clear_display,
+                super::drawer::open_cash_drawer,
+                super::drawer::set_drawer_assignment,
+                super::driver::list_printer_drivers,
+                super::email::email_receipt,
+                super::email::list_receipt_emails,
+                super::fiscal::get_fiscal_bill,
+                super::health::health,
+                super::invoice::generate_invoice_pdf,
+                super::invoice::print_invoice,
+                super::letterhead::get_receipt_header,
+                super::letterhead::set_receipt_header,
+                super::letterhead::set_document_footer,
+                super::logging::get_recent_logs,
+                super::metrics::get_printer_metrics,
+                super::migrations::get_schema_version,
+                super::monitor::start_status_monitor,
+                super::monitor::stop_status_monitor,
+                super::pickup::print_pickup_slip,
+                super::preview::preview_content,
+                super::preview::preview_png,
+                super::probe::probe_printer,
+                super::probe::detect_baud_rate,
+                super::probe::identify_printer,
+                super::profile::list_printers,
+                super::profile::save_printer,
+                super::profile::delete_printer,
+                super::queue::list_print_queue,
+                super::queue::flush_print_queue,
+                super::receipt::generate_receipt_pdf,
+                super::recovery::get_unprinted_orders,
+                super::sequences::next_bill_number,
+                super::sequences::get_bill_number,
+                super::snippets::list_command_snippets,
+                super::snippets::set_command_snippet,
+                super::spooler::purge_printer_queue,
+                super::spooler::pause_printer_queue,
+                super::spooler::resume_printer_queue,
+                super::spooler::get_default_printer,
+                super::spooler::set_default_printer,
+                super::status::get_printer_status,
+                super::status::get_drawer_status,
+                super::test_page::print_calibration_page,
+                super::test_page::print_test_page,
+                super::validation::validate_printer_settings,
+                super::vendor::get_vendor_commands,
+                super::vendor::set_vendor_override,
+            ])
+            .setup(move |app, api| {
+                let settings = api.config().as_ref();
+                // Applies to the plugin's own records only; the host's logger keeps its level
+                let mut level = log::LevelFilter::Info;
+                if let Some(configured) = settings.and_then(|s| s.log_level.as_deref()) {
+                    match log::LevelFilter::from_str(configured) {
+                        Ok(configured) => level = configured,
+                        Err(_) => warn!("Ignoring unknown printer log level {}", configured),
+                    }
+                }
+                logging::init(app.path().app_data_dir()?.join(LOG_DIR), level);
+                if let Some(width) = settings.and_then(|s| s.default_paper_width) {
+                    profile::set_default_paper_width(width);
+                }
+                if let Some(enabled) = settings.and_then(|s| s.simulation_mode) {
+                    config::update(|config| config.simulation_mode = enabled);
+                }
+                if let Some(timeout) = self.default_timeout {
+                    let _ = DEFAULT_TIMEOUT.set(timeout);
+                }
+                if let Some(id) = self.terminal_id {
+                    config::set_terminal_id(id);
+                }
+                for (name, driver) in self.drivers {
+                    driver::register(name, driver);
+                }
+                if app.try_state::<DbState>().is_none() {
+                    let path = match settings.and_then(|s| s.db_path.clone()).or(self.db_path) {
+                        Some(path) => path,
+                        None => app.path().app_data_dir()?.join(DEFAULT_DB_FILE),
+                    };
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    app.manage(DbState(database::open_pool(&path)?));
+                }
+                init_printer_tables(&database::connection(&app.state::<DbState>())?)?;
+                monitor::resume_status_monitor(app);
+                exporter::start(app);
+                clock::check_on_startup(app);
+
+                let handle = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    queue::resend_parked(&handle.state::<DbState>()).await;
+                });
+                Ok(())
+            })
+            .on_event(|app, event| {
+                if let RunEvent::Exit = event {
+                    shutdown::run(app);
+                }
+            })
+            .build()
+    }
+}
+
+// The plugin with default settings.
+pub fn init<R: Runtime>() -> TauriPlugin<R, Option<PluginConfig>> {
+    Builder::new().build()
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use super::config;
+use super::error::{ErrorCode, PrintError};
+
+struct IdleConnection {
+    stream: TcpStream,
+    since: Instant,
+}
+
+// At most one idle connection per address; callers hold the destination lock while they use it.
+fn idle_connections() -> &'static Mutex<HashMap<String, IdleConnection>> {
+    static IDLE: OnceLock<Mutex<HashMap<String, IdleConnection>>> = OnceLock::new();
+    IDLE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// A peer that hung up reads as EOF; anything the printer sent unprompted is drained and ignored.
+fn is_healthy(stream: &TcpStream) -> bool {
+    let mut buf = [0u8; 64];
+    loop {
+        match stream.try_read(&mut buf) {
+            Ok(0) => return false,
+            Ok(_) => continue,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return true,
+            Err(_) => return false,
+        }
+    }
+}
+
+async fn connect(address: &str, connect_timeout: Duration) -> Result<TcpStream, PrintError> {
+    match timeout(connect_timeout, TcpStream::connect(address)).await {
+        Ok(Ok(stream)) => Ok(stream),
+        Ok(Err(e)) => Err(PrintError::from_io("Connection failed", &e)),
+        Err(_) => Err(PrintError::new(ErrorCode::Timeout, "Connection timeout")),
+    }
+}
+
+// Returns the connection and whether it was reused, so callers know a failure may just be a stale socket.
+pub(crate) async fn checkout(address: &str, connect_timeout: Duration) -> Result<(TcpStream, bool), PrintError> {
+    let keep_alive = Duration::from_secs(config::current().network_keep_alive_secs);
+    let idle = idle_connections().lock().unwrap_or_else(|e| e.into_inner()).remove(address);
+    if let Some(connection) = idle {
+        if connection.since.elapsed() < keep_alive && is_healthy(&connection.stream) {
+            return Ok((connection.stream, true));
+        }
+        debug!("Dropping stale pooled connection to {}", address);
+    }
+    Ok((connect(address, connect_timeout).await?, false))
+}
+
+pub(crate) fn checkin(address: &str, stream: TcpStream) {
+    if config::current().network_keep_alive_secs == 0 {
+        return;
+    }
+    idle_connections()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(address.to_string(), IdleConnection { stream, since: Instant::now() });
+}
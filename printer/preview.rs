@@ -0,0 +1,172 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rusqlite::Connection;
+use serde::Deserialize;
+
+use crate::db::DbState;
+
+use super::database;
+use super::error::PrintError;
+use super::escpos::{self, Align};
+use super::{build_kot_content, layout, profile, raster, snippets, KotTemplate};
+
+// Either print content as the frontend would send it, or an order whose KOT is generated here.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PreviewSource {
+    OrderId(i64),
+    Content(String),
+}
+
+// Replays ESC/POS onto fixed-width lines: alignment and double width are applied, cuts are
+// drawn as a marker line and anything else that doesn't change the text layout is dropped.
+struct TextRenderer {
+    width: usize,
+    lines: Vec<String>,
+    line: Vec<u8>,
+    align: Align,
+    double_width: bool,
+}
+
+impl TextRenderer {
+    fn new(width: usize) -> Self {
+        TextRenderer { width: width.max(1), lines: Vec::new(), line: Vec::new(), align: Align::Left, double_width: false }
+    }
+
+    // Long lines wrap the way the printer wraps them, each piece aligned on its own.
+    fn flush(&mut self) {
+        let text = String::from_utf8_lossy(&self.line).into_owned();
+        self.line.clear();
+        let chars: Vec<char> = if self.double_width {
+            text.chars().flat_map(|c| [c, ' ']).collect()
+        } else {
+            text.chars().collect()
+        };
+        if chars.is_empty() {
+            self.lines.push(String::new());
+            return;
+        }
+        for piece in chars.chunks(self.width) {
+            let piece: String = piece.iter().collect::<String>().trim_end().to_string();
+            let padding = self.width.saturating_sub(piece.chars().count());
+            let indent = match self.align {
+                Align::Left => 0,
+                Align::Center => padding / 2,
+                Align::Right => padding,
+            };
+            self.lines.push(format!("{}{}", " ".repeat(indent), piece));
+        }
+    }
+
+    fn cut(&mut self) {
+        if !self.line.is_empty() {
+            self.flush();
+        }
+        self.lines.push(format!("{:-^width$}", " cut ", width = self.width));
+    }
+
+    fn command(&mut self, bytes: &[u8]) {
+        match bytes {
+            [0x1B, b'@', ..] => {
+                self.align = Align::Left;
+                self.double_width = false;
+            }
+            [0x1B, b'a', n, ..] => {
+                self.align = match n {
+                    1 | b'1' => Align::Center,
+                    2 | b'2' => Align::Right,
+                    _ => Align::Left,
+                }
+            }
+            [0x1B, b'!', n, ..] => self.double_width = n & 0x20 != 0,
+            [0x1D, b'!', n, ..] => self.double_width = n >> 4 > 0,
+            // ESC d n prints the buffer and feeds n lines
+            [0x1B, b'd', n, ..] => {
+                if *n > 0 || !self.line.is_empty() {
+                    self.flush();
+                }
+                for _ in 1..*n {
+                    self.lines.push(String::new());
+                }
+            }
+            [0x1D, b'V', ..] | [0x1B, b'i' | b'm', ..] => self.cut(),
+            _ => {}
+        }
+    }
+
+    fn render(mut self, content: &[u8]) -> Vec<String> {
+        let mut i = 0;
+        while i < content.len() {
+            match content[i] {
+                b'\n' => {
+                    self.flush();
+                    i += 1;
+                }
+                b'\t' => {
+                    self.line.extend_from_slice(b"    ");
+                    i += 1;
+                }
+                0x1B | 0x1D | 0x1C | 0x10 => {
+                    let length = escpos::command_length(&content[i..]);
+                    self.command(&content[i..i + length]);
+                    i += length;
+                }
+                byte if byte < 0x20 => i += 1,
+                byte => {
+                    self.line.push(byte);
+                    i += 1;
+                }
+            }
+        }
+        if !self.line.is_empty() {
+            self.flush();
+        }
+        self.lines
+    }
+}
+
+pub(crate) fn render_text(content: &[u8], width: usize) -> Vec<String> {
+    TextRenderer::new(width).render(content)
+}
+
+// The bytes that would be sent, snippets expanded, and the paper width in characters.
+fn resolve(conn: &Connection, source: PreviewSource, printer_id: Option<i64>) -> Result<(Vec<u8>, usize), PrintError> {
+    let printer = printer_id.map(|id| profile::get(conn, id)).transpose()?;
+    let width = printer.as_ref().map_or_else(profile::default_paper_width, |p| p.paper_width) as usize;
+    let content = match source {
+        PreviewSource::Content(content) => content,
+        PreviewSource::OrderId(order_id) => build_kot_content(conn, order_id, false, "", None, None, KotTemplate::Standard)?.unwrap_or_default(),
+    };
+    let expanded = snippets::expand(content.as_bytes(), None)?;
+    match printer {
+        Some(printer) => Ok((layout::apply(&expanded, &printer), width)),
+        None => Ok((expanded, width)),
+    }
+}
+
+// The receipt as it will come out of the printer, one string per printed line. Width is the
+// printer's paper width when `printer_id` is given.
+#[tauri::command]
+pub fn preview_content(
+    source: PreviewSource,
+    printer_id: Option<i64>,
+    state: tauri::State<'_, DbState>,
+) -> Result<Vec<String>, PrintError> {
+    let conn = database::connection(&state)?;
+    let (content, width) = resolve(&conn, source, printer_id)?;
+    Ok(render_text(&content, width))
+}
+
+// Same as preview_content but drawn dot for dot, as a base64 PNG.
+#[tauri::command]
+pub fn preview_png(
+    source: PreviewSource,
+    printer_id: Option<i64>,
+    state: tauri::State<'_, DbState>,
+) -> Result<String, PrintError> {
+    let (content, width) = {
+        let conn = database::connection(&state)?;
+        resolve(&conn, source, printer_id)?
+    };
+    Ok(STANDARD.encode(raster::render_png(&content, width)?))
+}
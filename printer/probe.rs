@@ -0,0 +1,258 @@
+use serde::Serialize;
+use std::future::Future;
+use std::time::Instant;
+
+use crate::db::DbState;
+
+use super::clock;
+use super::database;
+use super::driver;
+use super::error::{ErrorCode, PrintError};
+use super::profile::{self, PrinterProfile, Transport};
+use super::status::Reply;
+use super::vendor::VendorProfile;
+use super::{emulator, print_to_destination, spooler, status, validation, Destination, CUT_PAPER, INIT};
+
+// Tried in order; most receipt printers ship at 9600 or 115200
+const COMMON_BAUD_RATES: [u32; 4] = [9600, 19200, 38400, 115200];
+
+// GS I n: transmit printer ID
+const MODEL_ID: u8 = 1;
+const TYPE_ID: u8 = 2;
+const ROM_VERSION: u8 = 3;
+const FIRMWARE: u8 = 65;
+const MAKER: u8 = 66;
+const MODEL_NAME: u8 = 67;
+const SERIAL_NUMBER: u8 = 68;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PrinterIdentity {
+    // Empty when the printer only answers the one-byte IDs, as many clones do
+    pub maker: String,
+    pub model: String,
+    pub firmware: String,
+    pub serial_number: String,
+    pub model_id: Option<u8>,
+    pub autocutter: bool,
+    pub multibyte_characters: bool,
+    pub customer_display: bool,
+    // Answers DLE EOT, so paper and cover status can be polled
+    pub real_time_status: bool,
+    pub vendor_profile: VendorProfile,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeStep {
+    pub name: String,
+    pub ok: bool,
+    pub skipped: bool,
+    pub detail: String,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeReport {
+    pub destination: Destination,
+    pub success: bool,
+    pub steps: Vec<ProbeStep>,
+}
+
+async fn run_step<F>(name: &str, step: F) -> ProbeStep
+where
+    F: Future<Output = Result<String, PrintError>>,
+{
+    let started = Instant::now();
+    let outcome = step.await;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    match outcome {
+        Ok(detail) => ProbeStep { name: name.to_string(), ok: true, skipped: false, detail, elapsed_ms },
+        Err(e) => ProbeStep { name: name.to_string(), ok: false, skipped: false, detail: e.message, elapsed_ms },
+    }
+}
+
+fn skipped(name: &str, reason: &str) -> ProbeStep {
+    ProbeStep { name: name.to_string(), ok: true, skipped: true, detail: reason.to_string(), elapsed_ms: 0 }
+}
+
+async fn connect(destination: &Destination) -> Result<String, PrintError> {
+    match destination {
+        Destination::Network { address } | Destination::Ipp { url: address } | Destination::Lpr { address } => {
+            let issues = validation::check_destination(destination).await;
+            match issues.into_iter().next() {
+                Some(issue) => Err(PrintError::new(issue.code, issue.message)),
+                None => Ok(format!("Connected to {}", address)),
+            }
+        }
+        Destination::Virtual { directory, .. } => {
+            emulator::check_directory(directory)?;
+            Ok(format!("Virtual printer writing to {}", directory))
+        }
+        Destination::Driver { driver, address } => {
+            driver::get(driver)?.check(address)?;
+            Ok(format!("{} accepted {}", driver, address))
+        }
+        Destination::Usb { port, .. } if status::is_serial_port(port) => Ok(format!("{} is a serial port", port)),
+        Destination::Usb { port, .. } => {
+            let printers = spooler::installed_printers()?;
+            if printers.iter().any(|p| p.eq_ignore_ascii_case(port)) {
+                Ok(format!("{} is installed in Windows", port))
+            } else {
+                Err(PrintError::new(ErrorCode::PortNotFound, format!("No installed printer or COM port named {}", port)))
+            }
+        }
+    }
+}
+
+fn gs_i(n: u8) -> [u8; 3] {
+    [0x1D, 0x49, n]
+}
+
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim().to_string()
+}
+
+// By the maker GS I 66 reports. Star and Citizen answer it in ESC/POS emulation too.
+fn vendor_for(maker: &str) -> VendorProfile {
+    let maker = maker.to_ascii_lowercase();
+    if maker.contains("epson") {
+        VendorProfile::Epson
+    } else if maker.contains("star") {
+        VendorProfile::Star
+    } else if maker.contains("xprinter") {
+        VendorProfile::Xprinter
+    } else if maker.contains("rongta") {
+        VendorProfile::Rongta
+    } else {
+        VendorProfile::Generic
+    }
+}
+
+// Each query goes out on its own so a printer that ignores one still answers the rest.
+async fn identify(destination: &Destination) -> Result<PrinterIdentity, PrintError> {
+    let ids = status::exchange(
+        destination,
+        &[(&gs_i(MODEL_ID), Reply::Byte), (&gs_i(TYPE_ID), Reply::Byte), (&gs_i(ROM_VERSION), Reply::Byte)],
+    )
+    .await?;
+    let mut identity = PrinterIdentity {
+        model_id: ids[0].first().copied(),
+        firmware: ids[2].first().map(|rom| format!("ROM {:02X}", rom)).unwrap_or_default(),
+        ..Default::default()
+    };
+    if let Some(&type_id) = ids[1].first() {
+        identity.multibyte_characters = type_id & 0x01 != 0;
+        identity.autocutter = type_id & 0x02 != 0;
+        identity.customer_display = type_id & 0x04 != 0;
+    }
+
+    for (n, field) in [
+        (FIRMWARE, &mut identity.firmware),
+        (MAKER, &mut identity.maker),
+        (MODEL_NAME, &mut identity.model),
+        (SERIAL_NUMBER, &mut identity.serial_number),
+    ] {
+        match status::exchange(destination, &[(&gs_i(n), Reply::Block)]).await {
+            Ok(reply) => *field = text(&reply[0]),
+            Err(e) => debug!("{} did not answer GS I {}: {}", destination.key(), n, e),
+        }
+    }
+    identity.real_time_status = status::query_status_byte(destination, 1).await.is_ok();
+    identity.vendor_profile = vendor_for(&identity.maker);
+    Ok(identity)
+}
+
+// Walks through connect -> status -> test print and stops at the first failure.
+#[tauri::command]
+pub async fn probe_printer(profile: PrinterProfile) -> Result<ProbeReport, PrintError> {
+    let destination = profile.destination();
+    let mut steps = Vec::new();
+
+    let connect_step = run_step("connect", connect(&destination)).await;
+    let connected = connect_step.ok;
+    steps.push(connect_step);
+
+    if connected {
+        if status::supports_status(&destination) {
+            steps.push(run_step("status", async {
+                let byte = status::query_status_byte(&destination, 1).await?;
+                Ok(format!("Printer answered DLE EOT 1 with 0x{:02X}", byte))
+            }).await);
+        } else {
+            steps.push(skipped("status", "Spooled printers don't report status back"));
+        }
+
+        if matches!(destination, Destination::Network { .. }) || status::is_serial_destination(&destination) {
+            steps.push(run_step("identify", async {
+                let identity = identify(&destination).await?;
+                Ok(format!("{} {} firmware {}", identity.maker, identity.model, identity.firmware).trim().to_string())
+            }).await);
+        } else {
+            steps.push(skipped("identify", "Only raw network and serial printers report their model"));
+        }
+
+        let test_line = format!(
+            "{}Printer probe OK - {}\n{}\n\n\n{}",
+            INIT,
+            profile.name,
+            clock::now().format("%Y-%m-%d %I:%M:%S %p"),
+            CUT_PAPER
+        );
+        steps.push(run_step("print", async {
+            print_to_destination(test_line.as_bytes(), &destination, profile.print_timeout(), None).await?;
+            Ok("Test line sent".to_string())
+        }).await);
+    }
+
+    let success = steps.iter().all(|s| s.ok);
+    Ok(ProbeReport { destination, success, steps })
+}
+
+// Finds the rate a serial printer answers DLE EOT at and saves it into the profile.
+#[tauri::command]
+pub async fn detect_baud_rate(printer_id: i64, state: tauri::State<'_, DbState>) -> Result<u32, PrintError> {
+    let printer = database::run(&state, move |conn| profile::get(conn, printer_id)).await?;
+    if printer.transport != Transport::Usb || !status::is_serial_port(&printer.address) {
+        return Err(PrintError::new(
+            ErrorCode::InvalidSettings,
+            format!("{} is not a serial printer; only COM ports have a baud rate", printer.name),
+        ));
+    }
+
+    for baud_rate in COMMON_BAUD_RATES {
+        let destination = Destination::Usb {
+            port: printer.address.clone(),
+            baud_rate,
+            serial: printer.serial_settings(),
+            printer_type: printer.printer_type,
+        };
+        match status::query_status_byte(&destination, 1).await {
+            Ok(_) => {
+                info!("{} answered at {} baud", printer.name, baud_rate);
+                database::run(&state, move |conn| Ok(profile::set_baud_rate(conn, printer_id, baud_rate)?)).await?;
+                return Ok(baud_rate);
+            }
+            Err(e) => debug!("{} did not answer at {} baud: {}", printer.name, baud_rate, e),
+        }
+    }
+
+    Err(PrintError::new(
+        ErrorCode::Timeout,
+        format!("{} did not answer at any of {:?} baud", printer.name, COMMON_BAUD_RATES),
+    ))
+}
+
+// Asks the printer what it is and switches its vendor profile to match, unless the user already
+// picked one.
+#[tauri::command]
+pub async fn identify_printer(printer_id: i64, state: tauri::State<'_, DbState>) -> Result<PrinterIdentity, PrintError> {
+    let printer = database::run(&state, move |conn| profile::get(conn, printer_id)).await?;
+    let identity = identify(&printer.destination()).await?;
+    info!("{} identifies as {} {} ({})", printer.name, identity.maker, identity.model, identity.firmware);
+
+    if printer.vendor_profile == VendorProfile::Generic && identity.vendor_profile != VendorProfile::Generic {
+        let vendor = identity.vendor_profile;
+        database::run(&state, move |conn| Ok(profile::set_vendor_profile(conn, printer_id, vendor)?)).await?;
+        info!("{} switched to the {} vendor profile", printer.name, vendor.as_str());
+    }
+    Ok(identity)
+}
@@ -0,0 +1,521 @@
+use rusqlite::types::ToSqlOutput;
+use rusqlite::{params, Connection, OptionalExtension, Row, ToSql};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+use crate::db::DbState;
+
+use super::config;
+use super::database;
+use super::error::{ErrorCode, PrintError};
+use super::gdi::PrinterType;
+use super::serial::{self, FlowControl, Parity, SerialSettings};
+use super::vendor::VendorProfile;
+use super::{default_timeout, Destination};
+
+const DEFAULT_PAPER_WIDTH: u32 = 48;
+// Setup commands are a few bytes each; anything longer is a document pasted in the wrong field
+const MAX_INIT_SEQUENCE: usize = 64;
+// Printers at the terminal's outlet, bound as ?1, and those shared by every outlet
+const AT_THIS_OUTLET: &str = "(outlet_id IS NULL OR ?1 IS NULL OR outlet_id = ?1)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    Usb,
+    Network,
+    Ipp,
+    Lpr,
+    Virtual,
+    // Registered by the host app; `driver` names which one
+    Driver,
+}
+
+impl Transport {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Transport::Usb => "usb",
+            Transport::Network => "network",
+            Transport::Ipp => "ipp",
+            Transport::Lpr => "lpr",
+            Transport::Virtual => "virtual",
+            Transport::Driver => "driver",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "usb" => Some(Transport::Usb),
+            "network" => Some(Transport::Network),
+            "ipp" => Some(Transport::Ipp),
+            "lpr" => Some(Transport::Lpr),
+            "virtual" => Some(Transport::Virtual),
+            "driver" => Some(Transport::Driver),
+            _ => None,
+        }
+    }
+}
+
+impl ToSql for Transport {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaperStatus {
+    #[default]
+    Ok,
+    NearEnd,
+    Out,
+}
+
+impl PaperStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PaperStatus::Ok => "ok",
+            PaperStatus::NearEnd => "near_end",
+            PaperStatus::Out => "out",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "near_end" => PaperStatus::NearEnd,
+            "out" => PaperStatus::Out,
+            _ => PaperStatus::Ok,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrinterProfile {
+    // `None` until the profile has been saved
+    pub id: Option<i64>,
+    pub name: String,
+    pub transport: Transport,
+    // Windows printer name / COM port for USB, host:port for network, printer URL for IPP, host/queue for LPR
+    pub address: String,
+    // Driver transport only: the name it was registered under
+    #[serde(default)]
+    pub driver: Option<String>,
+    #[serde(default)]
+    pub baud_rate: u32,
+    // Printable characters per line (32 for 58mm, 48 for 80mm)
+    #[serde(default = "default_paper_width")]
+    pub paper_width: u32,
+    // ESC t code page number
+    #[serde(default)]
+    pub code_page: u8,
+    #[serde(default)]
+    pub vendor_profile: VendorProfile,
+    #[serde(default)]
+    pub flow_control: FlowControl,
+    #[serde(default = "default_data_bits")]
+    pub data_bits: u8,
+    #[serde(default)]
+    pub parity: Parity,
+    #[serde(default = "default_stop_bits")]
+    pub stop_bits: u8,
+    // Serial write pacing, see SerialSettings
+    #[serde(default)]
+    pub chunk_size: u32,
+    #[serde(default)]
+    pub chunk_delay_ms: u32,
+    // USB (spooled) printers only: office printers get plain text instead of ESC/POS
+    #[serde(default)]
+    pub printer_type: PrinterType,
+    // Connect/write timeout for this printer; 0 uses the plugin default
+    #[serde(default)]
+    pub timeout_secs: u32,
+    // ESC 3 line spacing in dots; None keeps the printer's own (usually 30)
+    #[serde(default)]
+    pub line_spacing: Option<u8>,
+    // Lines fed before each cut in place of the document's own blank lines (None leaves them), and
+    // after it; see layout.rs
+    #[serde(default)]
+    pub feed_lines_before_cut: Option<u8>,
+    #[serde(default)]
+    pub feed_lines_after_cut: u8,
+    // Sent after every ESC @ (code page, density, Chinese mode off...); see layout.rs
+    #[serde(default)]
+    pub init_sequence: Vec<u8>,
+    // Network printers only: poll status over SNMP with this community instead of DLE EOT
+    #[serde(default)]
+    pub snmp_community: Option<String>,
+    // Outlet the printer is installed at; `None` makes it available to every outlet
+    #[serde(default)]
+    pub outlet_id: Option<String>,
+    // Last paper state seen from a failed job or the status monitor; not editable
+    #[serde(default)]
+    pub paper_status: PaperStatus,
+    #[serde(default)]
+    pub paper_status_at: Option<String>,
+}
+
+// tauri.conf.json can change the width new printers start with
+static PAPER_WIDTH_OVERRIDE: OnceLock<u32> = OnceLock::new();
+
+pub(crate) fn set_default_paper_width(width: u32) {
+    let _ = PAPER_WIDTH_OVERRIDE.set(width.max(1));
+}
+
+pub(crate) fn default_paper_width() -> u32 {
+    PAPER_WIDTH_OVERRIDE.get().copied().unwrap_or(DEFAULT_PAPER_WIDTH)
+}
+
+// Init sequences by destination key, so the transport can send them without a database lookup.
+// Reloaded whenever a printer is saved, deleted or changes vendor.
+fn init_sequences() -> &'static RwLock<HashMap<String, (VendorProfile, Vec<u8>)>> {
+    static INIT_SEQUENCES: OnceLock<RwLock<HashMap<String, (VendorProfile, Vec<u8>)>>> = OnceLock::new();
+    INIT_SEQUENCES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+// This outlet's printers come last, so they win over a shared printer at the same address.
+pub(crate) fn load_init_sequences(conn: &Connection) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT * FROM printers WHERE length(init_sequence) > 0 AND {} ORDER BY outlet_id IS NOT NULL",
+        AT_THIS_OUTLET
+    ))?;
+    let loaded = stmt
+        .query_map(params![config::current().outlet_id], PrinterProfile::from_row)?
+        .map(|profile| profile.map(|p| (p.destination().key(), (p.vendor_profile, p.init_sequence))))
+        .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+    *init_sequences().write().unwrap_or_else(|e| e.into_inner()) = loaded;
+    Ok(())
+}
+
+pub(crate) fn init_sequence(destination: &Destination) -> Option<(VendorProfile, Vec<u8>)> {
+    init_sequences().read().unwrap_or_else(|e| e.into_inner()).get(&destination.key()).cloned()
+}
+
+fn default_data_bits() -> u8 {
+    SerialSettings::default().data_bits
+}
+
+fn default_stop_bits() -> u8 {
+    SerialSettings::default().stop_bits
+}
+
+impl PrinterProfile {
+    pub fn destination(&self) -> Destination {
+        match self.transport {
+            Transport::Usb => Destination::Usb {
+                port: self.address.clone(),
+                baud_rate: self.baud_rate,
+                serial: self.serial_settings(),
+                printer_type: self.printer_type,
+            },
+            Transport::Network => Destination::Network { address: self.address.clone() },
+            Transport::Ipp => Destination::Ipp { url: self.address.clone() },
+            Transport::Lpr => Destination::Lpr { address: self.address.clone() },
+            Transport::Virtual => Destination::Virtual { directory: self.address.clone(), columns: self.paper_width },
+            Transport::Driver => Destination::Driver {
+                driver: self.driver.clone().unwrap_or_default(),
+                address: self.address.clone(),
+            },
+        }
+    }
+
+    pub fn print_timeout(&self) -> Duration {
+        match self.timeout_secs {
+            0 => default_timeout(),
+            secs => Duration::from_secs(secs as u64),
+        }
+    }
+
+    pub fn serial_settings(&self) -> SerialSettings {
+        SerialSettings {
+            flow_control: self.flow_control,
+            data_bits: self.data_bits,
+            parity: self.parity,
+            stop_bits: self.stop_bits,
+            chunk_size: self.chunk_size as usize,
+            chunk_delay_ms: self.chunk_delay_ms as u64,
+        }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let transport: String = row.get("transport")?;
+        let vendor_profile: String = row.get("vendor_profile")?;
+        let flow_control: String = row.get("flow_control")?;
+        let parity: String = row.get("parity")?;
+        let paper_status: String = row.get("paper_status")?;
+        let printer_type: String = row.get("printer_type")?;
+        Ok(PrinterProfile {
+            id: Some(row.get("id")?),
+            name: row.get("name")?,
+            transport: Transport::parse(&transport).unwrap_or(Transport::Usb),
+            address: row.get("address")?,
+            driver: row.get("driver")?,
+            baud_rate: row.get("baud_rate")?,
+            paper_width: row.get("paper_width")?,
+            code_page: row.get("code_page")?,
+            vendor_profile: VendorProfile::parse(&vendor_profile),
+            flow_control: FlowControl::parse(&flow_control),
+            data_bits: row.get("data_bits")?,
+            parity: Parity::parse(&parity),
+            stop_bits: row.get("stop_bits")?,
+            chunk_size: row.get("chunk_size")?,
+            chunk_delay_ms: row.get("chunk_delay_ms")?,
+            printer_type: PrinterType::parse(&printer_type),
+            timeout_secs: row.get("timeout_secs")?,
+            line_spacing: row.get("line_spacing")?,
+            feed_lines_before_cut: row.get("feed_lines_before_cut")?,
+            feed_lines_after_cut: row.get("feed_lines_after_cut")?,
+            init_sequence: row.get("init_sequence")?,
+            snmp_community: row.get("snmp_community")?,
+            outlet_id: row.get("outlet_id")?,
+            paper_status: PaperStatus::parse(&paper_status),
+            paper_status_at: row.get("paper_status_at")?,
+        })
+    }
+}
+
+fn destination_columns(destination: &Destination) -> (&'static str, &str) {
+    match destination {
+        Destination::Usb { port, .. } => (Transport::Usb.as_str(), port),
+        Destination::Network { address } => (Transport::Network.as_str(), address),
+        Destination::Ipp { url } => (Transport::Ipp.as_str(), url),
+        Destination::Lpr { address } => (Transport::Lpr.as_str(), address),
+        Destination::Virtual { directory, .. } => (Transport::Virtual.as_str(), directory),
+        Destination::Driver { address, .. } => (Transport::Driver.as_str(), address),
+    }
+}
+
+// Tables created by older versions lack columns added since; SQLite has no ADD COLUMN IF NOT EXISTS.
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>("name"))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .iter()
+        .any(|name| name == column);
+    if !exists {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {};", table, column, definition))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn create_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS printers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            transport TEXT NOT NULL,
+            address TEXT NOT NULL,
+            baud_rate INTEGER NOT NULL DEFAULT 0,
+            paper_width INTEGER NOT NULL DEFAULT 48,
+            code_page INTEGER NOT NULL DEFAULT 0,
+            vendor_profile TEXT NOT NULL DEFAULT 'generic',
+            flow_control TEXT NOT NULL DEFAULT 'none',
+            data_bits INTEGER NOT NULL DEFAULT 8,
+            parity TEXT NOT NULL DEFAULT 'none',
+            stop_bits INTEGER NOT NULL DEFAULT 1,
+            chunk_size INTEGER NOT NULL DEFAULT 0,
+            chunk_delay_ms INTEGER NOT NULL DEFAULT 0,
+            printer_type TEXT NOT NULL DEFAULT 'auto',
+            timeout_secs INTEGER NOT NULL DEFAULT 0,
+            snmp_community TEXT,
+            paper_status TEXT NOT NULL DEFAULT 'ok',
+            paper_status_at TEXT
+        );",
+    )?;
+    ensure_column(conn, "printers", "paper_status", "TEXT NOT NULL DEFAULT 'ok'")?;
+    ensure_column(conn, "printers", "paper_status_at", "TEXT")?;
+    ensure_column(conn, "printers", "snmp_community", "TEXT")?;
+    ensure_column(conn, "printers", "flow_control", "TEXT NOT NULL DEFAULT 'none'")?;
+    ensure_column(conn, "printers", "data_bits", "INTEGER NOT NULL DEFAULT 8")?;
+    ensure_column(conn, "printers", "parity", "TEXT NOT NULL DEFAULT 'none'")?;
+    ensure_column(conn, "printers", "stop_bits", "INTEGER NOT NULL DEFAULT 1")?;
+    ensure_column(conn, "printers", "timeout_secs", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "printers", "chunk_size", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "printers", "chunk_delay_ms", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "printers", "vendor_profile", "TEXT NOT NULL DEFAULT 'generic'")?;
+    ensure_column(conn, "printers", "printer_type", "TEXT NOT NULL DEFAULT 'auto'")
+}
+
+// The printers at this terminal's outlet, plus those shared by every outlet.
+pub(crate) fn list(conn: &Connection) -> rusqlite::Result<Vec<PrinterProfile>> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM printers WHERE {} ORDER BY name", AT_THIS_OUTLET))?;
+    let profiles = stmt.query_map(params![config::current().outlet_id], PrinterProfile::from_row)?.collect();
+    profiles
+}
+
+// Another outlet's printer isn't found, as if it didn't exist.
+pub(crate) fn find(conn: &Connection, id: i64) -> rusqlite::Result<Option<PrinterProfile>> {
+    conn.query_row(
+        &format!("SELECT * FROM printers WHERE id = ?2 AND {}", AT_THIS_OUTLET),
+        params![config::current().outlet_id, id],
+        PrinterProfile::from_row,
+    )
+    .optional()
+}
+
+pub(crate) fn get(conn: &Connection, id: i64) -> Result<PrinterProfile, PrintError> {
+    find(conn, id)?.ok_or_else(|| PrintError::new(ErrorCode::InvalidSettings, format!("Printer {} does not exist", id)))
+}
+
+// Two outlets can have printers at the same address on their own networks; this outlet's comes first.
+pub(crate) fn find_by_destination(conn: &Connection, destination: &Destination) -> rusqlite::Result<Option<PrinterProfile>> {
+    let (transport, address) = destination_columns(destination);
+    conn.query_row(
+        &format!(
+            "SELECT * FROM printers WHERE transport = ?2 AND address = ?3 AND {} ORDER BY outlet_id IS NULL LIMIT 1",
+            AT_THIS_OUTLET
+        ),
+        params![config::current().outlet_id, transport, address],
+        PrinterProfile::from_row,
+    )
+    .optional()
+}
+
+pub(crate) fn set_paper_status(conn: &Connection, id: i64, paper_status: PaperStatus) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE printers SET paper_status = ?1, paper_status_at = datetime('now') WHERE id = ?2",
+        params![paper_status.as_str(), id],
+    )?;
+    Ok(())
+}
+
+pub(crate) fn set_baud_rate(conn: &Connection, id: i64, baud_rate: u32) -> rusqlite::Result<()> {
+    conn.execute("UPDATE printers SET baud_rate = ?1 WHERE id = ?2", params![baud_rate, id])?;
+    Ok(())
+}
+
+pub(crate) fn set_vendor_profile(conn: &Connection, id: i64, vendor: VendorProfile) -> rusqlite::Result<()> {
+    conn.execute("UPDATE printers SET vendor_profile = ?1 WHERE id = ?2", params![vendor, id])?;
+    load_init_sequences(conn)
+}
+
+// Columns the settings UI edits; the status columns are maintained by the print layer.
+fn editable_values(profile: &PrinterProfile) -> Vec<(&'static str, &dyn ToSql)> {
+    vec![
+        (":name", &profile.name),
+        (":transport", &profile.transport),
+        (":address", &profile.address),
+        (":driver", &profile.driver),
+        (":baud_rate", &profile.baud_rate),
+        (":paper_width", &profile.paper_width),
+        (":code_page", &profile.code_page),
+        (":vendor_profile", &profile.vendor_profile),
+        (":flow_control", &profile.flow_control),
+        (":data_bits", &profile.data_bits),
+        (":parity", &profile.parity),
+        (":stop_bits", &profile.stop_bits),
+        (":timeout_secs", &profile.timeout_secs),
+        (":chunk_size", &profile.chunk_size),
+        (":chunk_delay_ms", &profile.chunk_delay_ms),
+        (":printer_type", &profile.printer_type),
+        (":line_spacing", &profile.line_spacing),
+        (":feed_lines_before_cut", &profile.feed_lines_before_cut),
+        (":feed_lines_after_cut", &profile.feed_lines_after_cut),
+        (":init_sequence", &profile.init_sequence),
+        (":snmp_community", &profile.snmp_community),
+        (":outlet_id", &profile.outlet_id),
+    ]
+}
+
+fn validate(profile: &PrinterProfile) -> Result<(), PrintError> {
+    if profile.name.trim().is_empty() {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "Printer name cannot be empty"));
+    }
+    if profile.address.trim().is_empty() {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "Printer address cannot be empty"));
+    }
+    if profile.transport == Transport::Driver && profile.driver.as_deref().map_or(true, |d| d.trim().is_empty()) {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "Choose which printer driver to use"));
+    }
+    if profile.paper_width == 0 {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "Paper width must be greater than zero"));
+    }
+    if profile.init_sequence.len() > MAX_INIT_SEQUENCE {
+        return Err(PrintError::new(
+            ErrorCode::InvalidSettings,
+            format!("Init sequence can be at most {} bytes", MAX_INIT_SEQUENCE),
+        ));
+    }
+    if let Some(message) = serial::framing_error(&profile.serial_settings()) {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, message));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_printers(state: tauri::State<'_, DbState>) -> Result<Vec<PrinterProfile>, PrintError> {
+    let conn = database::connection(&state)?;
+    Ok(list(&conn)?)
+}
+
+#[tauri::command]
+pub fn save_printer(profile: PrinterProfile, state: tauri::State<'_, DbState>) -> Result<PrinterProfile, PrintError> {
+    validate(&profile)?;
+    let conn = database::connection(&state)?;
+
+    // The write and the read-back see the same row
+    let saved = database::write_transaction(&conn, |conn| {
+        let mut values = editable_values(&profile);
+        let columns: Vec<&'static str> = values.iter().map(|(name, _)| *name).map(|name| &name[1..]).collect();
+
+        let id = match profile.id {
+            Some(id) => {
+                let assignments: Vec<String> = columns.iter().map(|c| format!("{} = :{}", c, c)).collect();
+                values.push((":id", &id));
+                let updated = conn.execute(
+                    &format!("UPDATE printers SET {} WHERE id = :id", assignments.join(", ")),
+                    values.as_slice(),
+                )?;
+                if updated == 0 {
+                    return Err(PrintError::new(ErrorCode::InvalidSettings, format!("Printer {} does not exist", id)));
+                }
+                id
+            }
+            None => {
+                let placeholders: Vec<String> = columns.iter().map(|c| format!(":{}", c)).collect();
+                conn.execute(
+                    &format!("INSERT INTO printers ({}) VALUES ({})", columns.join(", "), placeholders.join(", ")),
+                    values.as_slice(),
+                )?;
+                conn.last_insert_rowid()
+            }
+        };
+
+        // Not `get`: a printer can be set up here for another outlet
+        Ok(conn.query_row("SELECT * FROM printers WHERE id = ?1", params![id], PrinterProfile::from_row)?)
+    })?;
+    load_init_sequences(&conn)?;
+    Ok(saved)
+}
+
+#[tauri::command]
+pub fn delete_printer(id: i64, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    {
+        let conn = database::connection(&state)?;
+        conn.execute("DELETE FROM printers WHERE id = ?1", params![id])?;
+        load_init_sequences(&conn)?;
+    }
+
+    // Don't leave the deleted printer behind as a default, label or expo target, or as a cashier's drawer
+    let current = config::current();
+    let has_drawers = current.drawer_assignments.values().any(|drawer| drawer.printer_id == id);
+    if current.default_printer_ids.contains(&id)
+        || current.label_printer_id == Some(id)
+        || current.expo_printer_id == Some(id)
+        || has_drawers
+    {
+        config::update_persisted(&state, |config| {
+            config.default_printer_ids.retain(|p| *p != id);
+            config.drawer_assignments.retain(|_, drawer| drawer.printer_id != id);
+            if config.label_printer_id == Some(id) {
+                config.label_printer_id = None;
+            }
+            if config.expo_printer_id == Some(id) {
+                config.expo_printer_id = None;
+            }
+        })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,305 @@
+// Jobs that were accepted but never reached their printer, kept in the database so they survive a
+// restart: jobs cut off by closing the app, and jobs for a printer that could not be reached (up to
+// `offline_queue_limit` per printer). They are sent again, oldest first, when the queue is flushed
+// and the next time the plugin starts.
+
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use crate::db::DbState;
+
+use super::breaker;
+use super::database;
+use super::error::{ErrorCode, PrintError};
+use super::profile::PrinterProfile;
+use super::{config, job_timeout, mark_printed, print_with_failover, profile, shutdown, DeliveredTo, Destination};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedJob {
+    pub id: i64,
+    // None for jobs that didn't belong to an order, e.g. raw bytes parked on exit
+    pub order_id: Option<i64>,
+    pub destination: Destination,
+    pub reason: String,
+    pub size: usize,
+    pub created_at: String,
+    #[serde(skip)]
+    payload: Vec<u8>,
+}
+
+impl QueuedJob {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let destination: String = row.get("destination")?;
+        let payload: Vec<u8> = row.get("payload")?;
+        Ok(QueuedJob {
+            id: row.get("id")?,
+            order_id: row.get("order_id")?,
+            destination: serde_json::from_str(&destination).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+            })?,
+            reason: row.get("reason")?,
+            size: payload.len(),
+            created_at: row.get("created_at")?,
+            payload,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FlushSummary {
+    pub printed: usize,
+    // Still queued because their printer is still unreachable
+    pub remaining: usize,
+    // Orders whose queued tickets went out, in the order they printed
+    pub order_ids: Vec<i64>,
+}
+
+pub const QUEUE_EVENT: &str = "printer://queue";
+
+// Sent when a printer that came back online has had its backlog drained.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueFlushedEvent {
+    pub printer_id: Option<i64>,
+    pub name: String,
+    #[serde(flatten)]
+    pub summary: FlushSummary,
+}
+
+// Two flushes at once, say a manual one racing the status monitor's, would print the same jobs twice.
+fn flush_lock() -> &'static tokio::sync::Mutex<()> {
+    static LOCK: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| tokio::sync::Mutex::new(()))
+}
+
+// `reason` says why the job was parked, for the logs and the support screen.
+pub(crate) fn park(
+    conn: &Connection,
+    order_id: Option<i64>,
+    destination: &Destination,
+    payload: &[u8],
+    reason: &str,
+) -> Result<(), PrintError> {
+    let serialized = serde_json::to_string(destination).map_err(|e| PrintError::new(ErrorCode::Unknown, e.to_string()))?;
+    conn.execute(
+        "INSERT INTO print_queue (destination_key, destination, payload, reason, order_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![destination.key(), serialized, payload, reason, order_id],
+    )?;
+    warn!("Parked a {} byte job for {} ({})", payload.len(), destination.key(), reason);
+    Ok(())
+}
+
+pub(crate) fn load(conn: &Connection, destination_key: Option<&str>) -> rusqlite::Result<Vec<QueuedJob>> {
+    let mut stmt = conn.prepare("SELECT * FROM print_queue WHERE (?1 IS NULL OR destination_key = ?1) ORDER BY id")?;
+    let jobs = stmt.query_map(params![destination_key], QueuedJob::from_row)?.collect();
+    jobs
+}
+
+fn queued_count(conn: &Connection, destination_key: &str) -> rusqlite::Result<u32> {
+    conn.query_row("SELECT COUNT(*) FROM print_queue WHERE destination_key = ?1", params![destination_key], |row| row.get(0))
+}
+
+// Jobs waiting per destination key.
+pub(crate) fn depths(conn: &Connection) -> rusqlite::Result<HashMap<String, u32>> {
+    let mut stmt = conn.prepare("SELECT destination_key, COUNT(*) FROM print_queue GROUP BY destination_key")?;
+    let depths = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect();
+    depths
+}
+
+// Only a printer that can't be reached is worth queueing for; a cover-open or paper-out printer
+// needs someone to walk over to it, and the cashier has to hear about that now.
+fn is_unreachable(error: &PrintError) -> bool {
+    matches!(error.code, ErrorCode::ConnectionFailed | ErrorCode::Timeout)
+}
+
+// Turns an unreachable-printer failure into a queued job while that printer's queue has room.
+// Any other outcome passes through untouched.
+pub(crate) async fn queue_if_unreachable(
+    state: &DbState,
+    order_id: Option<i64>,
+    destination: &Destination,
+    payload: &[u8],
+    outcome: Result<DeliveredTo, PrintError>,
+) -> Result<DeliveredTo, PrintError> {
+    let limit = config::current().offline_queue_limit;
+    // Jobs held for shutdown are parked by shutdown::run already
+    let error = match outcome {
+        Err(e) if limit > 0 && is_unreachable(&e) && !shutdown::is_shutting_down() => e,
+        other => return other,
+    };
+
+    let key = destination.key();
+    let (destination, payload, reason) = (destination.clone(), payload.to_vec(), error.to_string());
+    let queued = database::run(state, move |conn| {
+        database::write_transaction(conn, |conn| {
+            if queued_count(conn, &destination.key())? >= limit {
+                return Ok(false);
+            }
+            park(conn, order_id, &destination, &payload, &reason)?;
+            Ok(true)
+        })
+    })
+    .await;
+    match queued {
+        Ok(true) => Ok(DeliveredTo::Queued),
+        Ok(false) => {
+            error!("Offline queue for {} is full ({} jobs); failing the job", key, limit);
+            Err(error)
+        }
+        Err(e) => {
+            error!("Could not queue a job for {}: {}", key, e);
+            Err(error)
+        }
+    }
+}
+
+// A new job for a printer that still has queued jobs would print ahead of them, so the backlog is
+// tried first and, if some of it is still waiting, the job is queued behind it. `None` when the
+// printer has no backlog left and the job can be sent now.
+pub(crate) async fn join_backlog(
+    state: &DbState,
+    order_id: Option<i64>,
+    destination: &Destination,
+    payload: &[u8],
+) -> Option<Result<DeliveredTo, PrintError>> {
+    // A dry run leaves the queue alone, so it would never drain
+    if config::current().dry_run {
+        return None;
+    }
+    let key = destination.key();
+    let lookup = key.clone();
+    let waiting = database::run(state, move |conn| Ok(queued_count(conn, &lookup)?)).await.unwrap_or_default();
+    if waiting == 0 {
+        return None;
+    }
+
+    let remaining = match flush(state, Some(key.clone())).await {
+        Ok(summary) => summary.remaining,
+        Err(e) => {
+            error!("Could not drain the queue for {}: {}", key, e);
+            waiting as usize
+        }
+    };
+    if remaining == 0 {
+        return None;
+    }
+    match order_id {
+        Some(order_id) => info!("{} still has {} queued jobs; order {} waits behind them", key, remaining, order_id),
+        None => info!("{} still has {} queued jobs; the new job waits behind them", key, remaining),
+    }
+    let behind = PrintError::new(ErrorCode::ConnectionFailed, format!("{} still has {} queued jobs", key, remaining));
+    Some(queue_if_unreachable(state, order_id, destination, payload, Err(behind)).await)
+}
+
+async fn remove(state: &DbState, job: &QueuedJob) {
+    let id = job.id;
+    let removed = database::run(state, move |conn| {
+        conn.execute("DELETE FROM print_queue WHERE id = ?1", params![id])?;
+        Ok(())
+    })
+    .await;
+    if let Err(e) = removed {
+        error!("Queued job {} printed but could not be removed: {}", job.id, e);
+    }
+    if let Some(order_id) = job.order_id {
+        mark_printed(state, order_id, &job.destination).await;
+    }
+}
+
+// Sends queued jobs oldest first. Once a printer fails, its later jobs stay queued untried so
+// tickets never come out of order. In a dry run nothing is sent, so every job stays queued.
+pub(crate) async fn flush(state: &DbState, destination_key: Option<String>) -> Result<FlushSummary, PrintError> {
+    let _flushing = flush_lock().lock().await;
+    let jobs = database::run(state, move |conn| Ok(load(conn, destination_key.as_deref())?)).await?;
+    let mut summary = FlushSummary::default();
+    if config::current().dry_run {
+        if !jobs.is_empty() {
+            info!("Dry run is on; leaving {} queued jobs in the queue", jobs.len());
+        }
+        summary.remaining = jobs.len();
+        return Ok(summary);
+    }
+    let mut unreachable = HashSet::new();
+    for job in jobs {
+        let key = job.destination.key();
+        if unreachable.contains(&key) {
+            summary.remaining += 1;
+            continue;
+        }
+
+        let destination = job.destination.clone();
+        let profile = database::run(state, move |conn| Ok(profile::find_by_destination(conn, &destination)?))
+            .await
+            .unwrap_or_default();
+        let timeout = job_timeout(None, profile.as_ref());
+        match print_with_failover(&job.payload, &job.destination, timeout, job.order_id).await {
+            // Dry run switched on mid-flush; the job wasn't printed
+            Ok(DeliveredTo::DryRun) => summary.remaining += 1,
+            Ok(_) => {
+                remove(state, &job).await;
+                summary.printed += 1;
+                summary.order_ids.extend(job.order_id);
+            }
+            Err(e) => {
+                warn!("Queued job {} for {} still failing: {}", job.id, key, e);
+                unreachable.insert(key);
+                summary.remaining += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+pub(crate) async fn resend_parked(state: &DbState) {
+    match flush(state, None).await {
+        Ok(summary) if summary.printed + summary.remaining > 0 => {
+            info!("Resent {} parked print jobs; {} still waiting", summary.printed, summary.remaining)
+        }
+        Ok(_) => {}
+        Err(e) => error!("Could not load parked print jobs: {}", e),
+    }
+}
+
+// Called by the status monitor when `printer` answers again after being offline.
+pub(crate) async fn resume<R: Runtime>(app: &AppHandle<R>, printer: &PrinterProfile) {
+    let key = printer.destination().key();
+    let state = app.state::<DbState>();
+    let lookup = key.clone();
+    let waiting = database::run(&state, move |conn| Ok(queued_count(conn, &lookup)?)).await.unwrap_or_default();
+    if waiting == 0 {
+        return;
+    }
+
+    // The printer just answered, so a breaker still cooling down from the outage would only get in the way
+    breaker::reset(&key);
+    info!("{} is back online; sending its {} queued jobs", printer.name, waiting);
+    let summary = match flush(&state, Some(key)).await {
+        Ok(summary) => summary,
+        Err(e) => {
+            error!("Could not drain the queue for {}: {}", printer.name, e);
+            return;
+        }
+    };
+    let event = QueueFlushedEvent { printer_id: printer.id, name: printer.name.clone(), summary };
+    if let Err(e) = app.emit(QUEUE_EVENT, event) {
+        error!("Failed to emit queue event: {}", e);
+    }
+}
+
+// The backlog for one printer (by destination key), or for all of them.
+#[tauri::command]
+pub fn list_print_queue(destination_key: Option<String>, state: tauri::State<'_, DbState>) -> Result<Vec<QueuedJob>, PrintError> {
+    let conn = database::connection(&state)?;
+    Ok(load(&conn, destination_key.as_deref())?)
+}
+
+// Tries the backlog now instead of waiting for the next start, e.g. once the switch is back.
+#[tauri::command]
+pub async fn flush_print_queue(
+    destination_key: Option<String>,
+    state: tauri::State<'_, DbState>,
+) -> Result<FlushSummary, PrintError> {
+    flush(&state, destination_key).await
+}
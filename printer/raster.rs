@@ -0,0 +1,304 @@
+// Draws ESC/POS onto a 1-bit page the width of the paper, for image previews. Text uses an 8x8
+// bitmap font stretched to the 12x24 Font A cell; raster images (GS v 0) and QR codes are drawn
+// as the printer would. Barcodes show as a striped block above their text.
+
+use font8x8::{UnicodeFonts, BASIC_FONTS, LATIN_FONTS};
+use qrcode::{Color, EcLevel, QrCode};
+
+use super::error::{ErrorCode, PrintError};
+use super::escpos::{self, Align};
+
+const CELL_WIDTH: usize = 12;
+const CELL_HEIGHT: usize = 24;
+// ESC 2 default: 1/6 inch at 180 dpi
+const LINE_SPACING: usize = 30;
+const MARGIN: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+struct Glyph {
+    ch: char,
+    width: usize,
+    height: usize,
+    bold: bool,
+    underline: bool,
+}
+
+struct Page {
+    width: usize,
+    rows: Vec<Vec<bool>>,
+}
+
+impl Page {
+    fn set(&mut self, x: usize, y: usize) {
+        if x >= self.width {
+            return;
+        }
+        while self.rows.len() <= y {
+            self.rows.push(vec![false; self.width]);
+        }
+        self.rows[y][x] = true;
+    }
+
+    fn grow_to(&mut self, height: usize) {
+        while self.rows.len() < height {
+            self.rows.push(vec![false; self.width]);
+        }
+    }
+
+    // Grayscale PNG with a white margin all round.
+    fn to_png(&self) -> Result<Vec<u8>, PrintError> {
+        let (width, height) = (self.width + MARGIN * 2, self.rows.len() + MARGIN * 2);
+        let mut pixels = vec![0xFFu8; width * height];
+        for (y, row) in self.rows.iter().enumerate() {
+            for (x, black) in row.iter().enumerate() {
+                if *black {
+                    pixels[(y + MARGIN) * width + x + MARGIN] = 0;
+                }
+            }
+        }
+
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&pixels))
+            .map_err(|e| PrintError::new(ErrorCode::Unknown, format!("Could not encode preview: {}", e)))?;
+        Ok(png)
+    }
+}
+
+struct RasterRenderer {
+    page: Page,
+    y: usize,
+    line: Vec<Glyph>,
+    align: Align,
+    bold: bool,
+    underline: bool,
+    size: (usize, usize),
+    qr_data: Vec<u8>,
+    qr_module: usize,
+}
+
+impl RasterRenderer {
+    fn new(width_dots: usize) -> Self {
+        RasterRenderer {
+            page: Page { width: width_dots, rows: Vec::new() },
+            y: 0,
+            line: Vec::new(),
+            align: Align::Left,
+            bold: false,
+            underline: false,
+            size: (1, 1),
+            qr_data: Vec::new(),
+            qr_module: 3,
+        }
+    }
+
+    fn offset_for(&self, content_width: usize) -> usize {
+        let free = self.page.width.saturating_sub(content_width);
+        match self.align {
+            Align::Left => 0,
+            Align::Center => free / 2,
+            Align::Right => free,
+        }
+    }
+
+    fn draw_glyph(&mut self, glyph: &Glyph, x: usize, baseline: usize) {
+        let bitmap = BASIC_FONTS.get(glyph.ch).or_else(|| LATIN_FONTS.get(glyph.ch)).or_else(|| BASIC_FONTS.get('?'));
+        let Some(bitmap) = bitmap else {
+            return;
+        };
+        let (width, height) = (CELL_WIDTH * glyph.width, CELL_HEIGHT * glyph.height);
+        let top = baseline - height;
+        for dy in 0..height {
+            let bits = bitmap[dy * 8 / height];
+            for dx in 0..width {
+                // font8x8 stores the leftmost pixel in the lowest bit
+                if (bits >> (dx * 8 / width)) & 1 == 1 {
+                    self.page.set(x + dx, top + dy);
+                    if glyph.bold {
+                        self.page.set(x + dx + 1, top + dy);
+                    }
+                }
+            }
+        }
+        if glyph.underline {
+            for dx in 0..width {
+                self.page.set(x + dx, baseline - 1);
+            }
+        }
+    }
+
+    // Prints the pending text as one line; long lines wrap like the printer wraps them.
+    fn flush(&mut self) {
+        if self.line.is_empty() {
+            self.y += LINE_SPACING;
+            self.page.grow_to(self.y);
+            return;
+        }
+        let glyphs = std::mem::take(&mut self.line);
+        let mut start = 0;
+        while start < glyphs.len() {
+            let mut end = start;
+            let mut width = 0;
+            while end < glyphs.len() && width + glyphs[end].width * CELL_WIDTH <= self.page.width {
+                width += glyphs[end].width * CELL_WIDTH;
+                end += 1;
+            }
+            let end = end.max(start + 1);
+            let height = glyphs[start..end].iter().map(|g| g.height).max().unwrap_or(1);
+            let baseline = self.y + CELL_HEIGHT * height;
+            let mut x = self.offset_for(width);
+            for glyph in &glyphs[start..end] {
+                self.draw_glyph(glyph, x, baseline);
+                x += glyph.width * CELL_WIDTH;
+            }
+            self.y += LINE_SPACING + CELL_HEIGHT * (height - 1);
+            self.page.grow_to(self.y);
+            start = end;
+        }
+    }
+
+    fn draw_block(&mut self, width: usize, height: usize, black: impl Fn(usize, usize) -> bool) {
+        if !self.line.is_empty() {
+            self.flush();
+        }
+        let left = self.offset_for(width);
+        for dy in 0..height {
+            for dx in 0..width {
+                if black(dx, dy) {
+                    self.page.set(left + dx, self.y + dy);
+                }
+            }
+        }
+        self.y += height;
+        self.page.grow_to(self.y);
+    }
+
+    fn draw_qr(&mut self) {
+        let Ok(code) = QrCode::with_error_correction_level(&self.qr_data, EcLevel::M) else {
+            warn!("Preview skipped a QR code that doesn't encode");
+            return;
+        };
+        let modules = code.width();
+        let colors = code.to_colors();
+        let size = self.qr_module;
+        self.draw_block(modules * size, modules * size, |x, y| colors[(y / size) * modules + x / size] == Color::Dark);
+    }
+
+    // Stand-in for a barcode: bars from the data bits, with the text underneath.
+    fn draw_barcode(&mut self, data: &[u8]) {
+        let bits: Vec<bool> = data.iter().flat_map(|b| (0..8).map(move |i| (b >> (7 - i)) & 1 == 1)).collect();
+        let width = (bits.len() * 2).min(self.page.width);
+        self.draw_block(width, 80, |x, _| bits.get(x / 2).copied().unwrap_or(false));
+        self.line = String::from_utf8_lossy(data).chars().map(|ch| self.glyph(ch)).collect();
+        self.flush();
+    }
+
+    fn glyph(&self, ch: char) -> Glyph {
+        Glyph { ch, width: self.size.0, height: self.size.1, bold: self.bold, underline: self.underline }
+    }
+
+    fn cut(&mut self) {
+        if !self.line.is_empty() {
+            self.flush();
+        }
+        let y = self.y + LINE_SPACING / 2;
+        for x in (0..self.page.width).filter(|x| x % 8 < 4) {
+            self.page.set(x, y);
+        }
+        self.y += LINE_SPACING;
+    }
+
+    fn command(&mut self, bytes: &[u8]) {
+        let arg = |i: usize| bytes.get(i).copied().unwrap_or(0) as usize;
+        match bytes {
+            [0x1B, b'@', ..] => {
+                self.align = Align::Left;
+                self.bold = false;
+                self.underline = false;
+                self.size = (1, 1);
+            }
+            [0x1B, b'a', n, ..] => {
+                self.align = match n {
+                    1 | b'1' => Align::Center,
+                    2 | b'2' => Align::Right,
+                    _ => Align::Left,
+                }
+            }
+            [0x1B, b'E', n, ..] => self.bold = n & 1 == 1,
+            [0x1B, b'-', n, ..] => self.underline = n & 3 != 0,
+            [0x1B, b'!', n, ..] => {
+                self.bold = n & 0x08 != 0;
+                self.underline = n & 0x80 != 0;
+                self.size = (if n & 0x20 != 0 { 2 } else { 1 }, if n & 0x10 != 0 { 2 } else { 1 });
+            }
+            [0x1D, b'!', n, ..] => self.size = ((n >> 4) as usize + 1, (n & 0x0F) as usize + 1),
+            [0x1B, b'd', n, ..] => {
+                self.flush();
+                self.y += LINE_SPACING * (*n as usize).saturating_sub(1);
+            }
+            [0x1D, b'V', ..] | [0x1B, b'i' | b'm', ..] => self.cut(),
+            // GS v 0: m xL xH yL yH, then rows of packed bits
+            [0x1D, b'v', b'0', ..] => {
+                let (width_bytes, height) = (arg(4) + arg(5) * 256, arg(6) + arg(7) * 256);
+                let data = &bytes[bytes.len().min(8)..];
+                self.draw_block(width_bytes * 8, height, |x, y| {
+                    data.get(y * width_bytes + x / 8).is_some_and(|b| (b >> (7 - x % 8)) & 1 == 1)
+                });
+            }
+            // GS ( k: QR module size (fn 67), store data (fn 80), print (fn 81)
+            [0x1D, b'(', b'k', _, _, 0x31, 0x43, n, ..] => self.qr_module = (*n as usize).max(1),
+            [0x1D, b'(', b'k', _, _, 0x31, 0x50, 0x30, data @ ..] => self.qr_data = data.to_vec(),
+            [0x1D, b'(', b'k', _, _, 0x31, 0x51, ..] => self.draw_qr(),
+            [0x1D, b'k', m, rest @ ..] if *m <= 6 => {
+                let data: Vec<u8> = rest.iter().copied().take_while(|b| *b != 0).collect();
+                self.draw_barcode(&data);
+            }
+            [0x1D, b'k', _, _, data @ ..] => {
+                // CODE128 data starts with a code set selector like "{B"
+                let data = if data.first() == Some(&b'{') { &data[data.len().min(2)..] } else { data };
+                self.draw_barcode(data);
+            }
+            _ => {}
+        }
+    }
+
+    fn render(mut self, content: &[u8]) -> Page {
+        let mut i = 0;
+        while i < content.len() {
+            match content[i] {
+                b'\n' => {
+                    self.flush();
+                    i += 1;
+                }
+                0x1B | 0x1D | 0x1C | 0x10 => {
+                    let length = escpos::command_length(&content[i..]);
+                    self.command(&content[i..i + length]);
+                    i += length;
+                }
+                byte if byte < 0x20 => i += 1,
+                _ => {
+                    // Decode one UTF-8 character (or a lone high byte) at a time
+                    let end = (i + 1..=content.len().min(i + 4))
+                        .find(|end| std::str::from_utf8(&content[i..*end]).is_ok())
+                        .unwrap_or(i + 1);
+                    let ch = std::str::from_utf8(&content[i..end]).ok().and_then(|s| s.chars().next()).unwrap_or('?');
+                    self.line.push(self.glyph(ch));
+                    i = end;
+                }
+            }
+        }
+        if !self.line.is_empty() {
+            self.flush();
+        }
+        self.page
+    }
+}
+
+// `columns` is the paper width in Font A characters, 12 dots each.
+pub(crate) fn render_png(content: &[u8], columns: usize) -> Result<Vec<u8>, PrintError> {
+    RasterRenderer::new(columns.max(1) * CELL_WIDTH).render(content).to_png()
+}
@@ -0,0 +1,168 @@
+
+use crate::db::DbState;
+
+use super::clock;
+use super::config::{self, PrinterConfig};
+use super::database;
+use super::error::PrintError;
+use super::letterhead;
+use super::pdf::{Canvas, Jpeg, PdfWriter, Weight, A4};
+use super::sequences::{self, BILL_SERIES};
+use super::{fetch_order_details, DocumentType, OrderDetails, SectionData, SimpleSectionData};
+
+const MARGIN: f32 = 48.0;
+const LINE_HEIGHT: f32 = 16.0;
+const LOGO_MAX_HEIGHT: f32 = 60.0;
+
+// Flavor and dine-in/pack breakdown printed under an item, e.g. "Pack: Cheese x2 (extra sauce:1)".
+pub(crate) fn item_details(item_type: &str, dinein_json: &Option<String>, pack_json: &Option<String>) -> Vec<String> {
+    let mut details = Vec::new();
+    for (json, section) in [(dinein_json, "Table"), (pack_json, "Pack")] {
+        let Some(json) = json else {
+            continue;
+        };
+        match item_type {
+            "corndog" | "beverage" => {
+                let Ok(data) = serde_json::from_str::<SectionData>(json) else {
+                    continue;
+                };
+                for (flavor_name, flavor_data) in &data.flavors {
+                    if flavor_data.total == 0 {
+                        continue;
+                    }
+                    let modifiers: Vec<String> = flavor_data
+                        .modifier
+                        .iter()
+                        .filter(|(_, count)| **count > 0)
+                        .map(|(key, count)| format!("{}:{}", key.replace("_", " "), count))
+                        .collect();
+                    let mut detail = format!("{}: {} x{}", section, flavor_name.replace("_", " "), flavor_data.total);
+                    if !modifiers.is_empty() {
+                        detail.push_str(&format!(" ({})", modifiers.join(", ")));
+                    }
+                    details.push(detail);
+                }
+            }
+            _ => {
+                if let Ok(data) = serde_json::from_str::<SimpleSectionData>(json) {
+                    if data.total > 0 {
+                        details.push(format!("{}: {}", section, data.total));
+                    }
+                }
+            }
+        }
+    }
+    details
+}
+
+// A missing or unreadable logo leaves the header text-only rather than failing the document.
+pub(crate) fn load_logo(config: &PrinterConfig) -> Option<Jpeg> {
+    let path = config.receipt_logo_path.as_ref()?;
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) => {
+            warn!("Could not read receipt logo {}: {}", path, e);
+            return None;
+        }
+    };
+    Jpeg::parse(data).map_err(|e| warn!("Ignoring receipt logo {}: {}", path, e)).ok()
+}
+
+// Logo and business details at the top of the page; returns where the body starts.
+pub(crate) fn draw_letterhead<C: Canvas>(canvas: &mut C, config: &PrinterConfig) -> f32 {
+    let right = canvas.width() - MARGIN;
+    let mut y = MARGIN;
+    if let Some(logo) = load_logo(config) {
+        let height = LOGO_MAX_HEIGHT.min(120.0 / logo.aspect_ratio());
+        canvas.image(&logo, MARGIN, y, height * logo.aspect_ratio(), height);
+    }
+    canvas.text_right(right, y + 16.0, 16.0, Weight::Bold, &config.business_name);
+    for line in config.business_address.lines() {
+        y += 14.0;
+        canvas.text_right(right, y + 16.0, 9.0, Weight::Regular, line);
+    }
+    if !config.business_phone.is_empty() {
+        y += 14.0;
+        canvas.text_right(right, y + 16.0, 9.0, Weight::Regular, &format!("Phone: {}", config.business_phone));
+    }
+    (y + 16.0).max(MARGIN + LOGO_MAX_HEIGHT) + 24.0
+}
+
+pub(crate) fn render(order: &OrderDetails, bill_number: &str, config: &PrinterConfig) -> Vec<u8> {
+    let mut pdf = PdfWriter::new(A4);
+    let right = pdf.width() - MARGIN;
+    let bottom = pdf.height() - MARGIN;
+    let mut y = draw_letterhead(&mut pdf, config);
+
+    pdf.text(MARGIN, y, 14.0, Weight::Bold, "RECEIPT");
+    pdf.text_right(right, y, 10.0, Weight::Regular, &format!("Bill No: {}", bill_number));
+    y += LINE_HEIGHT;
+    let order_type = if order.has_table { "Dine-in" } else { "Takeaway" };
+    pdf.text(MARGIN, y, 10.0, Weight::Regular, &format!("{} - Order #{}", order_type, order.order_number));
+    pdf.text_right(right, y, 10.0, Weight::Regular, &clock::now().format("%Y-%m-%d %I:%M %p").to_string());
+    y += LINE_HEIGHT;
+
+    pdf.line(MARGIN, y, right, y, 1.0);
+    y += LINE_HEIGHT;
+    pdf.text(MARGIN, y, 10.0, Weight::Bold, "Qty");
+    pdf.text(MARGIN + 40.0, y, 10.0, Weight::Bold, "Item");
+    y += 6.0;
+    pdf.line(MARGIN, y, right, y, 0.5);
+    y += LINE_HEIGHT;
+
+    for (item_type, name, quantity, dinein_json, pack_json) in &order.item_data {
+        let details = item_details(item_type, dinein_json, pack_json);
+        // Keep an item and its breakdown on the same page
+        if y + LINE_HEIGHT * details.len() as f32 > bottom {
+            pdf.new_page();
+            y = MARGIN;
+        }
+        pdf.text(MARGIN, y, 10.0, Weight::Regular, &quantity.to_string());
+        pdf.text(MARGIN + 40.0, y, 10.0, Weight::Bold, name);
+        y += LINE_HEIGHT;
+        for detail in details {
+            pdf.text(MARGIN + 52.0, y, 9.0, Weight::Regular, &detail);
+            y += LINE_HEIGHT;
+        }
+    }
+
+    if y + LINE_HEIGHT * 5.0 > bottom {
+        pdf.new_page();
+        y = MARGIN;
+    }
+    pdf.line(MARGIN, y - 6.0, right, y - 6.0, 0.5);
+    y += 6.0;
+    if order.discount_amount > 0.0 {
+        pdf.text_right(right - 90.0, y, 10.0, Weight::Regular, "Discount");
+        pdf.text_right(right, y, 10.0, Weight::Regular, &format!("-{:.2}", order.discount_amount));
+        y += LINE_HEIGHT;
+    }
+    pdf.text_right(right - 90.0, y, 12.0, Weight::Bold, "Total");
+    pdf.text_right(right, y, 12.0, Weight::Bold, &format!("{:.2}", order.total_amount));
+    y += LINE_HEIGHT * 2.0;
+
+    if !order.notes.trim().is_empty() {
+        pdf.text(MARGIN, y, 9.0, Weight::Regular, &format!("Notes: {}", order.notes.trim()));
+        y += LINE_HEIGHT;
+    }
+    if let Some(footer) = letterhead::footer(config, DocumentType::Bill) {
+        for line in footer.lines() {
+            y += LINE_HEIGHT;
+            pdf.text_center(pdf.width() / 2.0, y, 10.0, Weight::Regular, line);
+        }
+    }
+    pdf.finish()
+}
+
+// Writes a PDF copy of the bill for customers who need something other than the thermal slip.
+#[tauri::command]
+pub async fn generate_receipt_pdf(order_id: i64, path: String, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    let (order, bill_number) = database::run(&state, move |conn| {
+        Ok((fetch_order_details(conn, order_id)?, sequences::number_for_order(conn, BILL_SERIES, order_id)?))
+    })
+    .await?;
+    let document = render(&order, &bill_number, &config::current());
+    std::fs::write(&path, document).map_err(|e| PrintError::from_io(&format!("Could not write {}", path), &e))?;
+    info!("Receipt PDF for order {} written to {}", order_id, path);
+    Ok(())
+}
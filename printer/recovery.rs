@@ -0,0 +1,101 @@
+// The orders table belongs to the host app, so the plugin keeps its own record of which order
+// documents it was asked to print and whether every printer took them. A row left unprinted means
+// the job failed everywhere, or the app died before it finished; either way the kitchen may never
+// have seen the ticket. The startup scan logs them and `get_unprinted_orders` lists them for the UI.
+
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+
+use crate::db::DbState;
+
+use super::config;
+use super::database;
+use super::error::PrintError;
+use super::{DeliveredTo, DocumentType};
+
+// Older attempts are forgotten; nobody reprints last week's KOT
+const KEEP_DAYS: u32 = 7;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnprintedOrder {
+    pub order_id: i64,
+    pub document_type: DocumentType,
+    // None when the app stopped mid-job rather than the printers failing it
+    pub error: Option<String>,
+    pub updated_at: String,
+}
+
+impl UnprintedOrder {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let document_type: String = row.get("document_type")?;
+        Ok(UnprintedOrder {
+            order_id: row.get("order_id")?,
+            document_type: DocumentType::parse(&document_type),
+            error: row.get("error")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+async fn record(state: &DbState, order_id: i64, document_type: DocumentType, printed: bool, error: Option<String>) {
+    let recorded = database::run(state, move |conn| {
+        conn.execute(
+            "INSERT INTO print_attempts (order_id, document_type, printed, error) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (order_id, document_type)
+             DO UPDATE SET printed = ?3, error = ?4, updated_at = datetime('now')",
+            params![order_id, document_type, printed, error],
+        )?;
+        Ok(())
+    })
+    .await;
+    if let Err(e) = recorded {
+        error!("Failed to record print attempt for order {}: {}", order_id, e);
+    }
+}
+
+// Before anything is sent, so a crash mid-job leaves the order unprinted.
+pub(crate) async fn started(state: &DbState, order_id: i64, document_type: DocumentType) {
+    record(state, order_id, document_type, false, None).await;
+}
+
+// With one outcome per printer the document went to. A job sitting in the offline queue counts
+// as printed here; the queue keeps it across restarts.
+pub(crate) async fn finished<'a>(
+    state: &DbState,
+    order_id: i64,
+    document_type: DocumentType,
+    outcomes: impl IntoIterator<Item = &'a Result<DeliveredTo, PrintError>>,
+) {
+    let error = outcomes.into_iter().find_map(|outcome| outcome.as_ref().err()).map(|e| e.to_string());
+    record(state, order_id, document_type, error.is_none(), error).await;
+}
+
+fn unprinted(conn: &Connection, within_hours: u32) -> rusqlite::Result<Vec<UnprintedOrder>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM print_attempts WHERE printed = 0 AND updated_at >= datetime('now', ?1) ORDER BY updated_at",
+    )?;
+    let orders = stmt.query_map(params![format!("-{} hours", within_hours)], UnprintedOrder::from_row)?.collect();
+    orders
+}
+
+// Runs once on startup, after migrations.
+pub(crate) fn scan(conn: &Connection) -> Result<(), PrintError> {
+    conn.execute(
+        "DELETE FROM print_attempts WHERE updated_at < datetime('now', ?1)",
+        params![format!("-{} days", KEEP_DAYS)],
+    )?;
+    let orders = unprinted(conn, config::current().recovery_window_hours)?;
+    if !orders.is_empty() {
+        let ids: Vec<String> = orders.iter().map(|o| format!("{} ({})", o.order_id, o.document_type.as_str())).collect();
+        warn!("{} recent orders never finished printing: {}", orders.len(), ids.join(", "));
+    }
+    Ok(())
+}
+
+// Recent order documents that didn't reach every printer, oldest first, so the cashier can reprint them.
+#[tauri::command]
+pub fn get_unprinted_orders(within_hours: Option<u32>, state: tauri::State<'_, DbState>) -> Result<Vec<UnprintedOrder>, PrintError> {
+    let conn = database::connection(&state)?;
+    let within_hours = within_hours.unwrap_or_else(|| config::current().recovery_window_hours);
+    Ok(unprinted(&conn, within_hours)?)
+}
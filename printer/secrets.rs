@@ -0,0 +1,156 @@
+// Credentials in the printer settings (the email password or API key, the SMS gateway's body and
+// headers, webhook signing secrets) live in their own table instead of the settings JSON, and the
+// settings commands show the webview a placeholder in their place. Saving the placeholder back
+// keeps the stored value.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::config::{EmailTransport, PrinterConfig};
+
+pub(crate) const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Secrets {
+    // The SMTP password or the API key, whichever the transport uses
+    email: Option<String>,
+    sms_body: Option<String>,
+    sms_headers: HashMap<String, String>,
+    // Keyed by webhook URL
+    webhooks: HashMap<String, String>,
+}
+
+impl Secrets {
+    fn masked(self) -> Secrets {
+        let mask = |_| REDACTED.to_string();
+        Secrets {
+            email: self.email.map(mask),
+            sms_body: self.sms_body.map(mask),
+            sms_headers: self.sms_headers.into_iter().map(|(name, _)| (name, REDACTED.to_string())).collect(),
+            webhooks: self.webhooks.into_iter().map(|(url, _)| (url, REDACTED.to_string())).collect(),
+        }
+    }
+}
+
+fn email_secret(transport: &mut EmailTransport) -> &mut String {
+    match transport {
+        EmailTransport::Smtp { password, .. } => password,
+        EmailTransport::Api { api_key, .. } => api_key,
+    }
+}
+
+// Moves the credentials out of `config`, leaving empty values behind.
+pub(crate) fn take(config: &mut PrinterConfig) -> Secrets {
+    let mut secrets = Secrets::default();
+    if let Some(email) = config.email.as_mut() {
+        secrets.email = Some(std::mem::take(email_secret(&mut email.transport))).filter(|s| !s.is_empty());
+    }
+    if let Some(sms) = config.sms.as_mut() {
+        secrets.sms_body = sms.body.take();
+        for (name, value) in sms.headers.iter_mut() {
+            secrets.sms_headers.insert(name.clone(), std::mem::take(value));
+        }
+    }
+    for hook in config.webhooks.iter_mut().filter(|hook| !hook.secret.is_empty()) {
+        secrets.webhooks.insert(hook.url.clone(), std::mem::take(&mut hook.secret));
+    }
+    secrets
+}
+
+pub(crate) fn put_back(config: &mut PrinterConfig, mut secrets: Secrets) {
+    if let (Some(email), Some(secret)) = (config.email.as_mut(), secrets.email) {
+        *email_secret(&mut email.transport) = secret;
+    }
+    if let Some(sms) = config.sms.as_mut() {
+        if sms.body.is_none() {
+            sms.body = secrets.sms_body;
+        }
+        for (name, value) in sms.headers.iter_mut() {
+            if let Some(secret) = secrets.sms_headers.remove(name) {
+                *value = secret;
+            }
+        }
+    }
+    for hook in &mut config.webhooks {
+        if let Some(secret) = secrets.webhooks.remove(&hook.url) {
+            hook.secret = secret;
+        }
+    }
+}
+
+// The settings as the webview gets them.
+pub(crate) fn redacted(mut config: PrinterConfig) -> PrinterConfig {
+    let secrets = take(&mut config);
+    put_back(&mut config, secrets.masked());
+    config
+}
+
+// Credentials sent back as the placeholder keep their value from `previous`.
+pub(crate) fn restore(config: &mut PrinterConfig, previous: &PrinterConfig) {
+    let mut old = take(&mut previous.clone());
+    let mut incoming = take(config);
+    let unchanged = |value: &str| value == REDACTED;
+    if incoming.email.as_deref().is_some_and(unchanged) {
+        incoming.email = old.email.take();
+    }
+    if incoming.sms_body.as_deref().is_some_and(unchanged) {
+        incoming.sms_body = old.sms_body.take();
+    }
+    for (name, value) in incoming.sms_headers.iter_mut().filter(|(_, value)| unchanged(value.as_str())) {
+        *value = old.sms_headers.remove(name).unwrap_or_default();
+    }
+    for (url, secret) in incoming.webhooks.iter_mut().filter(|(_, secret)| unchanged(secret.as_str())) {
+        *secret = old.webhooks.remove(url).unwrap_or_default();
+    }
+    put_back(config, incoming);
+}
+
+fn to_json<T: Serialize>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+pub(crate) fn read(conn: &Connection, terminal_id: &str) -> rusqlite::Result<Secrets> {
+    let stored: Option<String> = conn
+        .query_row("SELECT value FROM printer_secrets WHERE terminal_id = ?1", params![terminal_id], |row| row.get(0))
+        .optional()?;
+    // Unreadable secrets come back empty rather than locking the terminal out of its settings
+    Ok(stored.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default())
+}
+
+pub(crate) fn write(conn: &Connection, terminal_id: &str, secrets: &Secrets) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO printer_secrets (terminal_id, value) VALUES (?1, ?2)
+         ON CONFLICT(terminal_id) DO UPDATE SET value = excluded.value",
+        params![terminal_id, to_json(secrets)?],
+    )?;
+    Ok(())
+}
+
+// Migration: creates the table and moves the credentials out of every saved config.
+pub(crate) fn move_out_of_settings(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS printer_secrets (
+            terminal_id TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );",
+    )?;
+    let rows: Vec<(String, String)> = {
+        let mut stmt = conn.prepare("SELECT terminal_id, value FROM printer_config")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<rusqlite::Result<_>>()?;
+        rows
+    };
+    for (terminal_id, json) in rows {
+        // Left as it is; loading it reports the problem
+        let Ok(mut config) = serde_json::from_str::<PrinterConfig>(&json) else {
+            continue;
+        };
+        let secrets = take(&mut config);
+        conn.execute(
+            "UPDATE printer_config SET value = ?1 WHERE terminal_id = ?2",
+            params![to_json(&config)?, terminal_id],
+        )?;
+        write(conn, &terminal_id, &secrets)?;
+    }
+    Ok(())
+}
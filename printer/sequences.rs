@@ -0,0 +1,204 @@
+// Bill numbers from a counter per series instead of the tail of the order number, which reset
+// whenever the host app's order numbering did. A series can restart every day or fiscal year and
+// carry a prefix, e.g. "INV-{fy}-" -> "INV-2026/27-0042". An order keeps the number it was first
+// given, so reprinting a bill or invoice never takes a new one. Each outlet keeps its own counters
+// in a shared database.
+
+use chrono::{Datelike, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::db::DbState;
+
+use super::clock;
+use super::config::{self, PrinterConfig};
+use super::database;
+use super::error::PrintError;
+
+// Receipts and invoices for an order share it
+pub(crate) const BILL_SERIES: &str = "bill";
+// Each kitchen station numbers its own tickets, e.g. "kot:bar"
+const STATION_SERIES_PREFIX: &str = "kot:";
+
+pub(crate) fn station_series(station: &str) -> String {
+    format!("{}{}", STATION_SERIES_PREFIX, station.trim().to_lowercase())
+}
+
+// The stored name of `series` at this terminal's outlet; settings stay keyed by the plain name.
+fn outlet_series(series: &str, config: &PrinterConfig) -> String {
+    match &config.outlet_id {
+        Some(outlet) => format!("{}/{}", outlet, series),
+        None => series.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SequenceReset {
+    #[default]
+    Never,
+    Daily,
+    FiscalYear,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BillSeries {
+    // `{fy}` becomes the fiscal year ("2026/27") and `{date}` the day ("20261016")
+    pub prefix: String,
+    pub reset: SequenceReset,
+    // Zero-padded to this many digits; 0 leaves the number as is
+    pub digits: usize,
+}
+
+// The fiscal year `date` falls in, named by the year it starts in.
+fn fiscal_year(date: NaiveDate, config: &PrinterConfig) -> i32 {
+    let start = (config.fiscal_year_start_month, config.fiscal_year_start_day);
+    if (date.month(), date.day()) >= start {
+        date.year()
+    } else {
+        date.year() - 1
+    }
+}
+
+// Station tickets start again every day unless configured otherwise; everything else never resets.
+fn default_series(series: &str) -> BillSeries {
+    let reset = if series.starts_with(STATION_SERIES_PREFIX) { SequenceReset::Daily } else { SequenceReset::Never };
+    BillSeries { reset, ..BillSeries::default() }
+}
+
+// Which counter a number comes from; a change of period restarts the count at 1.
+fn period(series: &BillSeries, date: NaiveDate, config: &PrinterConfig) -> String {
+    match series.reset {
+        SequenceReset::Never => String::new(),
+        SequenceReset::Daily => date.format("%Y-%m-%d").to_string(),
+        SequenceReset::FiscalYear => format!("FY{}", fiscal_year(date, config)),
+    }
+}
+
+fn format_number(series: &BillSeries, number: i64, date: NaiveDate, config: &PrinterConfig) -> String {
+    let fy = fiscal_year(date, config);
+    let prefix = series
+        .prefix
+        .replace("{fy}", &format!("{}/{:02}", fy, (fy + 1) % 100))
+        .replace("{date}", &date.format("%Y%m%d").to_string());
+    format!("{}{:0width$}", prefix, number, width = series.digits)
+}
+
+// Must run inside a write transaction so two tills can't take the same number.
+fn take_next(conn: &Connection, series: &str) -> Result<String, PrintError> {
+    let config = config::current();
+    let settings = config.bill_series.get(series).cloned().unwrap_or_else(|| default_series(series));
+    let today = clock::today();
+    let period = period(&settings, today, &config);
+    let series = outlet_series(series, &config);
+    conn.execute(
+        "INSERT INTO bill_sequences (series, period, last_number) VALUES (?1, ?2, 1)
+         ON CONFLICT (series) DO UPDATE SET
+            last_number = CASE WHEN period = excluded.period THEN last_number + 1 ELSE 1 END,
+            period = excluded.period,
+            updated_at = datetime('now')",
+        params![series, period],
+    )?;
+    let number: i64 = conn.query_row("SELECT last_number FROM bill_sequences WHERE series = ?1", params![series], |row| row.get(0))?;
+    Ok(format_number(&settings, number, today, &config))
+}
+
+// The order's number in `series`, taking the next one the first time it is asked for.
+pub(crate) fn number_for_order(conn: &Connection, series: &str, order_id: i64) -> Result<String, PrintError> {
+    let stored = outlet_series(series, &config::current());
+    database::write_transaction(conn, |conn| {
+        let existing: Option<String> = conn
+            .query_row(
+                "SELECT number FROM bill_numbers WHERE series = ?1 AND order_id = ?2",
+                params![stored, order_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(number) = existing {
+            return Ok(number);
+        }
+        let number = take_next(conn, series)?;
+        conn.execute(
+            "INSERT INTO bill_numbers (series, order_id, number) VALUES (?1, ?2, ?3)",
+            params![stored, order_id, number],
+        )?;
+        Ok(number)
+    })
+}
+
+// For documents the host app numbers itself, e.g. a bill it renders; the number isn't tied to an order.
+#[tauri::command]
+pub fn next_bill_number(series: Option<String>, state: tauri::State<'_, DbState>) -> Result<String, PrintError> {
+    let conn = database::connection(&state)?;
+    let series = series.unwrap_or_else(|| BILL_SERIES.to_string());
+    database::write_transaction(&conn, |conn| take_next(conn, &series))
+}
+
+#[tauri::command]
+pub fn get_bill_number(order_id: i64, series: Option<String>, state: tauri::State<'_, DbState>) -> Result<String, PrintError> {
+    let conn = database::connection(&state)?;
+    number_for_order(&conn, series.as_deref().unwrap_or(BILL_SERIES), order_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::migrations;
+
+    fn config_with_april_fiscal_year() -> PrinterConfig {
+        PrinterConfig { fiscal_year_start_month: 4, fiscal_year_start_day: 1, ..PrinterConfig::default() }
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).expect("date")
+    }
+
+    #[test]
+    fn prefix_and_padding() {
+        let config = config_with_april_fiscal_year();
+        let series = BillSeries { prefix: "INV-{fy}-".to_string(), reset: SequenceReset::FiscalYear, digits: 4 };
+
+        assert_eq!(format_number(&series, 42, date(2026, 10, 16), &config), "INV-2026/27-0042");
+        assert_eq!(format_number(&series, 42, date(2026, 3, 31), &config), "INV-2025/26-0042");
+        let daily = BillSeries { prefix: "{date}/".to_string(), ..BillSeries::default() };
+        assert_eq!(format_number(&daily, 7, date(2026, 10, 16), &config), "20261016/7");
+    }
+
+    #[test]
+    fn periods_follow_the_reset() {
+        let config = config_with_april_fiscal_year();
+        let series = |reset| BillSeries { reset, ..BillSeries::default() };
+
+        assert_eq!(period(&series(SequenceReset::Never), date(2026, 10, 16), &config), "");
+        assert_eq!(period(&series(SequenceReset::Daily), date(2026, 10, 16), &config), "2026-10-16");
+        assert_eq!(period(&series(SequenceReset::FiscalYear), date(2027, 1, 5), &config), "FY2026");
+    }
+
+    #[test]
+    fn station_tickets_restart_daily_by_default() {
+        assert_eq!(station_series(" Bar "), "kot:bar");
+        assert_eq!(default_series(&station_series("bar")).reset, SequenceReset::Daily);
+        assert_eq!(default_series(BILL_SERIES).reset, SequenceReset::Never);
+    }
+
+    #[test]
+    fn each_outlet_counts_on_its_own() {
+        let outlet = PrinterConfig { outlet_id: Some("mg-road".to_string()), ..PrinterConfig::default() };
+
+        assert_eq!(outlet_series(BILL_SERIES, &outlet), "mg-road/bill");
+        assert_eq!(outlet_series(BILL_SERIES, &PrinterConfig::default()), "bill");
+    }
+
+    #[test]
+    fn an_order_keeps_its_number() {
+        let conn = Connection::open_in_memory().expect("database");
+        migrations::run(&conn).expect("migrate");
+
+        let first = number_for_order(&conn, "test-series", 1).expect("number");
+        let again = number_for_order(&conn, "test-series", 1).expect("number");
+        let second = number_for_order(&conn, "test-series", 2).expect("number");
+
+        assert_eq!((first.as_str(), again.as_str(), second.as_str()), ("1", "1", "2"));
+    }
+}
@@ -0,0 +1,151 @@
+use rusqlite::types::ToSqlOutput;
+use rusqlite::ToSql;
+use serde::{Deserialize, Serialize};
+use serialport::SerialPort;
+use std::time::Duration;
+
+use super::error::{ErrorCode, PrintError};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowControl {
+    #[default]
+    None,
+    // RTS/CTS
+    Hardware,
+    // XON/XOFF
+    Software,
+}
+
+impl FlowControl {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            FlowControl::None => "none",
+            FlowControl::Hardware => "hardware",
+            FlowControl::Software => "software",
+        }
+    }
+
+    pub(crate) fn parse(value: &str) -> Self {
+        match value {
+            "hardware" => FlowControl::Hardware,
+            "software" => FlowControl::Software,
+            _ => FlowControl::None,
+        }
+    }
+}
+
+impl ToSql for FlowControl {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Parity {
+    #[default]
+    None,
+    Odd,
+    Even,
+}
+
+impl Parity {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Parity::None => "none",
+            Parity::Odd => "odd",
+            Parity::Even => "even",
+        }
+    }
+
+    pub(crate) fn parse(value: &str) -> Self {
+        match value {
+            "odd" => Parity::Odd,
+            "even" => Parity::Even,
+            _ => Parity::None,
+        }
+    }
+}
+
+impl ToSql for Parity {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+// Line settings for printers on a COM port; ignored for spooled and network printers.
+// Defaults are 8N1, which nearly every receipt printer ships with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SerialSettings {
+    pub flow_control: FlowControl,
+    pub data_bits: u8,
+    pub parity: Parity,
+    pub stop_bits: u8,
+    // Slow printers without flow control drop bytes once their buffer fills; 0 writes everything at once
+    pub chunk_size: usize,
+    pub chunk_delay_ms: u64,
+}
+
+impl Default for SerialSettings {
+    fn default() -> Self {
+        SerialSettings {
+            flow_control: FlowControl::None,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: 1,
+            chunk_size: 0,
+            chunk_delay_ms: 0,
+        }
+    }
+}
+
+pub(crate) fn framing_error(settings: &SerialSettings) -> Option<String> {
+    if !(5..=8).contains(&settings.data_bits) {
+        return Some(format!("Data bits must be between 5 and 8, got {}", settings.data_bits));
+    }
+    if !matches!(settings.stop_bits, 1 | 2) {
+        return Some(format!("Stop bits must be 1 or 2, got {}", settings.stop_bits));
+    }
+    None
+}
+
+pub(crate) fn open(
+    port_name: &str,
+    baud_rate: u32,
+    settings: &SerialSettings,
+    timeout: Duration,
+) -> Result<Box<dyn SerialPort>, PrintError> {
+    if let Some(message) = framing_error(settings) {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, message));
+    }
+    let data_bits = match settings.data_bits {
+        5 => serialport::DataBits::Five,
+        6 => serialport::DataBits::Six,
+        7 => serialport::DataBits::Seven,
+        _ => serialport::DataBits::Eight,
+    };
+    let parity = match settings.parity {
+        Parity::None => serialport::Parity::None,
+        Parity::Odd => serialport::Parity::Odd,
+        Parity::Even => serialport::Parity::Even,
+    };
+    let stop_bits = match settings.stop_bits {
+        2 => serialport::StopBits::Two,
+        _ => serialport::StopBits::One,
+    };
+    let flow_control = match settings.flow_control {
+        FlowControl::None => serialport::FlowControl::None,
+        FlowControl::Hardware => serialport::FlowControl::Hardware,
+        FlowControl::Software => serialport::FlowControl::Software,
+    };
+    serialport::new(port_name, baud_rate)
+        .data_bits(data_bits)
+        .parity(parity)
+        .stop_bits(stop_bits)
+        .flow_control(flow_control)
+        .timeout(timeout)
+        .open()
+        .map_err(|e| PrintError::from_serial(&format!("Failed to open serial port {}", port_name), &e))
+}
@@ -0,0 +1,98 @@
+// Closing the app mid-rush must not eat kitchen tickets. Once shutdown starts, new jobs are held
+// instead of sent, jobs already on their way to a printer get `shutdown_grace_secs` to finish,
+// and everything left over is parked in the print queue to go out on the next start. A job cut
+// off mid-write may print twice; that beats losing it.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::db::DbState;
+
+use super::config;
+use super::database;
+use super::queue;
+use super::Destination;
+
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+static NEXT_JOB: AtomicU64 = AtomicU64::new(0);
+
+// The order is kept so the job still marks it printed once it goes out
+type Job = (Option<i64>, Destination, Vec<u8>);
+
+// Keyed by start order, so the jobs are parked in the order they were sent
+fn in_flight() -> &'static Mutex<BTreeMap<u64, Job>> {
+    static IN_FLIGHT: OnceLock<Mutex<BTreeMap<u64, Job>>> = OnceLock::new();
+    IN_FLIGHT.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+// Jobs that arrived after shutdown started
+fn held() -> &'static Mutex<Vec<Job>> {
+    static HELD: OnceLock<Mutex<Vec<Job>>> = OnceLock::new();
+    HELD.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+pub(crate) fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::Relaxed)
+}
+
+pub(crate) fn hold(order_id: Option<i64>, destination: &Destination, content: &[u8]) {
+    held().lock().unwrap_or_else(|e| e.into_inner()).push((order_id, destination.clone(), content.to_vec()));
+}
+
+// Registered while a job is being sent; dropping it marks the job finished, however it ended.
+pub(crate) struct InFlight(u64);
+
+impl InFlight {
+    pub(crate) fn start(order_id: Option<i64>, destination: &Destination, content: &[u8]) -> Self {
+        let id = NEXT_JOB.fetch_add(1, Ordering::Relaxed);
+        in_flight().lock().unwrap_or_else(|e| e.into_inner()).insert(id, (order_id, destination.clone(), content.to_vec()));
+        InFlight(id)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        in_flight().lock().unwrap_or_else(|e| e.into_inner()).remove(&self.0);
+    }
+}
+
+fn in_flight_count() -> usize {
+    in_flight().lock().unwrap_or_else(|e| e.into_inner()).len()
+}
+
+// Runs on RunEvent::Exit. Blocks the exiting thread; the jobs themselves finish on the async runtime.
+pub(crate) fn run<R: Runtime>(app: &AppHandle<R>) {
+    SHUTTING_DOWN.store(true, Ordering::Relaxed);
+    let deadline = Instant::now() + Duration::from_secs(config::current().shutdown_grace_secs);
+    while in_flight_count() > 0 && Instant::now() < deadline {
+        thread::sleep(DRAIN_POLL_INTERVAL);
+    }
+
+    let mut unfinished: Vec<Job> =
+        std::mem::take(&mut *in_flight().lock().unwrap_or_else(|e| e.into_inner())).into_values().collect();
+    unfinished.append(&mut held().lock().unwrap_or_else(|e| e.into_inner()));
+    if unfinished.is_empty() {
+        return;
+    }
+
+    warn!("Closing with {} unfinished print jobs; parking them for the next start", unfinished.len());
+    let state = app.state::<DbState>();
+    let conn = match database::connection(&state) {
+        Ok(conn) => conn,
+        Err(e) => {
+            error!("Could not park {} unfinished print jobs: {}", unfinished.len(), e);
+            return;
+        }
+    };
+    for (order_id, destination, payload) in unfinished {
+        if let Err(e) = queue::park(&conn, order_id, &destination, &payload, "app closed") {
+            error!("Could not park a job for {}: {}", destination.key(), e);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+// Texts pack-order customers their token number and total once the KOT is in the kitchen, so
+// they can wait outside instead of crowding the counter. Providers differ wildly, so the gateway
+// is described in the settings (a URL, an optional JSON body and headers) rather than coded per
+// provider.
+
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use crate::db::DbState;
+
+use super::config::{self, SmsGateway};
+use super::database;
+use super::error::{ErrorCode, PrintError};
+use super::fetch_order_details;
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(15);
+
+// Everything but unreserved characters, as query strings need.
+fn url_encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{:02X}", byte);
+        }
+    }
+    encoded
+}
+
+// The body template already has the quotes around each placeholder.
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::Value::String(value.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+fn fill(template: &str, to: &str, message: &str, escape: fn(&str) -> String) -> String {
+    template.replace("{{to}}", &escape(to)).replace("{{message}}", &escape(message))
+}
+
+async fn send(gateway: &SmsGateway, to: &str, message: &str) -> Result<(), PrintError> {
+    let failed = |e: String| PrintError::new(ErrorCode::ConnectionFailed, format!("SMS gateway request failed: {}", e));
+    let client = reqwest::Client::builder().timeout(SEND_TIMEOUT).build().map_err(|e| failed(e.to_string()))?;
+    let url = fill(&gateway.url, to, message, url_encode);
+    let mut request = match &gateway.body {
+        Some(body) => client.post(url).header("Content-Type", "application/json").body(fill(body, to, message, json_escape)),
+        None => client.get(url),
+    };
+    for (name, value) in &gateway.headers {
+        request = request.header(name, value);
+    }
+    let response = request.send().await.map_err(|e| failed(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(failed(format!("the gateway answered {}", response.status())));
+    }
+    Ok(())
+}
+
+// After a KOT goes out. Dine-in orders are skipped; their food comes to the table.
+pub(crate) async fn notify_pack_order(state: &DbState, order_id: i64, phone: &str) {
+    let Some(gateway) = config::current().sms else {
+        return;
+    };
+    let phone = phone.trim().to_string();
+    if phone.is_empty() {
+        return;
+    }
+    let order = match database::run(state, move |conn| Ok(fetch_order_details(conn, order_id)?)).await {
+        Ok(order) => order,
+        Err(e) => {
+            error!("Could not load order {} for its SMS: {}", order_id, e);
+            return;
+        }
+    };
+    if order.has_table {
+        return;
+    }
+
+    let message = gateway
+        .message
+        .replace("{{token}}", &order.order_number)
+        .replace("{{total}}", &format!("{:.2}", order.total_amount));
+    // The gateway can be slow; the cashier shouldn't wait on it
+    tauri::async_runtime::spawn(async move {
+        match send(&gateway, &phone, &message).await {
+            Ok(()) => info!("Texted order {} token to the customer", order_id),
+            Err(e) => warn!("SMS for order {} failed: {}", order_id, e),
+        }
+    });
+}
@@ -0,0 +1,169 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::db::DbState;
+
+use super::config;
+use super::error::{ErrorCode, PrintError};
+use super::letterhead;
+use super::DocumentType;
+
+const OPEN: &[u8] = b"{{";
+const CLOSE: &[u8] = b"}}";
+
+// Snippets every ESC/POS printer understands; entries in the config override these by name.
+const BUILT_IN: [(&str, &[u8]); 12] = [
+    ("init", b"\x1B\x40"),
+    ("open_drawer", b"\x1B\x70\x00\x19\xFA"),
+    ("open_drawer_2", b"\x1B\x70\x01\x19\xFA"),
+    ("cut", b"\x1D\x56\x00"),
+    ("partial_cut", b"\x1D\x56\x01"),
+    ("bold_on", b"\x1B\x45\x01"),
+    ("bold_off", b"\x1B\x45\x00"),
+    ("double_strike_on", b"\x1B\x47\x01"),
+    ("double_strike_off", b"\x1B\x47\x00"),
+    ("underline_on", b"\x1B\x2D\x01"),
+    ("underline_off", b"\x1B\x2D\x00"),
+    ("feed", b"\x0A"),
+];
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+enum Tag<'a> {
+    Snippet(&'a str),
+    // The configured footer for the job's document type
+    Footer,
+}
+
+// `cmd "open_drawer"` -> the `open_drawer` snippet, `footer` -> the footer; anything else isn't ours to touch.
+fn parse_tag(tag: &[u8]) -> Option<Tag<'_>> {
+    let tag = std::str::from_utf8(tag).ok()?.trim();
+    if tag == "footer" {
+        return Some(Tag::Footer);
+    }
+    tag.strip_prefix("cmd ")?.trim().strip_prefix('"')?.strip_suffix('"').map(Tag::Snippet)
+}
+
+// Space-separated hex bytes, e.g. "1B 70 00 19 FA".
+pub(crate) fn parse_hex(value: &str) -> Result<Vec<u8>, String> {
+    value
+        .split_whitespace()
+        .map(|byte| u8::from_str_radix(byte, 16).map_err(|_| format!("'{}' is not a hex byte", byte)))
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+}
+
+fn resolve(name: &str, custom: &HashMap<String, String>) -> Result<Vec<u8>, PrintError> {
+    if let Some(hex) = custom.get(name) {
+        return parse_hex(hex)
+            .map_err(|e| PrintError::new(ErrorCode::InvalidSettings, format!("Command snippet {} is invalid: {}", name, e)));
+    }
+    BUILT_IN
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, bytes)| bytes.to_vec())
+        .ok_or_else(|| PrintError::new(ErrorCode::InvalidContent, format!("Unknown command snippet \"{}\"", name)))
+}
+
+// Replaces every `{{cmd "name"}}` in the payload with that snippet's bytes, and `{{footer}}` with
+// the footer set for `document_type` (a KOT's when unknown), or nothing when it has none.
+pub(crate) fn expand(content: &[u8], document_type: Option<DocumentType>) -> Result<Vec<u8>, PrintError> {
+    if find(content, OPEN).is_none() {
+        return Ok(content.to_vec());
+    }
+
+    let config = config::current();
+    let custom = &config.command_snippets;
+    let mut expanded = Vec::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = find(rest, OPEN) {
+        let Some(length) = find(&rest[start..], CLOSE) else {
+            break;
+        };
+        let end = start + length + CLOSE.len();
+        match parse_tag(&rest[start + OPEN.len()..start + length]) {
+            Some(Tag::Snippet(name)) => {
+                expanded.extend_from_slice(&rest[..start]);
+                expanded.extend(resolve(name, custom)?);
+            }
+            Some(Tag::Footer) => {
+                expanded.extend_from_slice(&rest[..start]);
+                if let Some(footer) = letterhead::footer(&config, document_type.unwrap_or_default()) {
+                    expanded.extend_from_slice(footer.as_bytes());
+                    expanded.push(b'\n');
+                }
+            }
+            None => expanded.extend_from_slice(&rest[..end]),
+        }
+        rest = &rest[end..];
+    }
+    expanded.extend_from_slice(rest);
+    Ok(expanded)
+}
+
+// Built-ins merged with the configured overrides, as hex for the settings UI.
+#[tauri::command]
+pub fn list_command_snippets() -> BTreeMap<String, String> {
+    let mut snippets: BTreeMap<String, String> =
+        BUILT_IN.iter().map(|(name, bytes)| (name.to_string(), to_hex(bytes))).collect();
+    snippets.extend(config::current().command_snippets);
+    snippets
+}
+
+// `None` removes the override so a built-in of the same name applies again.
+#[tauri::command]
+pub fn set_command_snippet(name: String, hex: Option<String>, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    if name.trim().is_empty() || name.contains('"') {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, "Snippet names cannot be empty or contain quotes"));
+    }
+    if let Some(hex) = &hex {
+        parse_hex(hex).map_err(|e| PrintError::new(ErrorCode::InvalidSettings, e))?;
+    }
+    config::update_persisted(&state, |config| match hex {
+        Some(hex) => {
+            config.command_snippets.insert(name, hex);
+        }
+        None => {
+            config.command_snippets.remove(&name);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_without_tags_is_unchanged() {
+        assert_eq!(expand(b"plain \x1B\x40 text", None).expect("expand"), b"plain \x1B\x40 text");
+    }
+
+    #[test]
+    fn snippets_are_replaced_by_their_bytes() {
+        let out = expand(b"a{{cmd \"bold_on\"}}b{{ cmd \"cut\" }}", None).expect("expand");
+
+        assert_eq!(out, b"a\x1B\x45\x01b\x1D\x56\x00");
+    }
+
+    #[test]
+    fn other_braces_are_left_alone() {
+        assert_eq!(expand(b"{{name}} and {{cmd \"cut\"", None).expect("expand"), b"{{name}} and {{cmd \"cut\"");
+    }
+
+    #[test]
+    fn unknown_snippet_is_an_error() {
+        let error = expand(b"{{cmd \"confetti\"}}", None).expect_err("unknown snippet");
+
+        assert_eq!(error.code, ErrorCode::InvalidContent);
+    }
+
+    #[test]
+    fn hex_is_space_separated_bytes() {
+        assert_eq!(parse_hex("1B 70 00 19 FA"), Ok(vec![0x1B, 0x70, 0x00, 0x19, 0xFA]));
+        assert!(parse_hex("1B 7G").is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use snmp::{SyncSession, Value};
+use std::time::Duration;
+
+use super::error::{ErrorCode, PrintError};
+use super::status::PrinterStatus;
+
+const SNMP_PORT: u16 = 161;
+const SNMP_TIMEOUT: Duration = Duration::from_secs(2);
+
+// HOST-RESOURCES-MIB, first device
+const HR_DEVICE_STATUS: &[u32] = &[1, 3, 6, 1, 2, 1, 25, 3, 2, 1, 5, 1];
+const HR_PRINTER_DETECTED_ERROR_STATE: &[u32] = &[1, 3, 6, 1, 2, 1, 25, 3, 5, 1, 2, 1];
+
+const DEVICE_DOWN: i64 = 5;
+
+// hrPrinterDetectedErrorState bits, most significant bit of the first byte is bit 0
+const LOW_PAPER: u16 = 0x8000;
+const NO_PAPER: u16 = 0x4000;
+const DOOR_OPEN: u16 = 0x0800;
+const JAMMED: u16 = 0x0400;
+const OFFLINE: u16 = 0x0200;
+const SERVICE_REQUESTED: u16 = 0x0100;
+const INPUT_TRAY_EMPTY: u16 = 0x0004;
+
+fn snmp_error(context: &str, e: snmp::SnmpError) -> PrintError {
+    let code = match e {
+        snmp::SnmpError::ReceiveError | snmp::SnmpError::SendError => ErrorCode::Timeout,
+        _ => ErrorCode::Unknown,
+    };
+    PrintError::new(code, format!("{}: {:?}", context, e))
+}
+
+fn status_from_mib(device_status: i64, error_state: &[u8]) -> PrinterStatus {
+    let bits = u16::from_be_bytes([
+        error_state.first().copied().unwrap_or(0),
+        error_state.get(1).copied().unwrap_or(0),
+    ]);
+    PrinterStatus {
+        online: device_status != DEVICE_DOWN && bits & OFFLINE == 0,
+        cover_open: bits & DOOR_OPEN != 0,
+        paper_out: bits & (NO_PAPER | INPUT_TRAY_EMPTY) != 0,
+        paper_near_end: bits & LOW_PAPER != 0,
+        cutter_error: bits & JAMMED != 0,
+        unrecoverable_error: bits & SERVICE_REQUESTED != 0,
+        // SNMP has no view of the drawer kick connector
+        drawer_open: false,
+    }
+}
+
+fn query_blocking(agent: String, community: String) -> Result<PrinterStatus, PrintError> {
+    let mut session = SyncSession::new(agent.as_str(), community.as_bytes(), Some(SNMP_TIMEOUT), 0)
+        .map_err(|e| PrintError::from_io(&format!("Could not open SNMP session to {}", agent), &e))?;
+
+    let device_status = match session.get(HR_DEVICE_STATUS).map_err(|e| snmp_error("hrDeviceStatus", e))?.varbinds.next() {
+        Some((_, Value::Integer(status))) => status,
+        _ => return Err(PrintError::new(ErrorCode::Unknown, format!("{} did not report hrDeviceStatus", agent))),
+    };
+    let error_state = match session
+        .get(HR_PRINTER_DETECTED_ERROR_STATE)
+        .map_err(|e| snmp_error("hrPrinterDetectedErrorState", e))?
+        .varbinds
+        .next()
+    {
+        Some((_, Value::OctetString(bytes))) => bytes.to_vec(),
+        _ => Vec::new(),
+    };
+
+    Ok(status_from_mib(device_status, &error_state))
+}
+
+// `address` is the printer's host:port for printing; SNMP always talks to port 161 on the same host.
+pub(crate) async fn query_status(address: &str, community: &str) -> Result<PrinterStatus, PrintError> {
+    let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+    let agent = format!("{}:{}", host, SNMP_PORT);
+    let community = community.to_string();
+    tokio::task::spawn_blocking(move || query_blocking(agent, community))
+        .await
+        .map_err(|e| PrintError::new(ErrorCode::Unknown, format!("SNMP task failed: {}", e)))?
+}
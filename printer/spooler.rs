@@ -0,0 +1,180 @@
+// The Windows print spooler. Elsewhere there are no spooled queues: the queue commands fail with a
+// clear error and printer listings come back empty, so USB printing falls through to serial ports.
+
+#[cfg(windows)]
+use std::time::{Duration, Instant};
+#[cfg(windows)]
+use windows::Win32::Graphics::Printing::{
+    JOB_STATUS_BLOCKED_DEVQ, JOB_STATUS_COMPLETE, JOB_STATUS_DELETED, JOB_STATUS_ERROR, JOB_STATUS_OFFLINE,
+    JOB_STATUS_PAPEROUT, JOB_STATUS_PRINTED, JOB_STATUS_USER_INTERVENTION, PRINTER_CONTROL_PAUSE,
+    PRINTER_CONTROL_PURGE, PRINTER_CONTROL_RESUME, PRINTER_STATUS_PAUSED,
+};
+
+#[cfg(windows)]
+use super::error::ErrorCode;
+use super::error::PrintError;
+#[cfg(windows)]
+use super::win32::{self, Printer};
+
+#[cfg(windows)]
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy)]
+enum QueueControl {
+    Pause,
+    Resume,
+    Purge,
+}
+
+#[cfg(windows)]
+enum JobState {
+    Pending,
+    Printed,
+    Failed(PrintError),
+}
+
+#[cfg(windows)]
+fn control_printer(printer_name: &str, control: QueueControl) -> Result<(), PrintError> {
+    let (command, action) = match control {
+        QueueControl::Pause => (PRINTER_CONTROL_PAUSE, "SetPrinter(pause)"),
+        QueueControl::Resume => (PRINTER_CONTROL_RESUME, "SetPrinter(resume)"),
+        QueueControl::Purge => (PRINTER_CONTROL_PURGE, "SetPrinter(purge)"),
+    };
+    Printer::open_for_admin(printer_name)?.control(command, action)
+}
+
+#[cfg(windows)]
+fn job_state(printer_name: &str, job_id: u32) -> Result<JobState, PrintError> {
+    // A job that has left the queue has printed
+    let Some(status) = Printer::open(Some(printer_name))?.job_status(job_id)? else {
+        return Ok(JobState::Printed);
+    };
+
+    let failed = |code, message: &str| Ok(JobState::Failed(PrintError::new(code, format!("Job {}: {}", job_id, message))));
+    if status & (JOB_STATUS_PRINTED | JOB_STATUS_COMPLETE) != 0 {
+        Ok(JobState::Printed)
+    } else if status & JOB_STATUS_PAPEROUT != 0 {
+        failed(ErrorCode::PaperOut, "printer is out of paper")
+    } else if status & JOB_STATUS_OFFLINE != 0 {
+        failed(ErrorCode::ConnectionFailed, "printer is offline")
+    } else if status & JOB_STATUS_DELETED != 0 {
+        failed(ErrorCode::SpoolerError, "job was deleted")
+    } else if status & (JOB_STATUS_ERROR | JOB_STATUS_USER_INTERVENTION | JOB_STATUS_BLOCKED_DEVQ) != 0 {
+        failed(ErrorCode::SpoolerError, "spooler reported an error")
+    } else {
+        Ok(JobState::Pending)
+    }
+}
+
+#[cfg(windows)]
+fn queue_paused(printer_name: &str) -> bool {
+    Printer::open(Some(printer_name))
+        .and_then(|printer| printer.status())
+        .is_ok_and(|status| status & PRINTER_STATUS_PAUSED.0 != 0)
+}
+
+#[cfg(windows)]
+fn cancel_job(printer_name: &str, job_id: u32) {
+    if let Err(e) = Printer::open(Some(printer_name)).and_then(|printer| printer.cancel_job(job_id)) {
+        error!("Could not cancel job {} on {}: {}", job_id, printer_name, e);
+    }
+}
+
+// Polls the spooler until the job prints or fails. Failed and timed-out jobs are cancelled so
+// they can't come out of the printer minutes later after the ticket was sent elsewhere.
+#[cfg(windows)]
+pub(crate) async fn wait_for_job(printer_name: &str, job_id: u32, timeout: Duration) -> Result<(), PrintError> {
+    let started = Instant::now();
+    loop {
+        match job_state(printer_name, job_id)? {
+            JobState::Printed => return Ok(()),
+            JobState::Failed(e) => {
+                cancel_job(printer_name, job_id);
+                return Err(e);
+            }
+            // Nothing prints while the queue is paused, so the printer counts as offline. The job is
+            // taken back out so it can't print on resume after it went to a fallback or the offline queue
+            JobState::Pending if queue_paused(printer_name) => {
+                cancel_job(printer_name, job_id);
+                return Err(PrintError::new(
+                    ErrorCode::ConnectionFailed,
+                    format!("The queue of {} is paused; job {} was not printed", printer_name, job_id),
+                ));
+            }
+            JobState::Pending if started.elapsed() >= timeout => {
+                cancel_job(printer_name, job_id);
+                return Err(PrintError::new(
+                    ErrorCode::Timeout,
+                    format!("Job {} on {} did not print within {}s", job_id, printer_name, timeout.as_secs()),
+                ));
+            }
+            JobState::Pending => tokio::time::sleep(JOB_POLL_INTERVAL).await,
+        }
+    }
+}
+
+// Names of every printer the Windows spooler knows about, local and network connections.
+#[cfg(windows)]
+pub(crate) fn installed_printers() -> Result<Vec<String>, PrintError> {
+    win32::installed_printers()
+}
+
+#[cfg(not(windows))]
+fn control_printer(printer_name: &str, control: QueueControl) -> Result<(), PrintError> {
+    Err(PrintError::windows_only(&format!("{:?} for the {} queue", control, printer_name)))
+}
+
+#[cfg(not(windows))]
+pub(crate) fn installed_printers() -> Result<Vec<String>, PrintError> {
+    Ok(Vec::new())
+}
+
+// Drops every job in the Windows queue, e.g. after a jam left stale tickets waiting.
+#[tauri::command]
+pub fn purge_printer_queue(printer_name: String) -> Result<(), PrintError> {
+    control_printer(&printer_name, QueueControl::Purge)?;
+    warn!("Print queue purged for {}", printer_name);
+    Ok(())
+}
+
+// Holds jobs in the Windows queue (e.g. during a paper change) until resumed.
+#[tauri::command]
+pub fn pause_printer_queue(printer_name: String) -> Result<(), PrintError> {
+    control_printer(&printer_name, QueueControl::Pause)?;
+    info!("Print queue paused for {}", printer_name);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn resume_printer_queue(printer_name: String) -> Result<(), PrintError> {
+    control_printer(&printer_name, QueueControl::Resume)?;
+    info!("Print queue resumed for {}", printer_name);
+    Ok(())
+}
+
+// `None` when Windows has no default printer set.
+#[cfg(windows)]
+#[tauri::command]
+pub fn get_default_printer() -> Result<Option<String>, PrintError> {
+    win32::default_printer()
+}
+
+#[cfg(not(windows))]
+#[tauri::command]
+pub fn get_default_printer() -> Result<Option<String>, PrintError> {
+    Ok(None)
+}
+
+#[cfg(windows)]
+#[tauri::command]
+pub fn set_default_printer(printer_name: String) -> Result<(), PrintError> {
+    win32::set_default_printer(&printer_name)?;
+    info!("Windows default printer set to {}", printer_name);
+    Ok(())
+}
+
+#[cfg(not(windows))]
+#[tauri::command]
+pub fn set_default_printer(printer_name: String) -> Result<(), PrintError> {
+    Err(PrintError::windows_only(&format!("Making {} the default printer", printer_name)))
+}
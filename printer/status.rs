@@ -0,0 +1,345 @@
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time::timeout;
+
+use crate::db::DbState;
+
+use super::config;
+use super::database;
+use super::driver;
+use super::error::{ErrorCode, PrintError};
+use super::pool;
+use super::profile::{self, PrinterProfile, Transport};
+use super::serial;
+use super::snmp;
+use super::{lock_destination, Destination};
+
+const STATUS_TIMEOUT: Duration = Duration::from_secs(2);
+
+// DLE EOT n: real-time status transmission
+const PRINTER_STATUS: u8 = 1;
+const OFFLINE_CAUSE: u8 = 2;
+const ERROR_CAUSE: u8 = 3;
+const PAPER_SENSOR: u8 = 4;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrinterStatus {
+    pub online: bool,
+    pub cover_open: bool,
+    pub paper_out: bool,
+    pub paper_near_end: bool,
+    pub cutter_error: bool,
+    pub unrecoverable_error: bool,
+    pub drawer_open: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DrawerStatus {
+    pub open: bool,
+}
+
+// Bit 2 of DLE EOT 1 mirrors drawer kick-out connector pin 3.
+fn drawer_open(printer: u8) -> bool {
+    let pin_high = printer & 0x04 != 0;
+    pin_high == config::current().drawer_open_when_pin_high
+}
+
+impl PrinterStatus {
+    fn from_bytes(printer: u8, offline: u8, error: u8, paper: u8) -> Self {
+        PrinterStatus {
+            online: printer & 0x08 == 0,
+            drawer_open: drawer_open(printer),
+            cover_open: offline & 0x04 != 0,
+            paper_out: offline & 0x20 != 0 || paper & 0x60 != 0,
+            paper_near_end: paper & 0x0C != 0,
+            cutter_error: error & 0x08 != 0,
+            unrecoverable_error: error & 0x20 != 0,
+        }
+    }
+}
+
+pub(crate) fn is_serial_port(name: &str) -> bool {
+    serialport::available_ports()
+        .map(|ports| ports.iter().any(|p| p.port_name.eq_ignore_ascii_case(name)))
+        .unwrap_or(false)
+}
+
+pub(crate) fn is_serial_destination(destination: &Destination) -> bool {
+    matches!(destination, Destination::Usb { port, .. } if is_serial_port(port))
+}
+
+// Spooled Windows printers are write-only from our side.
+pub(crate) fn supports_status(destination: &Destination) -> bool {
+    match destination {
+        Destination::Network { .. } | Destination::Virtual { .. } => true,
+        Destination::Ipp { .. } | Destination::Lpr { .. } => false,
+        Destination::Usb { port, .. } => is_serial_port(port),
+        Destination::Driver { driver, .. } => driver::supports_status(driver),
+    }
+}
+
+// Every status byte has bits 1 and 4 set and bits 0 and 7 clear.
+fn check_status_byte(byte: u8) -> Result<u8, PrintError> {
+    if byte & 0x93 == 0x12 {
+        Ok(byte)
+    } else {
+        Err(PrintError::new(ErrorCode::Unknown, format!("Unexpected status byte 0x{:02X}", byte)))
+    }
+}
+
+// Sends DLE EOT n for each `n` over a single connection and returns the answers in order.
+pub(crate) async fn query_status_bytes(destination: &Destination, queries: &[u8]) -> Result<Vec<u8>, PrintError> {
+    let mut responses = Vec::with_capacity(queries.len());
+    // Simulated printers are always online with paper; failures come from the job itself
+    if config::current().simulation_mode {
+        return Ok(queries.iter().map(|_| 0x12).collect());
+    }
+    let _guard = lock_destination(destination.key()).await;
+
+    match destination {
+        Destination::Network { address } => {
+            // Shares the print connection; many printers accept only one socket at a time
+            let (mut stream, _) = pool::checkout(address, STATUS_TIMEOUT).await?;
+            for n in queries {
+                let mut response = [0u8; 1];
+                stream.write_all(&[0x10, 0x04, *n]).await.map_err(|e| PrintError::from_io("Write failed", &e))?;
+                match timeout(STATUS_TIMEOUT, stream.read_exact(&mut response)).await {
+                    Ok(Ok(_)) => responses.push(check_status_byte(response[0])?),
+                    Ok(Err(e)) => return Err(PrintError::from_io("Status read failed", &e)),
+                    Err(_) => return Err(PrintError::new(ErrorCode::Timeout, format!("{} did not answer the status query", address))),
+                }
+            }
+            pool::checkin(address, stream);
+        }
+        Destination::Usb { port, baud_rate, serial: settings, .. } if is_serial_port(port) => {
+            let mut serial = serial::open(port, *baud_rate, settings, STATUS_TIMEOUT)?;
+            for n in queries {
+                let mut response = [0u8; 1];
+                serial.write_all(&[0x10, 0x04, *n])
+                    .map_err(|e| PrintError::from_io(&format!("Failed to write to port {}", port), &e))?;
+                serial.read_exact(&mut response)
+                    .map_err(|e| PrintError::from_io(&format!("{} did not answer the status query", port), &e))?;
+                responses.push(check_status_byte(response[0])?);
+            }
+        }
+        Destination::Usb { port, .. } => {
+            return Err(PrintError::new(
+                ErrorCode::InvalidSettings,
+                format!("{} is a spooled printer; status queries need a serial or network connection", port),
+            ))
+        }
+        // Always online with paper, so status polling and preflight have something to read
+        Destination::Virtual { .. } => responses.extend(queries.iter().map(|_| 0x12)),
+        Destination::Driver { driver, address } => {
+            for byte in driver::query_status(driver, address, queries, STATUS_TIMEOUT).await? {
+                responses.push(check_status_byte(byte)?);
+            }
+        }
+        Destination::Ipp { url: address } | Destination::Lpr { address } => {
+            return Err(PrintError::new(
+                ErrorCode::InvalidSettings,
+                format!("{} is a print server queue; status queries need a raw network connection", address),
+            ))
+        }
+    }
+
+    Ok(responses)
+}
+
+// What an identification request answers with: one byte, or a block of `_`, text and NUL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Reply {
+    Byte,
+    Block,
+}
+
+// Longest block a printer is expected to send; anything longer is cut off
+const MAX_BLOCK: usize = 80;
+
+// Feeds one received byte into `reply`; true once the reply is complete.
+fn take_byte(reply: Reply, bytes: &mut Vec<u8>, byte: u8) -> bool {
+    match reply {
+        Reply::Byte => {
+            bytes.push(byte);
+            true
+        }
+        Reply::Block if byte == 0x00 => true,
+        // The `_` header isn't part of the text
+        Reply::Block if byte == b'_' && bytes.is_empty() => false,
+        Reply::Block => {
+            bytes.push(byte);
+            bytes.len() >= MAX_BLOCK
+        }
+    }
+}
+
+// Sends each request over one connection and returns the raw answers in order, for queries such
+// as GS I that aren't DLE EOT. Only raw network and serial connections can answer.
+pub(crate) async fn exchange(destination: &Destination, requests: &[(&[u8], Reply)]) -> Result<Vec<Vec<u8>>, PrintError> {
+    let _guard = lock_destination(destination.key()).await;
+    let mut replies = Vec::with_capacity(requests.len());
+
+    match destination {
+        Destination::Network { address } => {
+            let (mut stream, _) = pool::checkout(address, STATUS_TIMEOUT).await?;
+            for (request, reply) in requests {
+                stream.write_all(request).await.map_err(|e| PrintError::from_io("Write failed", &e))?;
+                let mut bytes = Vec::new();
+                let mut byte = [0u8; 1];
+                loop {
+                    match timeout(STATUS_TIMEOUT, stream.read_exact(&mut byte)).await {
+                        Ok(Ok(_)) => {}
+                        Ok(Err(e)) => return Err(PrintError::from_io("Read failed", &e)),
+                        Err(_) => return Err(PrintError::new(ErrorCode::Timeout, format!("{} did not answer", address))),
+                    }
+                    if take_byte(*reply, &mut bytes, byte[0]) {
+                        break;
+                    }
+                }
+                replies.push(bytes);
+            }
+            pool::checkin(address, stream);
+        }
+        Destination::Usb { port, baud_rate, serial: settings, .. } if is_serial_port(port) => {
+            let mut serial = serial::open(port, *baud_rate, settings, STATUS_TIMEOUT)?;
+            for (request, reply) in requests {
+                serial.write_all(request)
+                    .map_err(|e| PrintError::from_io(&format!("Failed to write to port {}", port), &e))?;
+                let mut bytes = Vec::new();
+                let mut byte = [0u8; 1];
+                loop {
+                    serial.read_exact(&mut byte)
+                        .map_err(|e| PrintError::from_io(&format!("{} did not answer", port), &e))?;
+                    if take_byte(*reply, &mut bytes, byte[0]) {
+                        break;
+                    }
+                }
+                replies.push(bytes);
+            }
+        }
+        _ => {
+            return Err(PrintError::new(
+                ErrorCode::InvalidSettings,
+                format!("{} can't be queried; identification needs a raw network or serial connection", destination.key()),
+            ))
+        }
+    }
+
+    Ok(replies)
+}
+
+pub(crate) async fn query_status_byte(destination: &Destination, n: u8) -> Result<u8, PrintError> {
+    Ok(query_status_bytes(destination, &[n]).await?[0])
+}
+
+// Returns the reason not to send a job right now, if the printer gives one. An unreachable
+// printer is let through so the transport reports its own error.
+pub(crate) async fn preflight_check(destination: &Destination) -> Option<PrintError> {
+    if !supports_status(destination) {
+        return None;
+    }
+    match query_status(destination).await {
+        Ok(status) if status.cover_open => Some(PrintError::new(ErrorCode::CoverOpen, format!("{} cover is open", destination.key()))),
+        Ok(status) if status.paper_out => Some(PrintError::new(ErrorCode::PaperOut, format!("{} is out of paper", destination.key()))),
+        Ok(_) => None,
+        Err(e) => {
+            debug!("Preflight status check for {} failed: {}", destination.key(), e);
+            None
+        }
+    }
+}
+
+// When a write fails, ask the printer why so paper-out doesn't surface as a generic transport error.
+pub(crate) async fn diagnose_failure(destination: &Destination, error: PrintError) -> PrintError {
+    if error.code == ErrorCode::PaperOut || !supports_status(destination) {
+        return error;
+    }
+    match query_status(destination).await {
+        Ok(status) if status.paper_out => PrintError::new(ErrorCode::PaperOut, format!("Printer is out of paper ({})", error.message)),
+        _ => error,
+    }
+}
+
+pub(crate) async fn query_status(destination: &Destination) -> Result<PrinterStatus, PrintError> {
+    let bytes = query_status_bytes(destination, &[PRINTER_STATUS, OFFLINE_CAUSE, ERROR_CAUSE, PAPER_SENSOR]).await?;
+    Ok(PrinterStatus::from_bytes(bytes[0], bytes[1], bytes[2], bytes[3]))
+}
+
+// Network printers with an SNMP community use the Printer MIB; everything else speaks DLE EOT.
+pub(crate) async fn query_profile_status(printer: &PrinterProfile) -> Result<PrinterStatus, PrintError> {
+    match (&printer.snmp_community, printer.transport) {
+        (Some(community), Transport::Network) if !community.is_empty() => snmp::query_status(&printer.address, community).await,
+        _ => query_status(&printer.destination()).await,
+    }
+}
+
+pub(crate) fn profile_supports_status(printer: &PrinterProfile) -> bool {
+    printer.transport == Transport::Network || supports_status(&printer.destination())
+}
+
+async fn load_profile(state: &DbState, printer_id: i64) -> Result<PrinterProfile, PrintError> {
+    database::run(state, move |conn| profile::get(conn, printer_id)).await
+}
+
+async fn profile_destination(state: &DbState, printer_id: i64) -> Result<Destination, PrintError> {
+    Ok(load_profile(state, printer_id).await?.destination())
+}
+
+#[tauri::command]
+pub async fn get_printer_status(printer_id: i64, state: tauri::State<'_, DbState>) -> Result<PrinterStatus, PrintError> {
+    let printer = load_profile(&state, printer_id).await?;
+    query_profile_status(&printer).await
+}
+
+#[tauri::command]
+pub async fn get_drawer_status(printer_id: i64, state: tauri::State<'_, DbState>) -> Result<DrawerStatus, PrintError> {
+    let destination = profile_destination(&state, printer_id).await?;
+    let printer = query_status_byte(&destination, PRINTER_STATUS).await?;
+    Ok(DrawerStatus { open: drawer_open(printer) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_bytes_have_their_fixed_bits() {
+        assert_eq!(check_status_byte(0x12).ok(), Some(0x12));
+        assert_eq!(check_status_byte(0x7E).ok(), Some(0x7E));
+        assert!(check_status_byte(0x00).is_err());
+        assert!(check_status_byte(0x92).is_err());
+    }
+
+    #[test]
+    fn idle_printer_reports_nothing_wrong() {
+        let status = PrinterStatus::from_bytes(0x12, 0x12, 0x12, 0x12);
+
+        assert!(status.online);
+        assert!(!status.cover_open && !status.paper_out && !status.paper_near_end);
+        assert!(!status.cutter_error && !status.unrecoverable_error);
+    }
+
+    #[test]
+    fn fault_bits_are_decoded() {
+        // Offline; cover open and paper out; cutter error; paper near its end
+        let status = PrinterStatus::from_bytes(0x1A, 0x36, 0x1A, 0x1E);
+
+        assert!(!status.online);
+        assert!(status.cover_open);
+        assert!(status.paper_out);
+        assert!(status.cutter_error);
+        assert!(!status.unrecoverable_error);
+        assert!(status.paper_near_end);
+    }
+
+    #[test]
+    fn block_replies_drop_the_header_and_end_at_nul() {
+        let mut bytes = Vec::new();
+        let done: Vec<bool> = b"_TM\0".iter().map(|byte| take_byte(Reply::Block, &mut bytes, *byte)).collect();
+
+        assert_eq!(done, vec![false, false, false, true]);
+        assert_eq!(bytes, b"TM");
+    }
+}
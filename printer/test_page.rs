@@ -0,0 +1,185 @@
+
+use crate::db::DbState;
+
+use super::clock;
+use super::config;
+use super::database;
+use super::error::PrintError;
+use super::escpos::{Align, EscPosBuilder, Font};
+use super::profile::{self, PrinterProfile};
+use super::tspl::TsplBuilder;
+use super::vendor::{self, Protocol};
+use super::{layout, print_to_destination};
+
+async fn load_profile(state: &DbState, printer_id: i64) -> Result<PrinterProfile, PrintError> {
+    database::run(state, move |conn| profile::get(conn, printer_id)).await
+}
+
+// Sends straight to the printer, skipping fallback routing, since the point is to test this one.
+// The profile's layout still applies so the page shows its init sequence and spacing at work.
+async fn send(profile: &PrinterProfile, bytes: Vec<u8>) -> Result<(), PrintError> {
+    print_to_destination(&layout::apply(&bytes, profile), &profile.destination(), profile.print_timeout(), None).await
+}
+
+fn test_page(profile: &PrinterProfile) -> Vec<u8> {
+    let width = profile.paper_width as usize;
+    let mut page = EscPosBuilder::for_vendor(vendor::commands(profile.vendor_profile));
+
+    page.code_page(profile.code_page)
+        .align(Align::Center)
+        .bold(true)
+        .size(2, 2)
+        .line("TEST PAGE")
+        .size(1, 1)
+        .bold(false)
+        .line(&profile.name)
+        .line(&clock::now().format("%Y-%m-%d %I:%M:%S %p").to_string())
+        .align(Align::Left)
+        .separator('-', width)
+        .line("Normal text")
+        .bold(true)
+        .line("Bold text")
+        .bold(false)
+        .underline(true)
+        .line("Underlined text")
+        .underline(false)
+        .size(2, 1)
+        .line("Double width")
+        .size(1, 2)
+        .line("Double height")
+        .size(2, 2)
+        .line("Double size")
+        .size(1, 1)
+        .separator('-', width)
+        .align(Align::Left)
+        .line("Left aligned")
+        .align(Align::Center)
+        .line("Centered")
+        .align(Align::Right)
+        .line("Right aligned")
+        .align(Align::Left)
+        .separator('-', width)
+        .line(&format!("Code page: {}", profile.code_page))
+        .raw(&code_page_sample(width))
+        .line(&format!("Paper width: {} characters", width))
+        .separator('-', width)
+        .align(Align::Center)
+        .qr(&format!("TEST {}", profile.name), 6)
+        .feed(1)
+        .barcode_code128("12345678", 80)
+        .feed(1)
+        .line("End of test page")
+        .align(Align::Left)
+        .feed(3)
+        .cut();
+
+    page.build()
+}
+
+// The upper half of the active code page (0x80-0xFF), one paper width per line.
+fn code_page_sample(width: usize) -> Vec<u8> {
+    let mut sample = Vec::new();
+    for row in (0x80..=0xFFu8).collect::<Vec<_>>().chunks(width.max(1)) {
+        sample.extend_from_slice(row);
+        sample.push(b'\n');
+    }
+    sample
+}
+
+// TSPL printers get a label instead, sized like the pack labels.
+fn test_label(profile: &PrinterProfile) -> Vec<u8> {
+    let config = config::current();
+    let mut label = TsplBuilder::new(config.label_width_mm, config.label_height_mm, config.label_gap_mm);
+    label
+        .text(16, 16, "3", 1, "TEST LABEL")
+        .text(16, 56, "2", 1, &profile.name)
+        .text(16, 88, "1", 1, &clock::now().format("%Y-%m-%d %H:%M").to_string())
+        .barcode_code128(16, 120, 64, "12345678")
+        .print(1);
+    label.build()
+}
+
+// Common widths: 32/42/48 columns in font A, 56/64 in the narrower font B.
+const FONT_A_WIDTHS: [usize; 3] = [32, 42, 48];
+const FONT_B_WIDTHS: [usize; 2] = [56, 64];
+
+// Two-line ruler: tens digits above, unit digits below, counting from column 1.
+fn ruler(width: usize) -> (String, String) {
+    let tens = (1..=width)
+        .map(|col| if col % 10 == 0 { char::from(b'0' + ((col / 10) % 10) as u8) } else { ' ' })
+        .collect();
+    let units = (1..=width).map(|col| char::from(b'0' + (col % 10) as u8)).collect();
+    (tens, units)
+}
+
+// "|---- 48 cols ----|", exactly `width` characters wide.
+fn width_marker(width: usize) -> String {
+    let label = format!(" {} cols ", width);
+    let dashes = width.saturating_sub(label.len() + 2);
+    let left = dashes / 2;
+    format!("|{}{}{}|", "-".repeat(left), label, "-".repeat(dashes - left))
+}
+
+fn calibration_section(page: &mut EscPosBuilder, width: usize, configured: usize) {
+    let (tens, units) = ruler(width);
+    let heading = if width == configured {
+        format!("Width {} (current setting)", width)
+    } else {
+        format!("Width {}", width)
+    };
+    page.bold(width == configured)
+        .line(&heading)
+        .bold(false)
+        .line(&tens)
+        .line(&units)
+        .line(&width_marker(width))
+        .feed(1);
+}
+
+fn calibration_page(profile: &PrinterProfile) -> Vec<u8> {
+    let configured = profile.paper_width as usize;
+    let mut page = EscPosBuilder::for_vendor(vendor::commands(profile.vendor_profile));
+
+    page.align(Align::Center)
+        .bold(true)
+        .line("CALIBRATION")
+        .bold(false)
+        .line(&profile.name)
+        .align(Align::Left)
+        .line("Pick the widest ruler that prints on a single line.")
+        .line(&format!("Current paper width: {} characters", configured))
+        .feed(1)
+        .font(Font::A);
+    for width in FONT_A_WIDTHS {
+        calibration_section(&mut page, width, configured);
+    }
+
+    page.line("Font B:").font(Font::B);
+    for width in FONT_B_WIDTHS {
+        calibration_section(&mut page, width, configured);
+    }
+
+    page.font(Font::A).feed(3).cut();
+    page.build()
+}
+
+#[tauri::command]
+pub async fn print_calibration_page(printer_id: i64, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    let profile = load_profile(&state, printer_id).await?;
+    send(&profile, calibration_page(&profile)).await?;
+    info!("Calibration page printed on {}", profile.name);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn print_test_page(printer_id: i64, state: tauri::State<'_, DbState>) -> Result<(), PrintError> {
+    let profile = load_profile(&state, printer_id).await?;
+    let page = if vendor::commands(profile.vendor_profile).protocol == Protocol::Tspl {
+        test_label(&profile)
+    } else {
+        test_page(&profile)
+    };
+    send(&profile, page).await?;
+    info!("Test page printed on {}", profile.name);
+    Ok(())
+}
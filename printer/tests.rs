@@ -0,0 +1,217 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpListener;
+
+use crate::db::{DbState, PrinterSettings};
+
+use super::config::{self, FallbackRoute, PrinterConfig};
+use super::error::ErrorCode;
+use super::{breaker, database, dispatch_all, memory, migrations, print_payload_to_all_printers, print_with_failover, queue};
+use super::{DeliveredTo, VendorProfile, BOLD_ON, INIT};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+// A fresh database with the plugin's tables, one file per test so they can run in parallel.
+fn test_state() -> DbState {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "printer-test-{}-{}.db",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::SeqCst)
+    ));
+    let _ = std::fs::remove_file(&path);
+    let state = DbState(database::open_pool(&path).expect("open test database"));
+    migrations::run(&database::connection(&state).expect("test connection")).expect("migrate test database");
+    state
+}
+
+// The config is global, so each test holds it for its whole run and puts back whatever it found;
+// a test changing a setting can't leak it into one running alongside.
+struct ConfigGuard {
+    saved: PrinterConfig,
+    _lock: tokio::sync::MutexGuard<'static, ()>,
+}
+
+impl Drop for ConfigGuard {
+    fn drop(&mut self) {
+        let saved = self.saved.clone();
+        config::update(|config| *config = saved);
+    }
+}
+
+async fn isolated_config() -> ConfigGuard {
+    static LOCK: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
+    let lock = LOCK.get_or_init(|| tokio::sync::Mutex::new(())).lock().await;
+    ConfigGuard { saved: config::current(), _lock: lock }
+}
+
+fn ticket(text: &str) -> Vec<u8> {
+    [INIT.as_bytes(), text.as_bytes(), b"\n"].concat()
+}
+
+#[tokio::test]
+async fn dispatch_all_fans_out_to_every_printer() {
+    let _config = isolated_config().await;
+    let names = ["fanout-bar", "fanout-grill", "fanout-counter"];
+    let targets = names
+        .iter()
+        .map(|name| {
+            memory::reset(name);
+            (*name, ticket(name), memory::destination(name), TIMEOUT)
+        })
+        .collect();
+
+    let results = dispatch_all(1, targets).await.expect("dispatch");
+
+    assert_eq!(results.len(), names.len());
+    for ((tag, payload, _, outcome, _), name) in results.iter().zip(names) {
+        assert_eq!(*tag, name, "results come back in input order");
+        assert_eq!(outcome.as_ref().ok(), Some(&DeliveredTo::Primary));
+        assert_eq!(memory::take_written(name), vec![payload.clone()]);
+    }
+}
+
+#[tokio::test]
+async fn print_payload_to_all_printers_sends_to_the_network_printer() {
+    let _config = isolated_config().await;
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind fake printer");
+    let address = listener.local_addr().expect("fake printer address").to_string();
+    let content = ticket("network fan-out");
+    let expected = content.len();
+    let received = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.expect("accept");
+        let mut buf = vec![0; expected];
+        socket.read_exact(&mut buf).await.expect("read job");
+        buf
+    });
+
+    let app = tauri::test::mock_app();
+    let settings: PrinterSettings =
+        serde_json::from_value(serde_json::json!({ "usb_port": "", "network_ip": address, "baud_rate": 0 }))
+            .expect("printer settings");
+    let state = test_state();
+    let result = print_payload_to_all_printers(app.handle(), 1, &content, settings, None, None, None, &state)
+        .await
+        .expect("print");
+
+    assert!(result.usb.is_none());
+    assert!(result.network.as_ref().is_some_and(|network| network.success));
+    assert_eq!(received.await.expect("fake printer task"), content);
+}
+
+#[tokio::test]
+async fn failover_prints_on_the_fallback_with_a_banner() {
+    let _config = isolated_config().await;
+    let (primary, fallback) = ("failover-primary", "failover-backup");
+    memory::reset(primary);
+    memory::reset(fallback);
+    let primary_destination = memory::destination(primary);
+    config::update(|config| {
+        config.fallbacks.insert(
+            primary_destination.key(),
+            FallbackRoute {
+                destination: memory::destination(fallback),
+                label: "Kitchen".to_string(),
+                vendor_profile: VendorProfile::Generic,
+            },
+        );
+    });
+    memory::fail_next(primary, 1, ErrorCode::ConnectionFailed);
+
+    let outcome = print_with_failover(&ticket("failover"), &primary_destination, TIMEOUT, None).await;
+
+    assert_eq!(outcome.ok(), Some(DeliveredTo::Fallback));
+    assert!(memory::take_written(primary).is_empty());
+    let written = memory::take_written(fallback);
+    assert_eq!(written.len(), 1);
+    let banner = format!("{}*** KITCHEN PRINTER DOWN ***", BOLD_ON);
+    assert!(written[0].starts_with(INIT.as_bytes()), "the banner goes after the reset");
+    assert!(String::from_utf8_lossy(&written[0]).contains(&banner));
+}
+
+#[tokio::test]
+async fn timeout_fails_the_job() {
+    let _config = isolated_config().await;
+    let name = "slow-printer";
+    memory::reset(name);
+    memory::set_latency(name, Duration::from_millis(200));
+
+    let outcome = print_with_failover(&ticket("slow"), &memory::destination(name), Duration::from_millis(50), None).await;
+
+    assert_eq!(outcome.err().map(|e| e.code), Some(ErrorCode::Timeout));
+    assert!(memory::take_written(name).is_empty());
+}
+
+#[tokio::test]
+async fn unreachable_job_is_queued_and_retried_on_flush() {
+    let _config = isolated_config().await;
+    let name = "retry-printer";
+    memory::reset(name);
+    let destination = memory::destination(name);
+    let state = test_state();
+    let content = ticket("retry");
+    memory::fail_next(name, 1, ErrorCode::ConnectionFailed);
+
+    let outcome = print_with_failover(&content, &destination, TIMEOUT, None).await;
+    let outcome = queue::queue_if_unreachable(&state, Some(7), &destination, &content, outcome).await;
+    assert_eq!(outcome.ok(), Some(DeliveredTo::Queued));
+    assert!(memory::take_written(name).is_empty());
+
+    let summary = queue::flush(&state, Some(destination.key())).await.expect("flush");
+
+    assert_eq!((summary.printed, summary.remaining), (1, 0));
+    assert_eq!(summary.order_ids, vec![7]);
+    assert_eq!(memory::take_written(name), vec![content]);
+}
+
+#[tokio::test]
+async fn new_job_waits_behind_the_backlog() {
+    let _config = isolated_config().await;
+    let name = "backlog-printer";
+    memory::reset(name);
+    let destination = memory::destination(name);
+    let state = test_state();
+    let (first, second) = (ticket("first"), ticket("second"));
+    memory::fail_next(name, 2, ErrorCode::ConnectionFailed);
+
+    let outcome = print_with_failover(&first, &destination, TIMEOUT, None).await;
+    let outcome = queue::queue_if_unreachable(&state, Some(1), &destination, &first, outcome).await;
+    assert_eq!(outcome.ok(), Some(DeliveredTo::Queued));
+
+    // The backlog is tried first and still fails, so the new ticket is queued behind it
+    let joined = queue::join_backlog(&state, Some(2), &destination, &second).await;
+    assert_eq!(joined.and_then(Result::ok), Some(DeliveredTo::Queued));
+    assert!(memory::take_written(name).is_empty());
+
+    // Once the backlog drains, a new ticket goes straight out
+    let summary = queue::flush(&state, Some(destination.key())).await.expect("flush");
+    assert_eq!(summary.order_ids, vec![1, 2]);
+    assert_eq!(memory::take_written(name), vec![first, second]);
+    assert!(queue::join_backlog(&state, Some(3), &destination, &ticket("third")).await.is_none());
+}
+
+#[tokio::test]
+async fn breaker_opens_after_repeated_failures() {
+    let _config = isolated_config().await;
+    let name = "breaker-printer";
+    memory::reset(name);
+    let destination = memory::destination(name);
+    let threshold = config::current().breaker_failure_threshold.max(1);
+    memory::fail_next(name, threshold as usize, ErrorCode::ConnectionFailed);
+
+    for _ in 0..threshold {
+        assert!(print_with_failover(&ticket("trip"), &destination, TIMEOUT, None).await.is_err());
+    }
+    assert!(breaker::is_open(&destination.key()));
+
+    // The printer would accept this one, but the open breaker never lets it through
+    let outcome = print_with_failover(&ticket("blocked"), &destination, TIMEOUT, None).await;
+    assert!(outcome.is_err_and(|e| e.message.contains("Circuit breaker open")));
+    assert!(memory::take_written(name).is_empty());
+
+    memory::reset(name);
+    assert!(!breaker::is_open(&destination.key()));
+}
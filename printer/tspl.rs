@@ -0,0 +1,130 @@
+// TSPL builder for label printers. Each method appends one command line; coordinates are in dots
+// (8 dots per mm on 203 dpi printers) and sizes in mm, as TSPL expects.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    None,
+    Quarter,
+    Half,
+    ThreeQuarter,
+}
+
+impl Rotation {
+    fn degrees(&self) -> u16 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Quarter => 90,
+            Rotation::Half => 180,
+            Rotation::ThreeQuarter => 270,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TsplBuilder {
+    buf: Vec<u8>,
+    // Applies to text added after it is set
+    rotation: Rotation,
+}
+
+// TSPL strings are double-quoted with no escape syntax; quotes print as \["] instead.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\\[\"]"))
+}
+
+impl TsplBuilder {
+    // Label size in mm, then the gap between labels; CLS starts from an empty image buffer.
+    pub fn new(width_mm: f32, height_mm: f32, gap_mm: f32) -> Self {
+        let mut builder = TsplBuilder::default();
+        builder
+            .command(&format!("SIZE {} mm,{} mm", width_mm, height_mm))
+            .command(&format!("GAP {} mm,0 mm", gap_mm))
+            .command("DIRECTION 1")
+            .command("CLS");
+        builder
+    }
+
+    pub fn command(&mut self, line: &str) -> &mut Self {
+        self.buf.extend_from_slice(line.as_bytes());
+        self.buf.extend_from_slice(b"\r\n");
+        self
+    }
+
+    // 0-15; higher is darker.
+    pub fn density(&mut self, level: u8) -> &mut Self {
+        self.command(&format!("DENSITY {}", level.min(15)))
+    }
+
+    pub fn rotate(&mut self, rotation: Rotation) -> &mut Self {
+        self.rotation = rotation;
+        self
+    }
+
+    // Built-in fonts "1" to "8"; magnification 1-10.
+    pub fn text(&mut self, x: u32, y: u32, font: &str, scale: u8, content: &str) -> &mut Self {
+        let scale = scale.clamp(1, 10);
+        let line = format!(
+            "TEXT {},{},{},{},{},{},{}",
+            x,
+            y,
+            quote(font),
+            self.rotation.degrees(),
+            scale,
+            scale,
+            quote(content)
+        );
+        self.command(&line)
+    }
+
+    // CODE128 with the human readable line centred below the bars.
+    pub fn barcode_code128(&mut self, x: u32, y: u32, height: u32, content: &str) -> &mut Self {
+        self.command(&format!("BARCODE {},{},\"128\",{},2,0,2,2,{}", x, y, height, quote(content)))
+    }
+
+    // Error correction M; cell width 1-10 dots.
+    pub fn qr(&mut self, x: u32, y: u32, cell_width: u8, content: &str) -> &mut Self {
+        self.command(&format!("QRCODE {},{},M,{},A,0,{}", x, y, cell_width.clamp(1, 10), quote(content)))
+    }
+
+    pub fn bar(&mut self, x: u32, y: u32, width: u32, height: u32) -> &mut Self {
+        self.command(&format!("BAR {},{},{},{}", x, y, width, height))
+    }
+
+    // Prints the image buffer `copies` times.
+    pub fn print(&mut self, copies: u16) -> &mut Self {
+        self.command(&format!("PRINT 1,{}", copies.max(1)))
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        self.buf.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_label_sets_up_the_page() {
+        let label = TsplBuilder::new(50.0, 30.0, 2.0).build();
+
+        assert_eq!(label, b"SIZE 50 mm,30 mm\r\nGAP 2 mm,0 mm\r\nDIRECTION 1\r\nCLS\r\n");
+    }
+
+    #[test]
+    fn text_is_quoted_and_rotated() {
+        let mut label = TsplBuilder::default();
+        label.rotate(Rotation::Quarter).text(10, 20, "3", 0, "Say \"hi\"");
+
+        assert_eq!(label.build(), b"TEXT 10,20,\"3\",90,1,1,\"Say \\[\"]hi\\[\"]\"\r\n");
+    }
+
+    #[test]
+    fn arguments_are_clamped() {
+        let mut label = TsplBuilder::default();
+        label.density(40).qr(0, 0, 20, "x").print(0);
+
+        assert_eq!(label.build(), b"DENSITY 15\r\nQRCODE 0,0,M,10,A,0,\"x\"\r\nPRINT 1,1\r\n");
+    }
+}
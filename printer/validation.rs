@@ -0,0 +1,136 @@
+use serde::Serialize;
+use std::net::ToSocketAddrs;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use crate::db::PrinterSettings;
+
+use super::error::{ErrorCode, PrintError};
+use super::profile::PrinterProfile;
+use super::{driver, emulator, ipp, lock_destination, lpr, settings_errors, spooler, Destination};
+
+const REACHABILITY_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationIssue {
+    pub field: String,
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub issues: Vec<ValidationIssue>,
+}
+
+fn issue(field: &str, code: ErrorCode, message: impl Into<String>) -> ValidationIssue {
+    ValidationIssue { field: field.to_string(), code, message: message.into() }
+}
+
+async fn check_network_address(address: &str) -> Vec<ValidationIssue> {
+    let Some((host, port)) = address.rsplit_once(':') else {
+        return vec![issue("network_ip", ErrorCode::InvalidSettings, format!("{} is missing a port, e.g. 192.168.1.50:9100", address))];
+    };
+    if host.is_empty() {
+        return vec![issue("network_ip", ErrorCode::InvalidSettings, "Host is empty")];
+    }
+    if !matches!(port.parse::<u16>(), Ok(p) if p > 0) {
+        return vec![issue("network_ip", ErrorCode::InvalidSettings, format!("{} is not a valid port", port))];
+    }
+    if address.to_socket_addrs().map(|mut addrs| addrs.next().is_none()).unwrap_or(true) {
+        return vec![issue("network_ip", ErrorCode::PortNotFound, format!("Could not resolve {}", host))];
+    }
+
+    // Some printers only take one connection at a time, so wait for any running job first
+    let _guard = lock_destination(format!("network:{}", address)).await;
+    match timeout(REACHABILITY_TIMEOUT, TcpStream::connect(address)).await {
+        Ok(Ok(_)) => Vec::new(),
+        Ok(Err(e)) => {
+            let e = PrintError::from_io(&format!("Could not connect to {}", address), &e);
+            vec![issue("network_ip", e.code, e.message)]
+        }
+        Err(_) => vec![issue("network_ip", ErrorCode::Timeout, format!("{} did not answer within {}s", address, REACHABILITY_TIMEOUT.as_secs()))],
+    }
+}
+
+fn check_usb_port(port: &str, baud_rate: u32) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+
+    let is_serial = serialport::available_ports()
+        .map(|ports| ports.iter().any(|p| p.port_name.eq_ignore_ascii_case(port)))
+        .unwrap_or(false);
+    if is_serial {
+        if baud_rate == 0 {
+            issues.push(issue("baud_rate", ErrorCode::InvalidSettings, "Serial printers need a baud rate"));
+        }
+        return issues;
+    }
+
+    match spooler::installed_printers() {
+        Ok(printers) if printers.iter().any(|p| p.eq_ignore_ascii_case(port)) => {}
+        Ok(_) => issues.push(issue("usb_port", ErrorCode::PortNotFound, format!("No installed printer or COM port named {}", port))),
+        Err(e) => issues.push(issue("usb_port", e.code, e.message)),
+    }
+    issues
+}
+
+pub(crate) async fn check_destination(destination: &Destination) -> Vec<ValidationIssue> {
+    match destination {
+        Destination::Usb { port, baud_rate, .. } => check_usb_port(port, *baud_rate),
+        Destination::Network { address } => check_network_address(address).await,
+        Destination::Ipp { url } => match ipp::socket_address(url) {
+            Ok(address) => check_network_address(&address).await,
+            Err(e) => vec![issue("network_ip", e.code, e.message)],
+        },
+        Destination::Lpr { address } => match lpr::socket_address(address) {
+            Ok(address) => check_network_address(&address).await,
+            Err(e) => vec![issue("network_ip", e.code, e.message)],
+        },
+        Destination::Virtual { directory, .. } => match emulator::check_directory(directory) {
+            Ok(()) => Vec::new(),
+            Err(e) => vec![issue("address", e.code, e.message)],
+        },
+        Destination::Driver { driver, address } => match driver::get(driver).and_then(|d| d.check(address)) {
+            Ok(()) => Vec::new(),
+            Err(e) => vec![issue("address", e.code, e.message)],
+        },
+    }
+}
+
+fn report(issues: Vec<ValidationIssue>) -> ValidationReport {
+    ValidationReport { valid: issues.is_empty(), issues }
+}
+
+// Checks either the legacy settings blob or a printer profile before it is saved.
+#[tauri::command]
+pub async fn validate_printer_settings(
+    printer_settings: Option<PrinterSettings>,
+    profile: Option<PrinterProfile>,
+) -> Result<ValidationReport, PrintError> {
+    let mut issues = Vec::new();
+
+    if let Some(settings) = printer_settings {
+        issues.extend(settings_errors(&settings).into_iter().map(|e| issue("settings", ErrorCode::InvalidSettings, e)));
+        if !settings.usb_port.is_empty() {
+            issues.extend(check_usb_port(&settings.usb_port, settings.baud_rate));
+        }
+        if !settings.network_ip.is_empty() {
+            issues.extend(check_network_address(&settings.network_ip).await);
+        }
+    }
+
+    if let Some(profile) = profile {
+        if profile.name.trim().is_empty() {
+            issues.push(issue("name", ErrorCode::InvalidSettings, "Printer name cannot be empty"));
+        }
+        if profile.address.trim().is_empty() {
+            issues.push(issue("address", ErrorCode::InvalidSettings, "Printer address cannot be empty"));
+        } else {
+            issues.extend(check_destination(&profile.destination()).await);
+        }
+    }
+
+    Ok(report(issues))
+}
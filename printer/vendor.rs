@@ -0,0 +1,253 @@
+use rusqlite::types::ToSqlOutput;
+use rusqlite::ToSql;
+use serde::{Deserialize, Serialize};
+
+use crate::db::DbState;
+
+use super::config;
+use super::error::{ErrorCode, PrintError};
+use super::snippets;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VendorProfile {
+    #[default]
+    Generic,
+    Epson,
+    Star,
+    // Star printers left in their native Star Line Mode rather than ESC/POS emulation
+    StarLine,
+    Xprinter,
+    Rongta,
+    // Budget label printers that speak TSPL instead of ESC/POS
+    Tspl,
+}
+
+impl VendorProfile {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            VendorProfile::Generic => "generic",
+            VendorProfile::Epson => "epson",
+            VendorProfile::Star => "star",
+            VendorProfile::StarLine => "star_line",
+            VendorProfile::Xprinter => "xprinter",
+            VendorProfile::Rongta => "rongta",
+            VendorProfile::Tspl => "tspl",
+        }
+    }
+
+    pub(crate) fn parse(value: &str) -> Self {
+        match value {
+            "epson" => VendorProfile::Epson,
+            "star" => VendorProfile::Star,
+            "star_line" => VendorProfile::StarLine,
+            "xprinter" => VendorProfile::Xprinter,
+            "rongta" => VendorProfile::Rongta,
+            "tspl" => VendorProfile::Tspl,
+            _ => VendorProfile::Generic,
+        }
+    }
+}
+
+impl ToSql for VendorProfile {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+// The command set the printer speaks; everything except cut, drawer and density follows from it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    #[default]
+    EscPos,
+    StarLine,
+    // Documents go through TsplBuilder instead of EscPosBuilder
+    Tspl,
+}
+
+// The drawer kick-out connector has two solenoid outputs, so one printer can run two drawers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawerPin {
+    #[default]
+    Pin2,
+    Pin5,
+}
+
+// Builder operations whose bytes differ between printer makers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VendorOperation {
+    Cut,
+    PartialCut,
+    OpenDrawer,
+    DensityLight,
+    DensityNormal,
+    DensityDark,
+}
+
+// Resolved sequences for one vendor. An empty sequence means the model has no such command.
+#[derive(Debug, Clone, Serialize)]
+pub struct VendorCommands {
+    pub protocol: Protocol,
+    pub cut: Vec<u8>,
+    pub partial_cut: Vec<u8>,
+    pub open_drawer: Vec<u8>,
+    pub density_light: Vec<u8>,
+    pub density_normal: Vec<u8>,
+    pub density_dark: Vec<u8>,
+}
+
+impl Default for VendorCommands {
+    fn default() -> Self {
+        built_in(VendorProfile::Generic)
+    }
+}
+
+impl VendorCommands {
+    // ESC E n on ESC/POS; Star Line Mode has ESC E and ESC F without an argument, and TSPL no bold.
+    pub fn bold(&self, on: bool) -> &'static [u8] {
+        match (self.protocol, on) {
+            (Protocol::EscPos, true) => b"\x1B\x45\x01",
+            (Protocol::EscPos, false) => b"\x1B\x45\x00",
+            (Protocol::StarLine, true) => b"\x1B\x45",
+            (Protocol::StarLine, false) => b"\x1B\x46",
+            (Protocol::Tspl, _) => b"",
+        }
+    }
+
+    // The open-drawer sequence retargeted at `pin`. ESC p takes the pin as its first argument;
+    // Star Line Mode fires drawer 1 with BEL and drawer 2 with SUB.
+    pub fn open_drawer_on(&self, pin: DrawerPin) -> Vec<u8> {
+        let mut sequence = self.open_drawer.clone();
+        if pin == DrawerPin::Pin2 {
+            return sequence;
+        }
+        match sequence.as_mut_slice() {
+            [0x1B, 0x70, m, ..] => *m = 0x01,
+            [0x07] => sequence = vec![0x1A],
+            _ => {}
+        }
+        sequence
+    }
+
+    fn get_mut(&mut self, operation: VendorOperation) -> &mut Vec<u8> {
+        match operation {
+            VendorOperation::Cut => &mut self.cut,
+            VendorOperation::PartialCut => &mut self.partial_cut,
+            VendorOperation::OpenDrawer => &mut self.open_drawer,
+            VendorOperation::DensityLight => &mut self.density_light,
+            VendorOperation::DensityNormal => &mut self.density_normal,
+            VendorOperation::DensityDark => &mut self.density_dark,
+        }
+    }
+}
+
+fn built_in(vendor: VendorProfile) -> VendorCommands {
+    let drawer = vec![0x1B, 0x70, 0x00, 0x19, 0xFA];
+    match vendor {
+        VendorProfile::Generic => VendorCommands {
+            protocol: Protocol::EscPos,
+            cut: vec![0x1D, 0x56, 0x41, 0x00],
+            partial_cut: vec![0x1D, 0x56, 0x42, 0x00],
+            open_drawer: drawer,
+            density_light: Vec::new(),
+            density_normal: Vec::new(),
+            density_dark: Vec::new(),
+        },
+        // GS ( K function 49: density -6..+6, sent as a signed byte
+        VendorProfile::Epson => VendorCommands {
+            protocol: Protocol::EscPos,
+            cut: vec![0x1D, 0x56, 0x41, 0x00],
+            partial_cut: vec![0x1D, 0x56, 0x42, 0x00],
+            open_drawer: drawer,
+            density_light: vec![0x1D, 0x28, 0x4B, 0x02, 0x00, 0x31, 0xFD],
+            density_normal: vec![0x1D, 0x28, 0x4B, 0x02, 0x00, 0x31, 0x00],
+            density_dark: vec![0x1D, 0x28, 0x4B, 0x02, 0x00, 0x31, 0x03],
+        },
+        // ESC/POS emulation; density is ESC RS d n with 3 as standard and lower values darker
+        VendorProfile::Star => VendorCommands {
+            protocol: Protocol::EscPos,
+            cut: vec![0x1D, 0x56, 0x00],
+            partial_cut: vec![0x1D, 0x56, 0x01],
+            open_drawer: drawer,
+            density_light: vec![0x1B, 0x1E, 0x64, 0x05],
+            density_normal: vec![0x1B, 0x1E, 0x64, 0x03],
+            density_dark: vec![0x1B, 0x1E, 0x64, 0x01],
+        },
+        // ESC d 2/3 feed to the cutter then cut; BEL fires drawer 1
+        VendorProfile::StarLine => VendorCommands {
+            protocol: Protocol::StarLine,
+            cut: vec![0x1B, 0x64, 0x02],
+            partial_cut: vec![0x1B, 0x64, 0x03],
+            open_drawer: vec![0x07],
+            density_light: vec![0x1B, 0x1E, 0x64, 0x05],
+            density_normal: vec![0x1B, 0x1E, 0x64, 0x03],
+            density_dark: vec![0x1B, 0x1E, 0x64, 0x01],
+        },
+        // Most budget models ignore GS V A and only honour the short form
+        VendorProfile::Xprinter | VendorProfile::Rongta => VendorCommands {
+            protocol: Protocol::EscPos,
+            cut: vec![0x1D, 0x56, 0x00],
+            partial_cut: vec![0x1D, 0x56, 0x01],
+            open_drawer: drawer,
+            density_light: vec![0x1D, 0x7C, 0x02],
+            density_normal: vec![0x1D, 0x7C, 0x04],
+            density_dark: vec![0x1D, 0x7C, 0x06],
+        },
+        // Whole command lines; labels have no drawer
+        VendorProfile::Tspl => VendorCommands {
+            protocol: Protocol::Tspl,
+            cut: b"CUT\r\n".to_vec(),
+            partial_cut: b"CUT\r\n".to_vec(),
+            open_drawer: Vec::new(),
+            density_light: b"DENSITY 4\r\n".to_vec(),
+            density_normal: b"DENSITY 8\r\n".to_vec(),
+            density_dark: b"DENSITY 12\r\n".to_vec(),
+        },
+    }
+}
+
+// Built-in sequences with the user's overrides for this vendor applied on top.
+pub fn commands(vendor: VendorProfile) -> VendorCommands {
+    let mut commands = built_in(vendor);
+    if let Some(overrides) = config::current().vendor_overrides.get(&vendor) {
+        for (operation, hex) in overrides {
+            match snippets::parse_hex(hex) {
+                Ok(bytes) => *commands.get_mut(*operation) = bytes,
+                Err(e) => warn!("Ignoring {:?} override for {}: {}", operation, vendor.as_str(), e),
+            }
+        }
+    }
+    commands
+}
+
+#[tauri::command]
+pub fn get_vendor_commands(vendor: VendorProfile) -> VendorCommands {
+    commands(vendor)
+}
+
+// `None` restores the built-in sequence.
+#[tauri::command]
+pub fn set_vendor_override(
+    vendor: VendorProfile,
+    operation: VendorOperation,
+    hex: Option<String>,
+    state: tauri::State<'_, DbState>,
+) -> Result<(), PrintError> {
+    if let Some(hex) = &hex {
+        snippets::parse_hex(hex).map_err(|e| PrintError::new(ErrorCode::InvalidSettings, e))?;
+    }
+    config::update_persisted(&state, |config| {
+        let overrides = config.vendor_overrides.entry(vendor).or_default();
+        match hex {
+            Some(hex) => {
+                overrides.insert(operation, hex);
+            }
+            None => {
+                overrides.remove(&operation);
+            }
+        }
+    })
+}
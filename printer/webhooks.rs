@@ -0,0 +1,125 @@
+// Pings the owner's chat when something needs a person: a job that failed for good (not one
+// waiting in the offline queue) or a printer the status monitor saw go offline. Each body is
+// JSON with a ready-made `text` line, which Slack-style incoming webhooks show as is, and is
+// signed with the hook's secret as `X-Printer-Signature: sha256=<hex HMAC of the body>` so a
+// relay can check it came from a till.
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::fmt::Write as _;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use super::config::{self, Webhook};
+use super::error::{ErrorCode, PrintError};
+
+const SIGNATURE_HEADER: &str = "X-Printer-Signature";
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    JobFailed,
+    PrinterOffline,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Notification {
+    event: WebhookEvent,
+    text: String,
+    terminal_id: String,
+    printer: String,
+    order_id: Option<i64>,
+    error: Option<PrintError>,
+    occurred_at: String,
+}
+
+// Chat webhook URLs carry their token in the path, so only the host goes into logs and bundles.
+pub(crate) fn redact_url(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return "<redacted>".to_string();
+    };
+    let host = rest.split(['/', '?']).next().unwrap_or_default();
+    let host = host.rsplit('@').next().unwrap_or_default();
+    format!("{}://{}/<redacted>", scheme, host)
+}
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| reqwest::Client::builder().timeout(SEND_TIMEOUT).build().unwrap_or_default())
+}
+
+fn sign(secret: &str, body: &[u8]) -> String {
+    // HMAC takes keys of any length, so this can't fail
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC key");
+    mac.update(body);
+    let mut signature = String::from("sha256=");
+    for byte in mac.finalize().into_bytes() {
+        let _ = write!(signature, "{:02x}", byte);
+    }
+    signature
+}
+
+async fn send(hook: &Webhook, body: &[u8]) -> Result<(), PrintError> {
+    let mut request = client().post(&hook.url).header("Content-Type", "application/json").body(body.to_vec());
+    if !hook.secret.is_empty() {
+        request = request.header(SIGNATURE_HEADER, sign(&hook.secret, body));
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| PrintError::new(ErrorCode::ConnectionFailed, format!("Webhook request failed: {}", e)))?;
+    if !response.status().is_success() {
+        return Err(PrintError::new(ErrorCode::Unknown, format!("Webhook answered {}", response.status())));
+    }
+    Ok(())
+}
+
+// Fire and forget: a chat service being down must never hold up printing.
+fn notify(notification: Notification) {
+    let config = config::current();
+    // Training failures are staged on purpose; nobody should be woken up for them
+    if config.webhooks.is_empty() || config.simulation_mode || config.dry_run {
+        return;
+    }
+    let body = match serde_json::to_vec(&notification) {
+        Ok(body) => body,
+        Err(e) => {
+            error!("Could not encode webhook notification: {}", e);
+            return;
+        }
+    };
+    for hook in config.webhooks {
+        let body = body.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = send(&hook, &body).await {
+                warn!("Webhook to {} failed: {}", redact_url(&hook.url), e);
+            }
+        });
+    }
+}
+
+pub(crate) fn job_failed(order_id: i64, printer: &str, error: &PrintError) {
+    notify(Notification {
+        event: WebhookEvent::JobFailed,
+        text: format!("[{}] Order {} did not print on {}: {}", config::terminal_id(), order_id, printer, error),
+        terminal_id: config::terminal_id(),
+        printer: printer.to_string(),
+        order_id: Some(order_id),
+        error: Some(error.clone()),
+        occurred_at: chrono::Utc::now().to_rfc3339(),
+    });
+}
+
+pub(crate) fn printer_offline(printer: &str) {
+    notify(Notification {
+        event: WebhookEvent::PrinterOffline,
+        text: format!("[{}] Printer {} went offline", config::terminal_id(), printer),
+        terminal_id: config::terminal_id(),
+        printer: printer.to_string(),
+        order_id: None,
+        error: None,
+        occurred_at: chrono::Utc::now().to_rfc3339(),
+    });
+}
@@ -0,0 +1,437 @@
+// Safe wrappers over the winspool and GDI calls the plugin makes. Every handle releases itself on
+// drop (ClosePrinter, EndDocPrinter, DeleteDC, ...), so an early return can't leak a printer
+// handle or leave a document open in the spooler. All `unsafe` for Windows printing lives here.
+
+use std::ffi::c_void;
+use std::ptr;
+use windows::core::{HSTRING, PCWSTR, PWSTR};
+use windows::Win32::Foundation::{GetLastError, COLORREF, HANDLE, RECT, WAIT_OBJECT_0};
+use windows::Win32::Graphics::Gdi::{
+    CreateDCW, CreateFontW, CreatePen, CreateSolidBrush, DeleteDC, DeleteObject, FillRect, GetDeviceCaps,
+    GetTextMetricsW, LineTo, MoveToEx, SelectObject, SetBkMode, SetTextAlign, TextOutW, CLIP_DEFAULT_PRECIS,
+    DEFAULT_CHARSET, DEFAULT_QUALITY, GET_DEVICE_CAPS_INDEX, HBRUSH, HDC, HGDIOBJ, OUT_DEFAULT_PRECIS, PS_SOLID,
+    TA_BASELINE, TA_LEFT, TEXTMETRICW, TRANSPARENT,
+};
+use windows::Win32::Graphics::Printing::{
+    ClosePrinter, EndDocPrinter, EnumPrintersW, FindClosePrinterChangeNotification, FindFirstPrinterChangeNotification,
+    FindNextPrinterChangeNotification, GetDefaultPrinterW, GetJobW, GetPrinterW, OpenPrinterW, SetDefaultPrinterW,
+    SetJobW, SetPrinterW, StartDocPrinterW, WritePrinter, DOC_INFO_1W, JOB_CONTROL_DELETE, JOB_INFO_1W,
+    PRINTER_ACCESS_ADMINISTER, PRINTER_DEFAULTSW, PRINTER_ENUM_CONNECTIONS, PRINTER_ENUM_LOCAL, PRINTER_HANDLE,
+    PRINTER_INFO_4W, PRINTER_INFO_6,
+};
+use windows::Win32::Storage::Xps::{AbortDoc, EndDoc, EndPage, StartDocW, StartPage, DOCINFOW};
+use windows::Win32::System::Threading::WaitForSingleObject;
+
+use super::error::{ErrorCode, PrintError};
+
+// GetJob fails with this once a finished job has been removed from the queue
+const ERROR_INVALID_PARAMETER: u32 = 87;
+const ERROR_FILE_NOT_FOUND: u32 = 2;
+
+fn last_error() -> u32 {
+    unsafe { GetLastError() }.0
+}
+
+fn fail(context: &str) -> PrintError {
+    PrintError::from_win32(context, last_error())
+}
+
+// Calls that return a windows::core::Result carry the Win32 code in the low word of the HRESULT.
+fn from_result(context: &str, e: windows::core::Error) -> PrintError {
+    PrintError::from_win32(context, (e.code().0 & 0xFFFF) as u32)
+}
+
+fn wide(text: &str) -> Result<HSTRING, PrintError> {
+    if text.contains('\0') {
+        return Err(PrintError::new(ErrorCode::InvalidSettings, format!("Invalid printer name: {:?}", text)));
+    }
+    Ok(HSTRING::from(text))
+}
+
+// Reads a NUL-terminated UTF-16 string the spooler handed back.
+fn from_wide(text: PWSTR) -> String {
+    if text.is_null() {
+        return String::new();
+    }
+    unsafe { text.to_string() }.unwrap_or_default()
+}
+
+// u64 backing keeps the spooler's structs pointer-aligned.
+fn aligned_buffer(size: u32) -> Vec<u64> {
+    vec![0u64; (size as usize).div_ceil(8)]
+}
+
+fn as_bytes(buffer: &mut [u64]) -> &mut [u8] {
+    unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, buffer.len() * 8) }
+}
+
+pub(crate) struct Printer(PRINTER_HANDLE);
+
+impl Printer {
+    // `None` opens the local print server, which sees every queue.
+    pub(crate) fn open(name: Option<&str>) -> Result<Self, PrintError> {
+        Self::open_with(name, None)
+    }
+
+    // Queue-wide control (purge, pause) needs an administer handle rather than the default use access.
+    pub(crate) fn open_for_admin(name: &str) -> Result<Self, PrintError> {
+        let defaults = PRINTER_DEFAULTSW {
+            pDatatype: PWSTR::null(),
+            pDevMode: ptr::null_mut(),
+            DesiredAccess: PRINTER_ACCESS_ADMINISTER,
+        };
+        Self::open_with(Some(name), Some(&defaults))
+    }
+
+    fn open_with(name: Option<&str>, defaults: Option<&PRINTER_DEFAULTSW>) -> Result<Self, PrintError> {
+        let name = name.map(wide).transpose()?;
+        let name = name.as_ref().map_or(PCWSTR::null(), |n| PCWSTR(n.as_ptr()));
+        let mut handle = PRINTER_HANDLE::default();
+        unsafe { OpenPrinterW(name, &mut handle, defaults.map(|d| d as *const _)) }
+            .map_err(|e| from_result("OpenPrinter", e))?;
+        Ok(Printer(handle))
+    }
+
+    pub(crate) fn control(&self, command: u32, action: &str) -> Result<(), PrintError> {
+        if !unsafe { SetPrinterW(self.0, 0, None, command) }.as_bool() {
+            return Err(fail(action));
+        }
+        Ok(())
+    }
+
+    // Starts a RAW job; the bytes go to the printer untouched by the driver.
+    pub(crate) fn start_raw_document(&self, document_name: &str) -> Result<RawDocument<'_>, PrintError> {
+        let doc_name = wide(document_name)?;
+        let data_type = HSTRING::from("RAW");
+        let doc_info = DOC_INFO_1W {
+            pDocName: PWSTR(doc_name.as_ptr() as *mut _),
+            pOutputFile: PWSTR::null(),
+            pDatatype: PWSTR(data_type.as_ptr() as *mut _),
+        };
+        let job_id = unsafe { StartDocPrinterW(self.0, 1, &doc_info as *const _ as *const _) };
+        if job_id == 0 {
+            return Err(fail("StartDocPrinter"));
+        }
+        Ok(RawDocument { printer: self, job_id })
+    }
+
+    // The JOB_STATUS_* flags of a job, or `None` once it has left the queue.
+    pub(crate) fn job_status(&self, job_id: u32) -> Result<Option<u32>, PrintError> {
+        let gone_or_error = |error: u32| {
+            if error == ERROR_INVALID_PARAMETER {
+                Ok(None)
+            } else {
+                Err(PrintError::from_win32("GetJob", error))
+            }
+        };
+
+        let mut needed = 0;
+        unsafe {
+            let _ = GetJobW(self.0, job_id, 1, None, &mut needed);
+        }
+        if needed == 0 {
+            return gone_or_error(last_error());
+        }
+
+        let mut buffer = aligned_buffer(needed);
+        if !unsafe { GetJobW(self.0, job_id, 1, Some(as_bytes(&mut buffer)), &mut needed) }.as_bool() {
+            return gone_or_error(last_error());
+        }
+        let info = unsafe { &*(buffer.as_ptr() as *const JOB_INFO_1W) };
+        Ok(Some(info.Status))
+    }
+
+    pub(crate) fn cancel_job(&self, job_id: u32) -> Result<(), PrintError> {
+        if !unsafe { SetJobW(self.0, job_id, 0, None, JOB_CONTROL_DELETE.0) }.as_bool() {
+            return Err(fail("SetJob(delete)"));
+        }
+        Ok(())
+    }
+
+    // PRINTER_STATUS_* flags of the queue.
+    pub(crate) fn status(&self) -> Result<u32, PrintError> {
+        let mut buffer = aligned_buffer(std::mem::size_of::<PRINTER_INFO_6>() as u32);
+        let mut needed = 0;
+        if !unsafe { GetPrinterW(self.0, 6, Some(as_bytes(&mut buffer)), &mut needed) }.as_bool() {
+            return Err(fail("GetPrinter"));
+        }
+        let info = unsafe { &*(buffer.as_ptr() as *const PRINTER_INFO_6) };
+        Ok(info.dwStatus.0)
+    }
+
+    pub(crate) fn watch(&self, filter: u32) -> Result<ChangeNotification<'_>, PrintError> {
+        let handle = unsafe { FindFirstPrinterChangeNotification(self.0, filter, 0, None) }
+            .map_err(|e| from_result("FindFirstPrinterChangeNotification", e))?;
+        Ok(ChangeNotification { _printer: self, handle })
+    }
+}
+
+impl Drop for Printer {
+    fn drop(&mut self) {
+        let _ = unsafe { ClosePrinter(self.0) };
+    }
+}
+
+// An open spooler document. Dropping it ends the document, so whatever was written is released
+// to the printer even when a later write fails.
+pub(crate) struct RawDocument<'a> {
+    printer: &'a Printer,
+    job_id: u32,
+}
+
+impl RawDocument<'_> {
+    pub(crate) fn job_id(&self) -> u32 {
+        self.job_id
+    }
+
+    pub(crate) fn write(&self, content: &[u8]) -> Result<(), PrintError> {
+        let mut written = 0;
+        let ok = unsafe {
+            WritePrinter(self.printer.0, content.as_ptr() as *const c_void, content.len() as u32, &mut written)
+        };
+        if !ok.as_bool() {
+            return Err(fail("WritePrinter"));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RawDocument<'_> {
+    fn drop(&mut self) {
+        let _ = unsafe { EndDocPrinter(self.printer.0) };
+    }
+}
+
+// Borrows the print server handle it was created from, so it can't outlive it.
+pub(crate) struct ChangeNotification<'a> {
+    _printer: &'a Printer,
+    handle: HANDLE,
+}
+
+impl ChangeNotification<'_> {
+    // `true` once something changed within `wait_ms`; the notification is re-armed.
+    pub(crate) fn wait(&self, wait_ms: u32) -> Result<bool, PrintError> {
+        if unsafe { WaitForSingleObject(self.handle, wait_ms) } != WAIT_OBJECT_0 {
+            return Ok(false);
+        }
+        let mut cause = 0;
+        if !unsafe { FindNextPrinterChangeNotification(self.handle, Some(&mut cause), None, None) }.as_bool() {
+            return Err(fail("FindNextPrinterChangeNotification"));
+        }
+        Ok(true)
+    }
+}
+
+impl Drop for ChangeNotification<'_> {
+    fn drop(&mut self) {
+        let _ = unsafe { FindClosePrinterChangeNotification(self.handle) };
+    }
+}
+
+// Names of every printer the spooler knows about, local and network connections.
+pub(crate) fn installed_printers() -> Result<Vec<String>, PrintError> {
+    let flags = PRINTER_ENUM_LOCAL | PRINTER_ENUM_CONNECTIONS;
+    let mut needed = 0;
+    let mut returned = 0;
+
+    // First call only reports the buffer size we need
+    let _ = unsafe { EnumPrintersW(flags, PCWSTR::null(), 4, None, &mut needed, &mut returned) };
+    if needed == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut buffer = aligned_buffer(needed);
+    unsafe { EnumPrintersW(flags, PCWSTR::null(), 4, Some(as_bytes(&mut buffer)), &mut needed, &mut returned) }
+        .map_err(|e| from_result("EnumPrinters", e))?;
+    let infos = unsafe { std::slice::from_raw_parts(buffer.as_ptr() as *const PRINTER_INFO_4W, returned as usize) };
+    Ok(infos.iter().filter(|info| !info.pPrinterName.is_null()).map(|info| from_wide(info.pPrinterName)).collect())
+}
+
+// `None` when Windows has no default printer set.
+pub(crate) fn default_printer() -> Result<Option<String>, PrintError> {
+    let mut size = 0;
+    let _ = unsafe { GetDefaultPrinterW(PWSTR::null(), &mut size) };
+    if size == 0 {
+        return match last_error() {
+            ERROR_FILE_NOT_FOUND => Ok(None),
+            error => Err(PrintError::from_win32("GetDefaultPrinter", error)),
+        };
+    }
+
+    let mut buffer = vec![0u16; size as usize];
+    if !unsafe { GetDefaultPrinterW(PWSTR(buffer.as_mut_ptr()), &mut size) }.as_bool() {
+        return Err(fail("GetDefaultPrinter"));
+    }
+    Ok(Some(from_wide(PWSTR(buffer.as_mut_ptr()))))
+}
+
+pub(crate) fn set_default_printer(name: &str) -> Result<(), PrintError> {
+    let name = wide(name)?;
+    if !unsafe { SetDefaultPrinterW(PCWSTR(name.as_ptr())) }.as_bool() {
+        return Err(fail("SetDefaultPrinter"));
+    }
+    Ok(())
+}
+
+// A printer device context. A document still open when it is dropped is aborted rather than
+// half-printed, and the DC is deleted.
+pub(crate) struct DeviceContext {
+    hdc: HDC,
+    printing: bool,
+}
+
+impl DeviceContext {
+    pub(crate) fn create(printer_name: &str) -> Result<Self, PrintError> {
+        let driver = HSTRING::from("WINSPOOL");
+        let name = wide(printer_name)?;
+        let hdc = unsafe { CreateDCW(PCWSTR(driver.as_ptr()), PCWSTR(name.as_ptr()), PCWSTR::null(), None) };
+        if hdc.is_invalid() {
+            return Err(fail("CreateDC"));
+        }
+        Ok(DeviceContext { hdc, printing: false })
+    }
+
+    pub(crate) fn caps(&self, index: GET_DEVICE_CAPS_INDEX) -> i32 {
+        unsafe { GetDeviceCaps(self.hdc, index) }
+    }
+
+    pub(crate) fn start_document(&mut self, document_name: &str) -> Result<(), PrintError> {
+        let doc_name = wide(document_name)?;
+        let doc_info = DOCINFOW {
+            cbSize: std::mem::size_of::<DOCINFOW>() as i32,
+            lpszDocName: PCWSTR(doc_name.as_ptr()),
+            ..Default::default()
+        };
+        if unsafe { StartDocW(self.hdc, &doc_info) } <= 0 {
+            return Err(fail("StartDoc"));
+        }
+        self.printing = true;
+        Ok(())
+    }
+
+    pub(crate) fn end_document(&mut self) -> Result<(), PrintError> {
+        self.printing = false;
+        if unsafe { EndDoc(self.hdc) } <= 0 {
+            return Err(fail("EndDoc"));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn start_page(&self) {
+        unsafe { StartPage(self.hdc) };
+    }
+
+    // Text is positioned by its baseline and drawn without a background box.
+    pub(crate) fn align_to_baseline(&self) {
+        unsafe {
+            SetTextAlign(self.hdc, TA_BASELINE | TA_LEFT);
+            SetBkMode(self.hdc, TRANSPARENT);
+        }
+    }
+
+    pub(crate) fn end_page(&self) {
+        unsafe { EndPage(self.hdc) };
+    }
+
+    // Selects `object` until the returned guard drops, then restores what was selected before.
+    pub(crate) fn select<'a>(&'a self, object: &'a GdiObject) -> Selection<'a> {
+        let previous = unsafe { SelectObject(self.hdc, object.0) };
+        Selection { dc: self, previous }
+    }
+
+    // Height plus external leading of the selected font.
+    pub(crate) fn line_height(&self) -> i32 {
+        let mut metrics = TEXTMETRICW::default();
+        unsafe {
+            let _ = GetTextMetricsW(self.hdc, &mut metrics);
+        }
+        metrics.tmHeight + metrics.tmExternalLeading
+    }
+
+    pub(crate) fn text(&self, x: i32, y: i32, text: &str) {
+        let wide: Vec<u16> = text.encode_utf16().collect();
+        unsafe {
+            let _ = TextOutW(self.hdc, x, y, &wide);
+        }
+    }
+
+    pub(crate) fn line(&self, x1: i32, y1: i32, x2: i32, y2: i32) {
+        unsafe {
+            let _ = MoveToEx(self.hdc, x1, y1, None);
+            let _ = LineTo(self.hdc, x2, y2);
+        }
+    }
+
+    pub(crate) fn fill_rect(&self, rect: RECT, brush: &GdiObject) {
+        unsafe { FillRect(self.hdc, &rect, HBRUSH(brush.0 .0)) };
+    }
+}
+
+impl Drop for DeviceContext {
+    fn drop(&mut self) {
+        unsafe {
+            if self.printing {
+                AbortDoc(self.hdc);
+            }
+            let _ = DeleteDC(self.hdc);
+        }
+    }
+}
+
+// A font, pen or brush, deleted on drop. Keep it alive longer than any Selection of it.
+pub(crate) struct GdiObject(HGDIOBJ);
+
+impl GdiObject {
+    pub(crate) fn font(height: i32, weight: i32, pitch_and_family: u32, face: &str) -> Self {
+        let face = HSTRING::from(face);
+        let font = unsafe {
+            CreateFontW(
+                height,
+                0,
+                0,
+                0,
+                weight,
+                0,
+                0,
+                0,
+                DEFAULT_CHARSET,
+                OUT_DEFAULT_PRECIS,
+                CLIP_DEFAULT_PRECIS,
+                DEFAULT_QUALITY,
+                pitch_and_family,
+                PCWSTR(face.as_ptr()),
+            )
+        };
+        GdiObject(font.into())
+    }
+
+    pub(crate) fn pen(width: i32, color: COLORREF) -> Self {
+        GdiObject(unsafe { CreatePen(PS_SOLID, width, color) }.into())
+    }
+
+    pub(crate) fn brush(color: COLORREF) -> Self {
+        GdiObject(unsafe { CreateSolidBrush(color) }.into())
+    }
+}
+
+impl Drop for GdiObject {
+    fn drop(&mut self) {
+        let _ = unsafe { DeleteObject(self.0) };
+    }
+}
+
+pub(crate) struct Selection<'a> {
+    dc: &'a DeviceContext,
+    previous: HGDIOBJ,
+}
+
+impl Drop for Selection<'_> {
+    fn drop(&mut self) {
+        unsafe { SelectObject(self.dc.hdc, self.previous) };
+    }
+}
+
+pub(crate) fn gray(level: u8) -> COLORREF {
+    let level = level as u32;
+    COLORREF(level | (level << 8) | (level << 16))
+}